@@ -1,17 +1,21 @@
 //! This crate contains most of the internal implementation of the macros in the
 //! `macro_magic_macros` crate. For the most part, the proc macros in `macro_magic_macros` just
 //! call their respective `_internal` variants in this crate.
+//!
+//! Note that this crate enables the `span-locations` feature of `proc-macro2` to capture the
+//! [`SourceInfo`] of exported items. Cargo features are unified across the dependency graph,
+//! so the feature is enabled for all other users of `proc-macro2` in the same build as well.
 #![warn(missing_docs)]
 
-use std::sync::atomic::{AtomicUsize, Ordering};
+use std::collections::BTreeMap;
 
-use const_random::const_random;
 use derive_syn_parse::Parse;
 use macro_magic_core_macros::*;
 use proc_macro2::{Delimiter, Group, Punct, Spacing, Span, TokenStream as TokenStream2, TokenTree};
-use quote::{format_ident, quote, ToTokens, TokenStreamExt};
+use quote::{format_ident, quote, quote_spanned, ToTokens, TokenStreamExt};
 use syn::{
-    parse::{Nothing, ParseStream},
+    ext::IdentExt,
+    parse::{ParseStream, Parser},
     parse2, parse_quote,
     spanned::Spanned,
     token::{Brace, Comma},
@@ -22,16 +26,16 @@ use syn::{
 /// generated macro code.
 ///
 /// See also [`get_macro_magic_root`].
-pub const MACRO_MAGIC_ROOT: &'static str = get_macro_magic_root!();
+pub const MACRO_MAGIC_ROOT: &str = get_macro_magic_root!();
 
-/// A global counter, can be used to generate a relatively unique identifier.
-static COUNTER: AtomicUsize = AtomicUsize::new(0);
-
-/// A compile-time random value used to help prevent collisions between hidden
-/// `__export_tokens_*` idents created by different crates and imported by glob imports into
-/// the same module/scope. Each instance of `macro_magic` will get a random compile-time
-/// [`u32`].
-const COMPILATION_TAG: u32 = const_random!(u32);
+/// Constant used to load the configured default prefix of the hidden callback macros generated
+/// by [`export_tokens_internal`], which is `__export_tokens_tt_` unless the
+/// `MACRO_MAGIC_EXPORT_PREFIX` environment variable is set when `macro_magic` is compiled.
+///
+/// The prefix can also be overridden on a per-export basis via `prefix = ".."`, which allows
+/// frameworks that rebrand `macro_magic` to give their hidden exports their own namespace.
+/// Importers must then specify the same prefix. See [`export_tokens_macro_path_with_prefix`].
+pub const MACRO_MAGIC_EXPORT_PREFIX: &str = get_macro_magic_export_prefix!();
 
 /// Private module containing custom keywords used for parsing in this crate
 mod keywords {
@@ -40,6 +44,26 @@ mod keywords {
     custom_keyword!(proc_macro_attribute);
     custom_keyword!(proc_macro);
     custom_keyword!(proc_macro_derive);
+    custom_keyword!(attributes);
+    custom_keyword!(derive);
+
+    // export_tokens options
+    custom_keyword!(private);
+    custom_keyword!(local);
+    custom_keyword!(version);
+    custom_keyword!(recursive);
+    custom_keyword!(cfg);
+    custom_keyword!(strip_docs);
+    custom_keyword!(signature_only);
+    custom_keyword!(public_fields_only);
+    custom_keyword!(strip_attrs);
+    custom_keyword!(tags);
+    custom_keyword!(meta);
+    custom_keyword!(prefix);
+    custom_keyword!(transform);
+    custom_keyword!(options);
+    // only passed along by `#[export_template]`, not a public `#[export_tokens]` option
+    custom_keyword!(__template);
 
     // WARNING: Must be kept same as in macro expansions
     custom_keyword!(__private_macro_magic_tokens_forwarded);
@@ -68,1081 +92,6762 @@ impl ToTokens for ForwardTokensExtraArg {
 /// Used to parse args that were passed to [`forward_tokens_internal`].
 ///
 /// You shouldn't need to use this directly.
-#[derive(Parse)]
 pub struct ForwardTokensArgs {
-    /// The path of the item whose tokens are being forwarded
+    /// The path of the item whose tokens are being forwarded, or the first of them when a
+    /// bracketed list of paths is specified
     pub source: Path,
-    _comma1: Comma,
+    /// The paths of the items that will be forwarded after [`ForwardTokensArgs::source`],
+    /// if a bracketed list of paths such as `[A, b::B, c::C]` is specified. In this case all
+    /// of the items are collected first and then forwarded to the target at once.
+    pub batch: Option<Vec<Path>>,
     /// The path of the macro that will receive the forwarded tokens
     pub target: Path,
-    _comma2: Option<Comma>,
     /// Contains the override path that will be used instead of `::macro_magic`, if specified.
-    #[parse_if(_comma2.is_some())]
     pub mm_path: Option<Path>,
-    _comma3: Option<Comma>,
     /// Optional extra data. This is how [`import_tokens_attr_internal`] passes the item the
     /// attribute macro is attached to, but this can be repurposed for other things potentially as
     /// it wraps a token stream.
-    #[parse_if(_comma3.is_some())]
     pub extra: Option<ForwardTokensExtraArg>,
+    /// Set when a trailing `proc_macro` is specified, in which case the target will be invoked
+    /// as a function-like proc macro that receives the [`ExportInfo`] of the item (and the
+    /// `extra` data, if any) in addition to the item itself. This is how
+    /// [`import_tokens_proc_internal`] receives the [`ExportInfo`] of the imported item.
+    pub proc_macro: bool,
+    /// Set when a trailing `derive` is specified, in which case the target is the name of a
+    /// derive macro that will be invoked on a hidden struct, receiving the item, the `extra`
+    /// data and the [`ExportInfo`] via a helper attribute. This is how
+    /// [`import_tokens_derive_internal`] receives the imported item.
+    pub derive: bool,
+    /// The prefix the source item was exported with, if specified via a trailing
+    /// `prefix = ".."`. See [`MACRO_MAGIC_EXPORT_PREFIX`].
+    pub prefix: Option<String>,
+}
+
+impl syn::parse::Parse for ForwardTokensArgs {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let (source, batch) = if input.peek(syn::token::Bracket) {
+            let sources;
+            let bracket = syn::bracketed!(sources in input);
+            let mut sources = sources
+                .parse_terminated(parse_item_path, Comma)?
+                .into_iter()
+                .collect::<Vec<_>>();
+            if sources.is_empty() {
+                return Err(Error::new(
+                    bracket.span.join(),
+                    "expected at least one source path",
+                ));
+            }
+            let source = sources.remove(0);
+            (source, Some(sources))
+        } else {
+            (parse_item_path(input)?, None)
+        };
+        input.parse::<Comma>()?;
+        let target = input.parse::<Path>()?;
+        let mut mm_path = None;
+        let mut extra = None;
+        let mut proc_macro = false;
+        let mut derive = false;
+        let mut prefix = None;
+        if input.parse::<Option<Comma>>()?.is_some()
+            && !input.peek(keywords::proc_macro)
+            && !input.peek(keywords::derive)
+            && !input.peek(keywords::prefix)
+        {
+            mm_path = Some(input.parse::<Path>()?);
+            if input.parse::<Option<Comma>>()?.is_some() && input.peek(Brace) {
+                extra = Some(input.parse::<ForwardTokensExtraArg>()?);
+                input.parse::<Option<Comma>>()?;
+            }
+        }
+        if input.peek(keywords::proc_macro) {
+            input.parse::<keywords::proc_macro>()?;
+            proc_macro = true;
+        } else if input.peek(keywords::derive) {
+            let keyword = input.parse::<keywords::derive>()?;
+            if extra.is_none() {
+                return Err(Error::new(
+                    keyword.span,
+                    "`derive` requires extra data containing the helper attribute",
+                ));
+            }
+            derive = true;
+        }
+        if !input.is_empty() {
+            prefix = parse_prefix_option(input)?;
+        }
+        if batch.is_some() && (extra.is_some() || proc_macro || derive) {
+            return Err(Error::new(
+                target.span(),
+                "extra data, `proc_macro` and `derive` are not supported when forwarding \
+                several items",
+            ));
+        }
+        Ok(ForwardTokensArgs {
+            source,
+            batch,
+            target,
+            mm_path,
+            extra,
+            proc_macro,
+            derive,
+            prefix,
+        })
+    }
+}
+
+/// The state of a chained forward of several items, which is how both
+/// [`forward_tokens_internal`] (when given a bracketed list of sources) and the attribute
+/// macros generated by [`import_tokens_attr_internal`] import several items, one after the
+/// other. Written as `{ b::Bar, c::Baz }, { { struct Foo {}, [..] } }`, i.e. the paths of the
+/// items that still have to be forwarded followed by each of the items that have been
+/// forwarded so far along with its [`ExportInfo`].
+///
+/// You shouldn't need to use this directly.
+#[derive(Clone, Default)]
+pub struct ForwardChain {
+    /// The paths of the items that still have to be forwarded
+    pub remaining: Vec<Path>,
+    /// The items that have been forwarded so far, along with their [`ExportInfo`]
+    pub collected: Vec<(Item, ExportInfo)>,
+}
+
+impl syn::parse::Parse for ForwardChain {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let remaining;
+        syn::braced!(remaining in input);
+        input.parse::<Comma>()?;
+        let collected_items;
+        syn::braced!(collected_items in input);
+        let mut collected = Vec::new();
+        while !collected_items.is_empty() {
+            let entry;
+            syn::braced!(entry in collected_items);
+            let item = entry.parse::<Item>()?;
+            entry.parse::<Comma>()?;
+            collected.push((item, entry.parse::<ExportInfo>()?));
+        }
+        Ok(ForwardChain {
+            remaining: remaining
+                .parse_terminated(Path::parse, Comma)?
+                .into_iter()
+                .collect(),
+            collected,
+        })
+    }
+}
+
+impl ToTokens for ForwardChain {
+    fn to_tokens(&self, tokens: &mut TokenStream2) {
+        let remaining = &self.remaining;
+        let collected = self
+            .collected
+            .iter()
+            .map(|(item, info)| quote!({ #item, #info }));
+        tokens.extend(quote!({ #(#remaining),* }, { #(#collected)* }));
+    }
+}
+
+impl ForwardChain {
+    /// Records `item` as forwarded and removes the path of the next item that has to be
+    /// forwarded from [`ForwardChain::remaining`], returning it, or [`None`] if all of the
+    /// items have been forwarded.
+    fn advance(&mut self, item: Item, export_info: ExportInfo) -> Option<Path> {
+        self.collected.push((item, export_info));
+        match self.remaining.is_empty() {
+            true => None,
+            false => Some(self.remaining.remove(0)),
+        }
+    }
+}
+
+/// Used to parse args that were passed to [`forward_tokens_batch_internal`].
+///
+/// You shouldn't need to use this directly.
+pub struct ForwardedBatch {
+    /// The path of the macro that will receive the forwarded tokens
+    pub target_path: Path,
+    /// The item whose tokens were just forwarded
+    pub item: Item,
+    /// The [`ExportInfo`] that was forwarded along with the item
+    pub export_info: ExportInfo,
+    /// Contains the override path that will be used instead of `::macro_magic`
+    pub mm_path: Path,
+    /// The items that were forwarded before [`ForwardedBatch::item`] and the paths of the
+    /// items that still have to be forwarded
+    pub chain: ForwardChain,
+    /// The prefix the source items were exported with, if specified
+    pub prefix: Option<String>,
+}
+
+impl syn::parse::Parse for ForwardedBatch {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let target_path = input.parse::<Path>()?;
+        input.parse::<Comma>()?;
+        let item = input.parse::<Item>()?;
+        input.parse::<Comma>()?;
+        let export_info = input.parse::<ExportInfo>()?;
+        input.parse::<Comma>()?;
+        let mm_path;
+        syn::braced!(mm_path in input);
+        input.parse::<Comma>()?;
+        let chain = input.parse::<ForwardChain>()?;
+        Ok(ForwardedBatch {
+            target_path,
+            item,
+            export_info,
+            mm_path: mm_path.parse()?,
+            chain,
+            prefix: parse_prefix_option(input)?,
+        })
+    }
 }
 
 /// Used to parse args that were passed to [`forward_tokens_inner_internal`].
 ///
 /// You shouldn't need to use this directly.
-#[derive(Parse)]
 pub struct ForwardedTokens {
     /// The path of the macro that will receive the forwarded tokens
     pub target_path: Path,
-    _comma1: Comma,
     /// The item whose tokens are being forwarded
     pub item: Item,
-    _comma2: Option<Comma>,
+    /// The [`ExportInfo`] that was forwarded along with the item
+    pub export_info: ExportInfo,
     /// Optional extra data. This is how [`import_tokens_attr_internal`] passes the item the
     /// attribute macro is attached to, but this can be repurposed for other things potentially as
     /// it wraps a token stream.
-    #[parse_if(_comma2.is_some())]
     pub extra: Option<ForwardTokensExtraArg>,
+    /// Whether the target should be invoked as a function-like proc macro. See
+    /// [`ForwardTokensArgs::proc_macro`].
+    pub proc_macro: bool,
+    /// Whether the target should be invoked as a derive macro. See
+    /// [`ForwardTokensArgs::derive`].
+    pub derive: bool,
+}
+
+impl syn::parse::Parse for ForwardedTokens {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let target_path = input.parse::<Path>()?;
+        input.parse::<Comma>()?;
+        let item = input.parse::<Item>()?;
+        input.parse::<Comma>()?;
+        let export_info = input.parse::<ExportInfo>()?;
+        let mut extra = None;
+        let mut proc_macro = false;
+        let mut derive = false;
+        if input.parse::<Option<Comma>>()?.is_some() {
+            if input.peek(Brace) {
+                extra = Some(input.parse::<ForwardTokensExtraArg>()?);
+                input.parse::<Option<Comma>>()?;
+            }
+            if input.peek(keywords::proc_macro) {
+                input.parse::<keywords::proc_macro>()?;
+                proc_macro = true;
+            } else if input.peek(keywords::derive) {
+                input.parse::<keywords::derive>()?;
+                derive = true;
+            }
+        }
+        Ok(ForwardedTokens {
+            target_path,
+            item,
+            export_info,
+            extra,
+            proc_macro,
+            derive,
+        })
+    }
 }
 
 /// Used to parse args passed to the inner pro macro auto-generated by
 /// [`import_tokens_attr_internal`].
 ///
 /// You shouldn't need to use this directly.
-#[derive(Parse)]
 pub struct AttrItemWithExtra {
     /// Contains the [`Item`] that is being imported (i.e. the item whose tokens we are
     /// obtaining)
     pub imported_item: Item,
-    _comma1: Comma,
-    #[brace]
-    _brace: Brace,
-    #[brace]
-    #[inside(_brace)]
-    _tokens_ident_brace: Brace,
     /// A [`TokenStream2`] representing the raw tokens for the [`struct@Ident`] the generated
     /// macro will use to refer to the tokens argument of the macro.
-    #[inside(_tokens_ident_brace)]
     pub tokens_ident: TokenStream2,
-    #[inside(_brace)]
-    _comma2: Comma,
-    #[brace]
-    #[inside(_brace)]
-    _source_path_brace: Brace,
-    /// Represents the path of the item that is being imported.
-    #[inside(_source_path_brace)]
+    /// Represents the path of the item that is being imported, or the comma-separated paths
+    /// of all of the items that are being imported if there are several.
     pub source_path: TokenStream2,
-    #[inside(_brace)]
-    _comma3: Comma,
-    #[brace]
-    #[inside(_brace)]
-    _custom_tokens_brace: Brace,
     /// when `#[with_custom_parsing(..)]` is used, the variable `__custom_tokens` will be
     /// populated in the resulting proc macro containing the raw [`TokenStream2`] for the
     /// tokens before custom parsing has been applied. This allows you to make use of any extra
     /// context information that may be obtained during custom parsing that you need to utilize
     /// in the final macro.
-    #[inside(_custom_tokens_brace)]
     pub custom_tokens: TokenStream2,
+    /// The items (along with their [`ExportInfo`]) that were imported before
+    /// [`AttrItemWithExtra::imported_item`] and the paths of the foreign items that still
+    /// have to be imported after it, when importing several items at once.
+    pub chain: ForwardChain,
+    /// Contains the [`ExportInfo`] that was forwarded along with the imported item, such as
+    /// its [`SourceInfo`].
+    pub export_info: ExportInfo,
+}
+
+impl syn::parse::Parse for AttrItemWithExtra {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let imported_item = input.parse::<Item>()?;
+        input.parse::<Comma>()?;
+        let extra;
+        syn::braced!(extra in input);
+        let tokens_ident;
+        syn::braced!(tokens_ident in extra);
+        extra.parse::<Comma>()?;
+        let source_path;
+        syn::braced!(source_path in extra);
+        extra.parse::<Comma>()?;
+        let custom_tokens;
+        syn::braced!(custom_tokens in extra);
+        let chain = match extra.parse::<Option<Comma>>()? {
+            Some(_) => extra.parse::<ForwardChain>()?,
+            None => ForwardChain::default(),
+        };
+        input.parse::<Comma>()?;
+        let export_info = input.parse::<ExportInfo>()?;
+        Ok(AttrItemWithExtra {
+            imported_item,
+            tokens_ident: tokens_ident.parse()?,
+            source_path: source_path.parse()?,
+            custom_tokens: custom_tokens.parse()?,
+            chain,
+            export_info,
+        })
+    }
+}
+
+impl AttrItemWithExtra {
+    /// Returns all of the imported items along with their [`ExportInfo`], in the order in
+    /// which their paths were specified.
+    pub fn imported_items(&self) -> Vec<(Item, ExportInfo)> {
+        let mut items = self.chain.collected.clone();
+        items.push((self.imported_item.clone(), self.export_info.clone()));
+        items
+    }
+
+    /// Generates the tokens that forward the next of the remaining paths of the
+    /// [`AttrItemWithExtra::chain`] to `target` (the attribute macro generated by [`import_tokens_attr_internal`]),
+    /// passing along the items that have been imported so far. Returns [`None`] if all of the
+    /// items have been imported.
+    pub fn forward_next(
+        &self,
+        target: &Path,
+        mm_path: &Path,
+        prefix: Option<&str>,
+    ) -> Option<TokenStream2> {
+        let mut chain = self.chain.clone();
+        let next = chain.advance(self.imported_item.clone(), self.export_info.clone())?;
+        let tokens_ident = &self.tokens_ident;
+        let source_path = &self.source_path;
+        let custom_tokens = &self.custom_tokens;
+        let prefix = prefix.map(|prefix| quote!(, prefix = #prefix));
+        Some(quote! {
+            #mm_path::forward_tokens! {
+                #next,
+                #target,
+                #mm_path,
+                {
+                    { #tokens_ident },
+                    { #source_path },
+                    { #custom_tokens },
+                    #chain
+                }
+                #prefix
+            }
+        })
+    }
+}
+
+/// Used to parse the extra data forwarded to the derive macros auto-generated by
+/// [`import_tokens_derive_internal`], i.e. `{ helper, { source_path }, derive_input }`.
+fn parse_derive_extra(input: ParseStream) -> Result<(Ident, TokenStream2, syn::DeriveInput)> {
+    let extra;
+    syn::braced!(extra in input);
+    let helper = extra.parse::<Ident>()?;
+    extra.parse::<Comma>()?;
+    let source_path;
+    syn::braced!(source_path in extra);
+    extra.parse::<Comma>()?;
+    let derive_input = extra.parse::<syn::DeriveInput>()?;
+    Ok((helper, source_path.parse()?, derive_input))
+}
+
+/// Used to parse args passed to the inner proc macro auto-generated by
+/// [`import_tokens_derive_internal`].
+///
+/// You shouldn't need to use this directly.
+pub struct DeriveItemWithExtra {
+    /// Contains the [`Item`] that is being imported (i.e. the item whose tokens we are
+    /// obtaining)
+    pub imported_item: Item,
+    /// The name of the helper attribute that specified the path of the imported item
+    pub helper: Ident,
+    /// Represents the path of the item that is being imported
+    pub source_path: TokenStream2,
+    /// The item the derive macro was originally attached to
+    pub derive_input: syn::DeriveInput,
+    /// Contains the [`ExportInfo`] that was forwarded along with the imported item, such as
+    /// its [`SourceInfo`].
+    pub export_info: ExportInfo,
+}
+
+impl syn::parse::Parse for DeriveItemWithExtra {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let imported_item = input.parse::<Item>()?;
+        input.parse::<Comma>()?;
+        let (helper, source_path, derive_input) = parse_derive_extra(input)?;
+        input.parse::<Comma>()?;
+        let export_info = input.parse::<ExportInfo>()?;
+        Ok(DeriveItemWithExtra {
+            imported_item,
+            helper,
+            source_path,
+            derive_input,
+            export_info,
+        })
+    }
+}
+
+/// Returns the tokens inside of the helper attribute called `helper` (i.e. `#[from(..)]`)
+/// attached to the specified [`syn::DeriveInput`]. Used by the derive macros generated by
+/// [`import_tokens_derive_internal`] to find the path of the foreign item.
+pub fn derive_helper_args(input: &syn::DeriveInput, helper: &str) -> Result<TokenStream2> {
+    let mut attrs = input
+        .attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident(helper));
+    let Some(attr) = attrs.next() else {
+        return Err(Error::new(
+            input.ident.span(),
+            format!("expected a `#[{helper}(..)]` attribute specifying the path of a foreign item"),
+        ));
+    };
+    if let Some(attr) = attrs.next() {
+        return Err(Error::new(
+            attr.span(),
+            format!("only one `#[{helper}(..)]` attribute is supported"),
+        ));
+    }
+    Ok(attr.meta.require_list()?.tokens.clone())
+}
+
+/// Used to parse args passed to the inner proc macro auto-generated by
+/// [`import_tokens_proc_internal`].
+///
+/// You shouldn't need to use this directly.
+pub struct ProcItemWithExtra {
+    /// Contains the [`Item`] that is being imported (i.e. the item whose tokens we are
+    /// obtaining)
+    pub imported_item: Item,
+    /// Represents the path of the item that is being imported. Empty if the path was not
+    /// forwarded along with the item.
+    pub source_path: TokenStream2,
+    /// Contains the [`ExportInfo`] that was forwarded along with the imported item, such as
+    /// its [`SourceInfo`].
+    pub export_info: ExportInfo,
+}
+
+impl syn::parse::Parse for ProcItemWithExtra {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let imported_item = input.parse::<Item>()?;
+        input.parse::<Comma>()?;
+        let mut source_path = TokenStream2::new();
+        if input.peek(Brace) {
+            let extra;
+            syn::braced!(extra in input);
+            source_path = extra.parse()?;
+            input.parse::<Comma>()?;
+        }
+        let export_info = input.parse::<ExportInfo>()?;
+        Ok(ProcItemWithExtra {
+            imported_item,
+            source_path,
+            export_info,
+        })
+    }
 }
 
 /// Used to parse the args for the [`import_tokens_internal`] function.
 ///
 /// You shouldn't need to use this directly.
-#[derive(Parse)]
 pub struct ImportTokensArgs {
-    _let: Token![let],
     /// The [`struct@Ident`] for the `tokens` variable. Usually called `tokens` but could be
     /// something different, hence this variable.
     pub tokens_var_ident: Ident,
-    _eq: Token![=],
+    /// The type the imported item should be parsed as, if specified, i.e. `syn::ItemStruct`
+    /// in `let item: syn::ItemStruct = ..`. See [`ImportedItemType`].
+    pub ty: Option<syn::Type>,
     /// The [`Path`] where the item we are importing can be found.
     pub source_path: Path,
+    /// The [`VersionReq`] the imported item must satisfy, if specified via a trailing
+    /// `version = ..`.
+    pub version: Option<VersionReq>,
+    /// The prefix the item was exported with, if specified via a trailing `prefix = ".."`.
+    /// See [`MACRO_MAGIC_EXPORT_PREFIX`].
+    pub prefix: Option<String>,
+}
+
+impl syn::parse::Parse for ImportTokensArgs {
+    fn parse(input: ParseStream) -> Result<Self> {
+        input.parse::<Token![let]>()?;
+        let tokens_var_ident = input.parse::<Ident>()?;
+        let ty = match input.parse::<Option<Token![:]>>()? {
+            Some(_) => Some(input.parse::<syn::Type>()?),
+            None => None,
+        };
+        input.parse::<Token![=]>()?;
+        let source_path = parse_item_path(input)?;
+        let (version, prefix) = parse_import_options(input)?;
+        Ok(ImportTokensArgs {
+            tokens_var_ident,
+            ty,
+            source_path,
+            version,
+            prefix,
+        })
+    }
+}
+
+/// The types typed imports (i.e. `import_tokens!(let item: syn::ItemStruct = ..)`) know the
+/// corresponding kinds of items of, such that the kind of the imported item can be checked
+/// when the import is expanded rather than when the resulting code runs.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ImportedItemType {
+    /// `syn::Item`, which accepts any kind of item.
+    Item,
+    /// `syn::DeriveInput`, which accepts structs, enums and unions.
+    DeriveInput,
+    /// A specific kind of item, identified by the name of the corresponding `syn` type, i.e.
+    /// `ItemStruct`.
+    Kind(&'static str),
+}
+
+impl ImportedItemType {
+    /// Determines the [`ImportedItemType`] of the specified type based on its last path
+    /// segment, returning [`None`] if it isn't one of the known `syn` item types, in which
+    /// case the imported item is parsed as the specified type without any checks.
+    pub fn from_type(ty: &syn::Type) -> Option<ImportedItemType> {
+        let syn::Type::Path(type_path) = ty else {
+            return None;
+        };
+        let name = type_path.path.segments.last()?.ident.to_string();
+        match name.as_str() {
+            "Item" => Some(ImportedItemType::Item),
+            "DeriveInput" => Some(ImportedItemType::DeriveInput),
+            name => ITEM_KINDS
+                .iter()
+                .find(|(kind, _)| *kind == name)
+                .map(|(kind, _)| ImportedItemType::Kind(kind)),
+        }
+    }
+
+    /// Returns `true` if the specified item can be parsed as this type.
+    pub fn accepts(&self, item: &Item) -> bool {
+        match self {
+            ImportedItemType::Item => true,
+            ImportedItemType::DeriveInput => {
+                matches!(item, Item::Struct(_) | Item::Enum(_) | Item::Union(_))
+            }
+            ImportedItemType::Kind(kind) => item_kind(item).0 == *kind,
+        }
+    }
+}
+
+/// The names of the `syn` types of the different kinds of items, along with a description of
+/// each kind.
+const ITEM_KINDS: &[(&str, &str)] = &[
+    ("ItemConst", "a const"),
+    ("ItemEnum", "an enum"),
+    ("ItemExternCrate", "an extern crate"),
+    ("ItemFn", "a function"),
+    ("ItemForeignMod", "an extern block"),
+    ("ItemImpl", "an impl block"),
+    ("ItemMacro", "a macro invocation"),
+    ("ItemMod", "a module"),
+    ("ItemStatic", "a static"),
+    ("ItemStruct", "a struct"),
+    ("ItemTrait", "a trait"),
+    ("ItemTraitAlias", "a trait alias"),
+    ("ItemType", "a type alias"),
+    ("ItemUnion", "a union"),
+    ("ItemUse", "a use declaration"),
+];
+
+/// Returns the name of the `syn` type of the specified item along with a description of its
+/// kind, i.e. `("ItemStruct", "a struct")`.
+fn item_kind(item: &Item) -> (&'static str, &'static str) {
+    let name = match item {
+        Item::Const(_) => "ItemConst",
+        Item::Enum(_) => "ItemEnum",
+        Item::ExternCrate(_) => "ItemExternCrate",
+        Item::Fn(_) => "ItemFn",
+        Item::ForeignMod(_) => "ItemForeignMod",
+        Item::Impl(_) => "ItemImpl",
+        Item::Macro(_) => "ItemMacro",
+        Item::Mod(_) => "ItemMod",
+        Item::Static(_) => "ItemStatic",
+        Item::Struct(_) => "ItemStruct",
+        Item::Trait(_) => "ItemTrait",
+        Item::TraitAlias(_) => "ItemTraitAlias",
+        Item::Type(_) => "ItemType",
+        Item::Union(_) => "ItemUnion",
+        Item::Use(_) => "ItemUse",
+        _ => return ("Item", "a verbatim item"),
+    };
+    match ITEM_KINDS.iter().find(|(kind, _)| *kind == name) {
+        Some(kind) => *kind,
+        None => unreachable!("all item kinds are listed"),
+    }
 }
 
 /// Used to parse the args for the [`import_tokens_inner_internal`] function.
 ///
 /// You shouldn't need to use this directly.
-#[derive(Parse)]
 pub struct ImportedTokens {
     /// Represents the [`struct@Ident`] that was used to refer to the `tokens` in the original
     /// [`ImportTokensArgs`].
     pub tokens_var_ident: Ident,
-    _comma: Comma,
     /// Contains the [`Item`] that has been imported.
     pub item: Item,
+    /// Contains the [`ExportInfo`] that was forwarded along with the imported item.
+    pub export_info: ExportInfo,
+    /// The [`VersionReq`] that was specified in the original [`ImportTokensArgs`], if any.
+    pub version: Option<VersionReq>,
+    /// The type that was specified in the original [`ImportTokensArgs`], if any.
+    pub ty: Option<syn::Type>,
 }
 
-/// Delineates the different types of proc macro
-#[derive(Copy, Clone, Eq, PartialEq, Debug)]
-pub enum ProcMacroType {
-    /// Corresponds with `#[proc_macro]`
-    Normal,
-    /// Corresponds with `#[proc_macro_attribute]`
-    Attribute,
-    /// Corresponds with `#[proc_macro_derive]`
-    Derive,
+impl syn::parse::Parse for ImportedTokens {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let tokens_var_ident = input.parse::<Ident>()?;
+        input.parse::<Comma>()?;
+        let item = input.parse::<Item>()?;
+        input.parse::<Comma>()?;
+        let export_info = input.parse::<ExportInfo>()?;
+        let mut version = None;
+        let mut ty = None;
+        if input.parse::<Option<Comma>>()?.is_some() {
+            let content;
+            syn::braced!(content in input);
+            if !content.peek(Token![type]) {
+                version = parse_version_option(&content)?;
+            }
+            if content.parse::<Option<Token![type]>>()?.is_some() {
+                content.parse::<Token![=]>()?;
+                ty = Some(content.parse::<syn::Type>()?);
+            }
+        }
+        Ok(ImportedTokens {
+            tokens_var_ident,
+            item,
+            export_info,
+            version,
+            ty,
+        })
+    }
 }
 
-impl ProcMacroType {
-    /// Gets the `&'static str` representation of this proc macro type
-    pub fn to_str(&self) -> &'static str {
-        match self {
-            ProcMacroType::Normal => "#[proc_macro]",
-            ProcMacroType::Attribute => "#[proc_macro_attribute]",
-            ProcMacroType::Derive => "#[proc_macro_derive]",
+/// A requirement on the version of an exported item, as specified via
+/// `#[export_tokens(version = N)]`. Importers can specify these to get a clear compile error
+/// when the shape of an item they depend on has changed.
+///
+/// Version requirements use the same syntax as integer ranges, i.e. `2`, `1..3`, `1..=2`,
+/// `2..` and `..3` are all valid, where a single number requires that exact version.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct VersionReq {
+    /// The minimum (inclusive) version, if any.
+    pub min: Option<u64>,
+    /// The maximum version, if any.
+    pub max: Option<u64>,
+    /// Whether `max` is inclusive (`..=`) or exclusive (`..`).
+    pub max_inclusive: bool,
+}
+
+impl VersionReq {
+    /// Returns `true` if the specified version satisfies this requirement.
+    pub fn matches(&self, version: u64) -> bool {
+        let above_min = match self.min {
+            Some(min) => version >= min,
+            None => true,
+        };
+        let below_max = match (self.max, self.max_inclusive) {
+            (Some(max), true) => version <= max,
+            (Some(max), false) => version < max,
+            (None, _) => true,
+        };
+        above_min && below_max
+    }
+
+    /// Checks that the version in the specified [`ExportInfo`] satisfies this requirement,
+    /// returning an [`Error`] with the specified [`Span`] if it does not.
+    pub fn check(&self, info: &ExportInfo, span: Span) -> Result<()> {
+        let location = format!("{}:{}", info.source.file, info.source.line);
+        match info.version {
+            Some(version) if self.matches(version) => Ok(()),
+            Some(version) => Err(Error::new(
+                span,
+                format!(
+                    "version {self} of the exported item defined at {location} is required, \
+                    but it has version {version}"
+                ),
+            )),
+            None => Err(Error::new(
+                span,
+                format!(
+                    "version {self} of the exported item defined at {location} is required, \
+                    but it does not specify a version, i.e. `#[export_tokens(version = 1)]`"
+                ),
+            )),
         }
     }
+}
 
-    /// Gets the [`Attribute`] representation of this proc macro type
-    pub fn to_attr(&self) -> Attribute {
-        match self {
-            ProcMacroType::Normal => parse_quote!(#[proc_macro]),
-            ProcMacroType::Attribute => parse_quote!(#[proc_macro_attribute]),
-            ProcMacroType::Derive => parse_quote!(#[proc_macro_derive]),
+impl std::fmt::Display for VersionReq {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let (Some(min), true) = (self.min, self.max_inclusive && self.min == self.max) {
+            return write!(f, "{min}");
         }
+        if let Some(min) = self.min {
+            write!(f, "{min}")?;
+        }
+        write!(f, "..")?;
+        if self.max_inclusive {
+            write!(f, "=")?;
+        }
+        if let Some(max) = self.max {
+            write!(f, "{max}")?;
+        }
+        Ok(())
     }
 }
 
-/// Should be implemented by structs that will be passed to `#[with_custom_parsing(..)]`. Such
-/// structs should also implement [`syn::parse::Parse`].
-///
-/// ## Example
-///
-/// ```ignore
-/// #[derive(derive_syn_parse::Parse)]
-/// struct CustomParsingA {
-///     foreign_path: syn::Path,
-///     _comma: syn::token::Comma,
-///     custom_path: syn::Path,
-/// }
-///
-/// impl ToTokens for CustomParsingA {
-///     fn to_tokens(&self, tokens: &mut proc_macro2::TokenStream) {
-///         tokens.extend(self.foreign_path.to_token_stream());
-///         tokens.extend(self._comma.to_token_stream());
-///         tokens.extend(self.custom_path.to_token_stream());
-///     }
-/// }
-///
-/// impl ForeignPath for CustomParsingA {
-///     fn foreign_path(&self) -> &syn::Path {
-///         &self.foreign_path
-///     }
-/// }
-/// ```
-pub trait ForeignPath {
-    /// Returns the path of the foreign item whose tokens will be imported.
-    ///
-    /// This is used with custom parsing. See [`ForeignPath`] for more info.
-    fn foreign_path(&self) -> &syn::Path;
+impl syn::parse::Parse for VersionReq {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let mut req = VersionReq::default();
+        if input.peek(syn::LitInt) {
+            req.min = Some(input.parse::<syn::LitInt>()?.base10_parse()?);
+        }
+        if input.peek(Token![..=]) {
+            input.parse::<Token![..=]>()?;
+            req.max = Some(input.parse::<syn::LitInt>()?.base10_parse()?);
+            req.max_inclusive = true;
+        } else if input.peek(Token![..]) {
+            input.parse::<Token![..]>()?;
+            if input.peek(syn::LitInt) {
+                req.max = Some(input.parse::<syn::LitInt>()?.base10_parse()?);
+            }
+        } else if req.min.is_some() {
+            req.max = req.min;
+            req.max_inclusive = true;
+        } else {
+            return Err(Error::new(
+                input.span(),
+                "expected a version or range of versions, i.e. `2` or `1..3`",
+            ));
+        }
+        Ok(req)
+    }
 }
 
-/// Generically parses a proc macro definition with support for all variants.
-#[derive(Clone)]
-pub struct ProcMacro {
-    /// The underlying proc macro function definition
-    pub proc_fn: ItemFn,
-    /// Specified the type of this proc macro, i.e. attribute vs normal vs derive
-    pub macro_type: ProcMacroType,
-    /// Specifies the [`struct@Ident`] for the `tokens` parameter of this proc macro function
-    /// definition. For normal and derive macros this is the only parameter, and for attribute
-    /// macros this is the second parameter.
-    pub tokens_ident: Ident,
-    /// Specifies the [`struct@Ident`] for the `attr` parameter of this proc macro function
-    /// definition, if it is an attribute macro. Otherwise this will be set to [`None`].
-    pub attr_ident: Option<Ident>,
+impl ToTokens for VersionReq {
+    fn to_tokens(&self, tokens: &mut TokenStream2) {
+        tokens.extend(self.to_string().parse::<TokenStream2>().unwrap());
+    }
 }
 
-impl ProcMacro {
-    /// Constructs a [`ProcMacro`] from anything compatible with [`TokenStream2`].
-    pub fn from<T: Into<TokenStream2>>(tokens: T) -> Result<Self> {
-        let proc_fn = parse2::<ItemFn>(tokens.into())?;
-        let Visibility::Public(_) = proc_fn.vis else { return Err(Error::new(proc_fn.vis.span(), "Visibility must be public")) };
-        let mut macro_type: Option<ProcMacroType> = None;
-        if proc_fn
-            .attrs
-            .iter()
-            .find(|attr| {
-                if syn::parse2::<keywords::proc_macro>(attr.path().to_token_stream()).is_ok() {
-                    macro_type = Some(ProcMacroType::Normal);
-                } else if syn::parse2::<keywords::proc_macro_attribute>(
-                    attr.path().to_token_stream(),
-                )
-                .is_ok()
-                {
-                    macro_type = Some(ProcMacroType::Attribute);
-                } else if syn::parse2::<keywords::proc_macro>(attr.path().to_token_stream()).is_ok()
-                {
-                    macro_type = Some(ProcMacroType::Derive);
-                }
-                macro_type.is_some()
-            })
-            .is_none()
-        {
-            return Err(Error::new(
-                proc_fn.sig.ident.span(),
-                "can only be attached to a proc macro function definition",
-            ));
-        };
-        let macro_type = macro_type.unwrap();
+/// Parses an optional trailing `, version = ..` (or a bare `version = ..` if nothing precedes
+/// it) as a [`VersionReq`].
+fn parse_version_option(input: ParseStream) -> Result<Option<VersionReq>> {
+    if input.is_empty() {
+        return Ok(None);
+    }
+    if !input.peek(keywords::version) {
+        input.parse::<Comma>()?;
+    }
+    input.parse::<keywords::version>()?;
+    input.parse::<Token![=]>()?;
+    let req = input.parse::<VersionReq>()?;
+    input.parse::<Option<Comma>>()?;
+    Ok(Some(req))
+}
 
-        // tokens_ident
-        let Some(FnArg::Typed(tokens_arg)) = proc_fn.sig.inputs.last() else {
-            unreachable!("missing tokens arg");
-        };
-        let Pat::Ident(tokens_ident) = *tokens_arg.pat.clone() else {
-            unreachable!("invalid tokens arg");
-        };
-        let tokens_ident = tokens_ident.ident;
+/// Parses an optional `prefix = ".."` option (optionally preceded and followed by a comma),
+/// returning the specified export prefix, if any. See [`MACRO_MAGIC_EXPORT_PREFIX`].
+fn parse_prefix_option(input: ParseStream) -> Result<Option<String>> {
+    Ok(parse_prefix_literal(input)?.map(|prefix| prefix.value()))
+}
 
-        // attr_ident (if applicable)
-        let attr_ident = match macro_type {
-            ProcMacroType::Attribute => {
-                let Some(FnArg::Typed(attr_arg)) = proc_fn.sig.inputs.first() else {
-                    unreachable!("missing attr arg");
-                };
-                let Pat::Ident(attr_ident) = *attr_arg.pat.clone() else {
-                    unreachable!("invalid attr arg");
-                };
-                Some(attr_ident.ident)
-            }
-            _ => None,
-        };
-        Ok(ProcMacro {
-            proc_fn,
-            macro_type,
-            tokens_ident,
-            attr_ident,
-        })
+/// Like [`parse_prefix_option`], but returns the literal the prefix was specified as.
+fn parse_prefix_literal(input: ParseStream) -> Result<Option<syn::LitStr>> {
+    if input.peek(Comma) && input.peek2(keywords::prefix) {
+        input.parse::<Comma>()?;
     }
+    if !input.peek(keywords::prefix) {
+        input.parse::<Option<Comma>>()?;
+        return Ok(None);
+    }
+    input.parse::<keywords::prefix>()?;
+    input.parse::<Token![=]>()?;
+    let prefix = input.parse::<syn::LitStr>()?;
+    input.parse::<Option<Comma>>()?;
+    validate_export_prefix(&prefix.value(), prefix.span())?;
+    Ok(Some(prefix))
 }
 
-/// Parses a proc macro function from a `TokenStream2` expecting only the specified `macro_type`
-pub fn parse_proc_macro_variant<T: Into<TokenStream2>>(
-    tokens: T,
-    macro_type: ProcMacroType,
-) -> Result<ProcMacro> {
-    let proc_macro = ProcMacro::from(tokens.into())?;
-    if proc_macro.macro_type != macro_type {
-        let actual = proc_macro.macro_type.to_str();
-        let desired = macro_type.to_str();
-        return Err(Error::new(
-            proc_macro.proc_fn.sig.ident.span(),
-            format!(
-                "expected a function definition with {} but found {} instead",
-                actual, desired
-            ),
-        ));
+/// Ensures the specified export prefix can be used as the beginning of an [`struct@Ident`].
+fn validate_export_prefix(prefix: &str, span: Span) -> Result<()> {
+    let valid = prefix
+        .chars()
+        .all(|c| c == '_' || c.is_ascii_alphanumeric())
+        && prefix.chars().next().is_some_and(|c| !c.is_ascii_digit());
+    match valid {
+        true => Ok(()),
+        false => Err(Error::new(
+            span,
+            "the export prefix must consist of ASCII letters, digits and underscores and \
+            must not start with a digit",
+        )),
     }
-    Ok(proc_macro)
 }
 
-/// Safely access the `macro_magic` root based on the `MACRO_MAGIC_ROOT` env var, which
-/// defaults to `::macro_magic`, but can be configured via the `[env]` section of
-/// `.cargo/config.toml`
-pub fn macro_magic_root() -> Path {
-    parse2::<Path>(
-        MACRO_MAGIC_ROOT
-            .parse::<TokenStream2>()
-            .expect("environment var `MACRO_MAGIC_ROOT` must parse to a valid TokenStream2"),
-    )
-    .expect("environment variable `MACRO_MAGIC_ROOT` must parse to a valid syn::Path")
+/// Parses the trailing options accepted by importers, i.e. `version = ..` and `prefix = ".."`,
+/// in any order.
+fn parse_import_options(input: ParseStream) -> Result<(Option<VersionReq>, Option<String>)> {
+    let mut version = None;
+    let mut prefix = None;
+    while !input.is_empty() {
+        if input.peek(Comma) {
+            input.parse::<Comma>()?;
+        } else if input.peek(keywords::prefix) {
+            prefix = parse_prefix_option(input)?;
+        } else {
+            version = parse_version_option(input)?;
+        }
+    }
+    Ok((version, prefix))
 }
 
-/// Safely access a subpath of `macro_magic::__private`
-pub fn private_path<T: Into<TokenStream2> + Clone>(subpath: &T) -> Path {
-    let subpath = subpath.clone().into();
-    let root = macro_magic_root();
-    parse_quote!(#root::__private::#subpath)
+/// Describes where an exported item was defined. This is captured by
+/// [`export_tokens_internal`] and forwarded along with the tokens of the item, allowing
+/// importers to refer back to the original definition, i.e. in error messages or generated
+/// docs.
+///
+/// Note that the `module_path` is only a best-effort guess. Proc macros can't expand
+/// `module_path!()`, and expanding it within the generated export macro yields the module of
+/// the importer rather than that of the exporter, so the `module_path` is derived from the
+/// location of the source file within the crate. Inline `mod` blocks, modules loaded via
+/// `#[path = ".."]` and modules generated by other macros are therefore not reflected. The
+/// `file`, `line` and `column` are exact. When location information is unavailable (i.e.
+/// outside of a proc macro context), `file` will be empty and `line` and `column` will be `0`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SourceInfo {
+    /// The name of the crate the item was exported from, as in `CARGO_CRATE_NAME`.
+    pub crate_name: String,
+    /// The version of the crate the item was exported from, as in `CARGO_PKG_VERSION`.
+    pub crate_version: String,
+    /// A best-effort guess at the path of the module the item was exported from, in the same
+    /// format as `module_path!()`, i.e. `my_crate::some_module`. This is the module of the file
+    /// the item is defined in, so an item defined in an inline `mod inner { .. }` block of
+    /// `src/some_module.rs` has the module path `my_crate::some_module` rather than
+    /// `my_crate::some_module::inner`. See [`SourceInfo`].
+    pub module_path: String,
+    /// The file the item was defined in, in the same format as `file!()`.
+    pub file: String,
+    /// The (1-based) line the item starts on.
+    pub line: usize,
+    /// The (0-based) column the item starts on.
+    pub column: usize,
 }
 
-/// Safely access a subpath of `macro_magic`
-pub fn macro_magic_path<T: Into<TokenStream2> + Clone>(subpath: &T) -> Path {
-    let subpath = subpath.clone().into();
-    let root = macro_magic_root();
-    parse_quote! {
-        #root::#subpath
+impl SourceInfo {
+    /// Captures the [`SourceInfo`] for an item whose tokens begin at the specified [`Span`],
+    /// using the environment variables cargo sets for the crate that is currently being
+    /// compiled.
+    pub fn from_span(span: Span) -> SourceInfo {
+        let crate_name = std::env::var("CARGO_CRATE_NAME")
+            .or_else(|_| std::env::var("CARGO_PKG_NAME").map(|name| name.replace('-', "_")))
+            .unwrap_or_default();
+        let crate_version = std::env::var("CARGO_PKG_VERSION").unwrap_or_default();
+        let start = span.start();
+        let (file, line, column) = match span.local_file() {
+            Some(_) => (span.file(), start.line, start.column),
+            None => (String::new(), 0, 0),
+        };
+        let mut module_path = vec![crate_name.clone()];
+        if let Some(local_file) = span.local_file() {
+            module_path.extend(file_module_path(&local_file, &crate_name));
+        }
+        SourceInfo {
+            crate_name,
+            crate_version,
+            module_path: module_path.join("::"),
+            file,
+            line,
+            column,
+        }
     }
 }
 
-/// Returns the specified string in snake_case
-pub fn to_snake_case(input: impl Into<String>) -> String {
-    let input: String = input.into();
-    if input.len() == 0 {
-        return input.into();
+/// Returns the path of `file` relative to the root of the crate that is currently being
+/// compiled, i.e. `src/foo.rs`.
+///
+/// Cargo passes the paths of workspace members to the compiler relative to the workspace root
+/// and the paths of all other crates as absolute paths, so rather than resolving relative
+/// paths against the working directory, the longest trailing portion of `CARGO_MANIFEST_DIR`
+/// that `file` begins with is stripped from them.
+fn crate_relative_file(file: &std::path::Path) -> Option<std::path::PathBuf> {
+    let manifest_dir = std::path::PathBuf::from(std::env::var("CARGO_MANIFEST_DIR").ok()?);
+    if file.is_absolute() {
+        return file
+            .strip_prefix(manifest_dir)
+            .ok()
+            .map(|path| path.to_path_buf());
     }
-    let mut prev_lower = input.chars().next().unwrap().is_lowercase();
-    let mut prev_whitespace = true;
-    let mut first = true;
-    let mut output: Vec<char> = Vec::new();
-    for c in input.chars() {
-        if c == '_' {
-            prev_whitespace = true;
-            output.push('_');
-            continue;
+    let components: Vec<_> = manifest_dir.components().collect();
+    (0..=components.len()).find_map(|skip| {
+        let suffix: std::path::PathBuf = components[skip..].iter().collect();
+        file.strip_prefix(suffix)
+            .ok()
+            .map(|path| path.to_path_buf())
+    })
+}
+
+/// Derives the module path segments implied by the location of `file` within the crate that
+/// is currently being compiled, i.e. `src/foo/bar.rs` and `src/foo/bar/mod.rs` both map to
+/// `foo::bar`. Files outside of `CARGO_MANIFEST_DIR` map to the crate root.
+fn file_module_path(file: &std::path::Path, crate_name: &str) -> Vec<String> {
+    let Some(relative) = crate_relative_file(file) else {
+        return Vec::new();
+    };
+    let mut segments: Vec<String> = relative
+        .with_extension("")
+        .iter()
+        .map(|segment| segment.to_string_lossy().into_owned())
+        .collect();
+    let is_target_dir = matches!(
+        segments.first().map(String::as_str),
+        Some("tests" | "examples" | "benches")
+    );
+    if segments.first().is_some_and(|segment| segment == "src") {
+        segments.remove(0);
+        if segments.len() == 1 && matches!(segments[0].as_str(), "lib" | "main") {
+            segments.clear();
         }
-        if !c.is_ascii_alphanumeric() && c != '_' && !c.is_whitespace() {
-            continue;
+    }
+    if is_target_dir || segments.first().is_some_and(|segment| segment == "bin") {
+        // the crate root of these targets is either `<dir>/<crate>.rs` or
+        // `<dir>/<crate>/main.rs`, anything else is a module shared via `mod`
+        segments.remove(0);
+        if segments
+            .first()
+            .is_some_and(|segment| segment == crate_name)
+        {
+            segments.remove(0);
+            if segments.len() == 1 && segments[0] == "main" {
+                segments.clear();
+            }
         }
-        if !first && c.is_whitespace() || c == '_' {
-            if !prev_whitespace {
-                output.push('_');
+    }
+    if segments.last().is_some_and(|segment| segment == "mod") {
+        segments.pop();
+    }
+    segments
+}
+
+impl syn::parse::Parse for SourceInfo {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let mut info = SourceInfo::default();
+        while !input.is_empty() {
+            let key = input.parse::<Ident>()?;
+            input.parse::<Token![=]>()?;
+            match key.to_string().as_str() {
+                "crate_name" => info.crate_name = input.parse::<syn::LitStr>()?.value(),
+                "crate_version" => info.crate_version = input.parse::<syn::LitStr>()?.value(),
+                "module_path" => info.module_path = input.parse::<syn::LitStr>()?.value(),
+                "file" => info.file = input.parse::<syn::LitStr>()?.value(),
+                "line" => info.line = input.parse::<syn::LitInt>()?.base10_parse()?,
+                "column" => info.column = input.parse::<syn::LitInt>()?.base10_parse()?,
+                _ => return Err(Error::new(key.span(), "unknown source info key")),
             }
-            prev_whitespace = true;
-        } else {
-            let current_lower = c.is_lowercase();
-            if ((prev_lower != current_lower && prev_lower)
-                || (prev_lower == current_lower && !prev_lower))
-                && !first
-                && !prev_whitespace
-            {
-                output.push('_');
+            if !input.is_empty() {
+                input.parse::<Comma>()?;
             }
-            output.push(c.to_ascii_lowercase());
-            prev_lower = current_lower;
-            prev_whitespace = false;
         }
-        first = false;
+        Ok(info)
     }
-    output.iter().collect::<String>()
 }
 
-/// "Flattens" an [`struct@Ident`] by converting it to snake case.
-///
-/// Used by [`export_tokens_macro_ident`].
-pub fn flatten_ident(ident: &Ident) -> Ident {
-    Ident::new(to_snake_case(ident.to_string()).as_str(), ident.span())
+impl ToTokens for SourceInfo {
+    fn to_tokens(&self, tokens: &mut TokenStream2) {
+        let crate_name = &self.crate_name;
+        let crate_version = &self.crate_version;
+        let module_path = &self.module_path;
+        let file = &self.file;
+        let line = proc_macro2::Literal::usize_unsuffixed(self.line);
+        let column = proc_macro2::Literal::usize_unsuffixed(self.column);
+        tokens.extend(quote! {
+            crate_name = #crate_name,
+            crate_version = #crate_version,
+            module_path = #module_path,
+            file = #file,
+            line = #line,
+            column = #column
+        });
+    }
 }
 
-/// Produces the full path for the auto-generated callback-based decl macro that allows us to
-/// forward tokens across crate boundaries.
+/// Computes a stable fingerprint of the specified tokens, returned as a 16 character
+/// lowercase hex string.
 ///
-/// Used by [`export_tokens_internal`] and several other functions.
-pub fn export_tokens_macro_ident(ident: &Ident) -> Ident {
-    let ident = flatten_ident(ident);
-    let ident_string = format!("__export_tokens_tt_{}", ident.to_token_stream().to_string());
-    Ident::new(ident_string.as_str(), Span::call_site())
+/// The hash is computed (using 64-bit FNV-1a) over a normalized walk of the token trees
+/// rather than over their string representation, so it is not affected by whitespace,
+/// comments (other than doc comments) or changes in how the compiler pretty-prints tokens.
+/// It does change whenever the tokens themselves change, such as when a field is added,
+/// renamed or has its type changed.
+pub fn tokens_hash(tokens: &TokenStream2) -> String {
+    fn write(hash: &mut u64, bytes: &[u8]) {
+        for byte in bytes {
+            *hash ^= *byte as u64;
+            *hash = hash.wrapping_mul(0x100000001b3);
+        }
+    }
+    fn walk(tokens: TokenStream2, hash: &mut u64) {
+        for token in tokens {
+            match token {
+                TokenTree::Group(group) => {
+                    let (open, close) = match group.delimiter() {
+                        Delimiter::Parenthesis => (b"(", b")"),
+                        Delimiter::Brace => (b"{", b"}"),
+                        Delimiter::Bracket => (b"[", b"]"),
+                        Delimiter::None => (b"\x02", b"\x03"),
+                    };
+                    write(hash, open);
+                    walk(group.stream(), hash);
+                    write(hash, close);
+                }
+                TokenTree::Ident(ident) => write(hash, ident.to_string().as_bytes()),
+                TokenTree::Punct(punct) => write(hash, punct.as_char().to_string().as_bytes()),
+                TokenTree::Literal(literal) => write(hash, literal.to_string().as_bytes()),
+            }
+            write(hash, b" ");
+        }
+    }
+    let mut hash = 0xcbf29ce484222325;
+    walk(tokens.clone(), &mut hash);
+    format!("{hash:016x}")
 }
 
-/// Resolves to the path of the `#[export_tokens]` macro for the given item path.
+/// Metadata that is forwarded along with the tokens of an exported item by the hidden macros
+/// generated by [`export_tokens_internal`], such as the [`SourceInfo`] of the item.
 ///
-/// If the specified [`Path`] doesn't exist or there isn't a valid `#[export_tokens]` attribute
-/// on the item at that path, the returned macro path will be invalid.
-pub fn export_tokens_macro_path(item_path: &Path) -> Path {
-    let mut macro_path = item_path.clone();
-    let Some(last_seg) = macro_path.segments.pop()
-        else { unreachable!("must have at least one segment") };
-    let last_seg = export_tokens_macro_ident(&last_seg.into_value().ident);
-    macro_path.segments.push(last_seg.into());
-    macro_path
-}
-
-/// Generates a new unique `#[export_tokens]` macro identifier
-fn new_unique_export_tokens_ident(ident: &Ident) -> Ident {
-    let unique_id = COUNTER.fetch_add(1, Ordering::SeqCst);
-    let ident = flatten_ident(ident).to_token_stream().to_string();
-    let ident_string = format!("__export_tokens_tt_{COMPILATION_TAG}_{ident}_{unique_id}");
-    Ident::new(ident_string.as_str(), Span::call_site())
+/// Importers can access this via [`AttrItemWithExtra::export_info`] and
+/// [`ProcItemWithExtra::export_info`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ExportInfo {
+    /// Describes where the exported item was defined.
+    pub source: SourceInfo,
+    /// A fingerprint of the tokens of the exported item, as computed by [`tokens_hash`].
+    pub hash: String,
+    /// The version of the exported item, if specified via
+    /// `#[export_tokens(version = N)]`. See [`VersionReq`].
+    pub version: Option<u64>,
+    /// The `#[cfg(..)]` predicates found within the exported item, along with whether each of
+    /// them was active in the exporting crate, if `#[export_tokens(cfg)]` was specified. See
+    /// [`strip_inactive_cfg`].
+    pub cfg: Vec<CfgPredicate>,
+    /// The tags attached to the exported item via `#[export_tokens(tags(..))]`.
+    pub tags: Vec<String>,
+    /// The key-value metadata attached to the exported item via
+    /// `#[export_tokens(meta(key = "value", ..))]`. Values that are not string literals are
+    /// stored as their literal representation, i.e. `3` or `true`.
+    pub meta: BTreeMap<String, String>,
+    /// Set when the exported item is an export group created via `export_group!`, in which
+    /// case the item is an inline module containing the items of the group. See
+    /// [`ExportInfo::group_items`].
+    pub group: bool,
+    /// The placeholders of the exported item if it is a template exported via
+    /// `#[export_template(..)]`, which are substituted by `instantiate_template!`.
+    pub placeholders: Vec<String>,
+    /// The kind of the exported fragment if it was exported via `export_fragment!`, in which
+    /// case the item is a wrapper around the fragment. See [`ExportInfo::fragment`].
+    pub fragment_kind: Option<FragmentKind>,
+    /// Set when the exported item was exported via `#[export_tokens(signature_only)]`, in
+    /// which case the bodies of its functions and methods have been replaced by empty blocks
+    /// (and the default bodies of trait methods have been removed).
+    pub signature_only: bool,
 }
 
-/// The internal code behind the `#[export_tokens]` attribute macro.
-///
-/// The `attr` variable contains the tokens for the optional naming [`struct@Ident`] (necessary
-/// on [`Item`]s that don't have an inherent [`struct@Ident`]), and the `tokens` variable is
-/// the tokens for the [`Item`] the attribute macro can be attached to. The `attr` variable can
-/// be blank tokens for supported items, which include every valid [`syn::Item`] except for
-/// [`syn::ItemForeignMod`], [`syn::ItemUse`], [`syn::ItemImpl`], and [`Item::Verbatim`], which
-/// all require `attr` to be specified.
-///
-/// An empty [`TokenStream2`] is sufficient for opting out of using `attr`
-pub fn export_tokens_internal<T: Into<TokenStream2>, E: Into<TokenStream2>>(
-    attr: T,
-    tokens: E,
-    emit: bool,
-) -> Result<TokenStream2> {
-    let attr = attr.into();
-    let item: Item = parse2(tokens.into())?;
-    let ident = match item.clone() {
-        Item::Const(item_const) => Some(item_const.ident),
-        Item::Enum(item_enum) => Some(item_enum.ident),
-        Item::ExternCrate(item_extern_crate) => Some(item_extern_crate.ident),
-        Item::Fn(item_fn) => Some(item_fn.sig.ident),
-        Item::Macro(item_macro) => item_macro.ident, // note this one might not have an Ident as well
-        Item::Mod(item_mod) => Some(item_mod.ident),
-        Item::Static(item_static) => Some(item_static.ident),
-        Item::Struct(item_struct) => Some(item_struct.ident),
-        Item::Trait(item_trait) => Some(item_trait.ident),
-        Item::TraitAlias(item_trait_alias) => Some(item_trait_alias.ident),
-        Item::Type(item_type) => Some(item_type.ident),
-        Item::Union(item_union) => Some(item_union.ident),
-        // Item::ForeignMod(item_foreign_mod) => None,
-        // Item::Use(item_use) => None,
-        // Item::Impl(item_impl) => None,
-        // Item::Verbatim(_) => None,
-        _ => None,
-    };
-    let ident = match ident {
-        Some(ident) => {
-            if let Ok(_) = parse2::<Nothing>(attr.clone()) {
-                ident
-            } else {
-                parse2::<Ident>(attr)?
-            }
+impl ExportInfo {
+    /// Captures the [`ExportInfo`] for the specified exported item.
+    pub fn for_item<T: ToTokens>(item: &T) -> ExportInfo {
+        ExportInfo {
+            source: SourceInfo::from_span(item.span()),
+            hash: tokens_hash(&item.to_token_stream()),
+            version: None,
+            cfg: Vec::new(),
+            tags: Vec::new(),
+            meta: BTreeMap::new(),
+            group: false,
+            placeholders: Vec::new(),
+            fragment_kind: None,
+            signature_only: false,
         }
-        None => parse2::<Ident>(attr)?,
-    };
-    let macro_ident = new_unique_export_tokens_ident(&ident);
-    let ident = export_tokens_macro_ident(&ident);
-    let item_emit = match emit {
-        true => quote! {
-            #[allow(unused)]
-            #item
-        },
-        false => quote!(),
-    };
-    let output = quote! {
-        #[doc(hidden)]
-        #[macro_export]
-        macro_rules! #macro_ident {
-            // arm with extra support (used by attr)
-            (
-                $(::)?$($tokens_var:ident)::*,
-                $(::)?$($callback:ident)::*,
-                { $( $extra:tt )* }
-            ) => {
-                $($callback)::*! {
-                    $($tokens_var)::*,
-                    #item,
-                    { $( $extra )* }
-                }
-            };
-            // regular arm (used by proc, import_tokens, etc)
-            ($(::)?$($tokens_var:ident)::*, $(::)?$($callback:ident)::*) => {
-                $($callback)::*! {
-                    $($tokens_var)::*,
-                    #item
-                }
-            };
+    }
+
+    /// Returns the items of the specified exported item. For an export group created via
+    /// `export_group!` these are the items of the group, in the order they were listed,
+    /// otherwise this is just the item itself.
+    pub fn group_items(&self, item: Item) -> Vec<Item> {
+        match item {
+            Item::Mod(syn::ItemMod {
+                content: Some((_, items)),
+                ..
+            }) if self.group => items,
+            item => vec![item],
         }
-        pub use #macro_ident as #ident;
-        #item_emit
-    };
-    Ok(output)
-}
+    }
 
-/// Internal implementation of `export_tokens_alias!`. Allows creating a renamed/rebranded
-/// macro that does the same thing as `#[export_tokens]`
-pub fn export_tokens_alias_internal<T: Into<TokenStream2>>(
-    tokens: T,
-    emit: bool,
-) -> Result<TokenStream2> {
-    let alias = parse2::<Ident>(tokens.into())?;
-    let export_tokens_internal_path = macro_magic_path(&quote!(mm_core::export_tokens_internal));
-    Ok(quote! {
-        #[proc_macro_attribute]
-        pub fn #alias(attr: proc_macro::TokenStream, tokens: proc_macro::TokenStream) -> proc_macro::TokenStream {
-            match #export_tokens_internal_path(attr, tokens, #emit) {
-                Ok(tokens) => tokens.into(),
-                Err(err) => err.to_compile_error().into(),
+    /// Returns the fragment wrapped by the specified exported item if it was exported via
+    /// `export_fragment!`, parsed as the kind of fragment it was exported as.
+    pub fn fragment(&self, item: Item) -> Result<Fragment> {
+        let Some(kind) = self.fragment_kind else {
+            return Err(Error::new(
+                item.span(),
+                "the specified item is not a fragment exported via `export_fragment!`",
+            ));
+        };
+        let Item::Macro(syn::ItemMacro { mac, .. }) = item else {
+            return Err(Error::new(item.span(), "malformed fragment"));
+        };
+        kind.parse(mac.tokens)
+    }
+
+    /// Returns `true` if the exported item has the specified tag.
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.iter().any(|other| other == tag)
+    }
+
+    /// Returns the metadata value associated with the specified key, if any.
+    pub fn meta(&self, key: &str) -> Option<&str> {
+        self.meta.get(key).map(String::as_str)
+    }
+
+    /// Generates the tokens of this [`ExportInfo`], using the specified `cfg` tokens in place
+    /// of the ones that would be generated from [`ExportInfo::cfg`], if specified.
+    fn to_tokens_with_cfg(&self, cfg: Option<TokenStream2>) -> TokenStream2 {
+        let source = &self.source;
+        let hash = &self.hash;
+        let version = self.version.map(|version| {
+            let version = proc_macro2::Literal::u64_unsuffixed(version);
+            quote!(, version = #version)
+        });
+        let cfg = cfg.or_else(|| {
+            if self.cfg.is_empty() {
+                return None;
             }
-        }
-    })
+            let predicates = self.cfg.iter().map(|cfg| {
+                let predicate: TokenStream2 = cfg.predicate.parse().unwrap_or_default();
+                let active = cfg.active;
+                quote!((#predicate) = #active)
+            });
+            Some(quote!(#(#predicates),*))
+        });
+        let cfg = cfg.map(|cfg| quote!(, cfg(#cfg)));
+        let tags = (!self.tags.is_empty()).then(|| {
+            let tags = self.tags.iter().map(|tag| format_ident!("{}", tag));
+            quote!(, tags(#(#tags),*))
+        });
+        let meta = (!self.meta.is_empty()).then(|| {
+            let keys = self.meta.keys().map(|key| format_ident!("{}", key));
+            let values = self.meta.values();
+            quote!(, meta(#(#keys = #values),*))
+        });
+        let group = self.group.then(|| quote!(, group));
+        let signature_only = self.signature_only.then(|| quote!(, signature_only));
+        let fragment = self.fragment_kind.map(|kind| quote!(, fragment = #kind));
+        let template = (!self.placeholders.is_empty()).then(|| {
+            let placeholders = self
+                .placeholders
+                .iter()
+                .map(|name| format_ident!("{}", name));
+            quote!(, template(#(#placeholders),*))
+        });
+        quote!([source(#source), hash = #hash #version #cfg #tags #meta #group #template #fragment #signature_only])
+    }
 }
 
-/// The internal implementation for the `import_tokens` macro.
-///
-/// You can call this in your own proc macros to make use of the `import_tokens` functionality
-/// directly, though this approach is limited. The arguments should be a [`TokenStream2`] that
-/// can parse into an [`ImportTokensArgs`] successfully. That is a valid `let` variable
-/// declaration set to equal a path where an `#[export_tokens]` with the specified ident can be
-/// found.
-///
-/// ### Example:
-/// ```
-/// use macro_magic_core::*;
-/// use quote::quote;
-///
-/// let some_ident = quote!(my_tokens);
-/// let some_path = quote!(other_crate::exported_item);
-/// let tokens = import_tokens_internal(quote!(let #some_ident = other_crate::ExportedItem)).unwrap();
-/// assert_eq!(
-///     tokens.to_string(),
-///     "other_crate :: __export_tokens_tt_exported_item ! { my_tokens , \
-///     :: macro_magic :: __private :: import_tokens_inner }");
-/// ```
-/// If these tokens were emitted as part of a proc macro, they would expand to a variable
-/// declaration like:
-/// ```ignore
-/// let my_tokens: TokenStream2;
-/// ```
-/// where `my_tokens` contains the tokens of `ExportedItem`.
-pub fn import_tokens_internal<T: Into<TokenStream2>>(tokens: T) -> Result<TokenStream2> {
-    let args = parse2::<ImportTokensArgs>(tokens.into())?;
-    let source_path = export_tokens_macro_path(&args.source_path);
-    let inner_macro_path = private_path(&quote!(import_tokens_inner));
-    let tokens_var_ident = args.tokens_var_ident;
-    Ok(quote! {
-        #source_path! { #tokens_var_ident, #inner_macro_path }
-    })
+/// Parses a parenthesized list of tags, i.e. `(db, api)`.
+fn parse_tags(input: ParseStream) -> Result<Vec<String>> {
+    let content;
+    syn::parenthesized!(content in input);
+    let tags = content.parse_terminated(Ident::parse_any, Comma)?;
+    Ok(tags.into_iter().map(|tag| tag.to_string()).collect())
 }
 
-/// The internal implementation for the `import_tokens_inner` macro.
-///
-/// You shouldn't need to call this in any circumstances but it is provided just in case.
-pub fn import_tokens_inner_internal<T: Into<TokenStream2>>(tokens: T) -> Result<TokenStream2> {
-    let parsed = parse2::<ImportedTokens>(tokens.into())?;
-    let tokens_string = parsed.item.to_token_stream().to_string();
-    let ident = parsed.tokens_var_ident;
-    let token_stream_2 = private_path(&quote!(TokenStream2));
-    Ok(quote! {
-        let #ident = #tokens_string.parse::<#token_stream_2>().expect("failed to parse quoted tokens");
-    })
+/// Parses a parenthesized list of key-value metadata, i.e. `(table = "users", shards = 4)`.
+fn parse_meta(input: ParseStream) -> Result<BTreeMap<String, String>> {
+    let content;
+    syn::parenthesized!(content in input);
+    let mut meta = BTreeMap::new();
+    while !content.is_empty() {
+        let key = content.call(Ident::parse_any)?;
+        content.parse::<Token![=]>()?;
+        let value = match content.parse::<syn::Lit>()? {
+            syn::Lit::Str(lit) => lit.value(),
+            lit => lit.to_token_stream().to_string(),
+        };
+        if meta.insert(key.to_string(), value).is_some() {
+            return Err(Error::new(
+                key.span(),
+                format!("duplicate metadata key `{key}`"),
+            ));
+        }
+        if !content.is_empty() {
+            content.parse::<Comma>()?;
+        }
+    }
+    Ok(meta)
 }
 
-/// The internal implementation for the `forward_tokens` macro.
-///
-/// You shouldn't need to call this in any circumstances but it is provided just in case.
-pub fn forward_tokens_internal<T: Into<TokenStream2>>(tokens: T) -> Result<TokenStream2> {
-    let args = parse2::<ForwardTokensArgs>(tokens.into())?;
-    let mm_path = match args.mm_path {
-        Some(path) => path,
-        None => macro_magic_root(),
-    };
-    let source_path = export_tokens_macro_path(&args.source);
-    let target_path = args.target;
-    if let Some(extra) = args.extra {
-        Ok(quote! {
-            #source_path! {
-                #target_path,
-                #mm_path::__private::forward_tokens_inner,
-                #extra
+/// The kinds of fragments that can be exported via `export_fragment!`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum FragmentKind {
+    /// An expression, specified as `expr`, which parses as a [`syn::Expr`].
+    Expr,
+    /// A type, specified as `ty`, which parses as a [`syn::Type`].
+    Type,
+    /// A pattern, specified as `pat`, which parses as a [`syn::Pat`].
+    Pat,
+    /// A sequence of statements, specified as `stmts`, which parses as a `Vec<syn::Stmt>`.
+    Stmts,
+}
+
+impl FragmentKind {
+    /// Parses the specified tokens as this kind of fragment.
+    pub fn parse(self, tokens: TokenStream2) -> Result<Fragment> {
+        Ok(match self {
+            FragmentKind::Expr => Fragment::Expr(parse2(tokens)?),
+            FragmentKind::Type => Fragment::Type(parse2(tokens)?),
+            FragmentKind::Pat => {
+                Fragment::Pat(syn::Pat::parse_multi_with_leading_vert.parse2(tokens)?)
             }
-        })
-    } else {
-        Ok(quote! {
-            #source_path! { #target_path, #mm_path::__private::forward_tokens_inner }
+            FragmentKind::Stmts => Fragment::Stmts(syn::Block::parse_within.parse2(tokens)?),
         })
     }
 }
 
-/// Used by [`forward_tokens_internal`].
-pub fn forward_tokens_inner_internal<T: Into<TokenStream2>>(tokens: T) -> Result<TokenStream2> {
-    let parsed = parse2::<ForwardedTokens>(tokens.into())?;
-    let target_path = parsed.target_path;
-    let imported_tokens = parsed.item;
-    let tokens_forwarded_keyword = keywords::__private_macro_magic_tokens_forwarded::default();
-    let pound = Punct::new('#', Spacing::Alone);
-    match parsed.extra {
-        // some extra, used by attr, so expand to attribute macro
-        Some(extra) => Ok(quote! {
-            #pound [#target_path(
-                #tokens_forwarded_keyword
-                #imported_tokens,
-                #extra
-            )] type __Discarded = ();
-        }),
-        // no extra, used by proc, import_tokens, etc, so expand to proc macro
-        None => Ok(quote! {
-            #target_path! {
-                #tokens_forwarded_keyword
-                #imported_tokens
-            }
-        }),
+impl syn::parse::Parse for FragmentKind {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let kind = input.parse::<Ident>()?;
+        match kind.to_string().as_str() {
+            "expr" => Ok(FragmentKind::Expr),
+            "ty" => Ok(FragmentKind::Type),
+            "pat" => Ok(FragmentKind::Pat),
+            "stmts" => Ok(FragmentKind::Stmts),
+            _ => Err(Error::new(
+                kind.span(),
+                "expected one of `expr`, `ty`, `pat` or `stmts`",
+            )),
+        }
     }
 }
 
-/// The internal implementation for the `#[with_custom_parsing(..)` attribute macro.
-///
-/// Note that this implementation just does parsing and re-orders the attributes of the
-/// attached proc macro attribute definition such that the `#[import_tokens_attr]` attribute
-/// comes before this attribute. The real implementation for `#[with_custom_parsing(..)]` can
-/// be found in [`import_tokens_attr_internal`]. The purpose of this is to allow programmers to
-/// use either ordering and still have the proper compiler errors when something is invalid.
-///
-/// The `import_tokens_att_name` argument is used when generating error messages and matching
-/// against the `#[import_tokens_attr]` macro this is to be used with. If you use a
-/// renamed/rebranded version of `#[import_tokens_attr]`, you should change this value to match
-/// the name of your macro.
-pub fn with_custom_parsing_internal<T1: Into<TokenStream2>, T2: Into<TokenStream2>>(
-    attr: T1,
-    tokens: T2,
-    import_tokens_attr_name: &'static str,
-) -> Result<TokenStream2> {
-    // verify that we are attached to a valid #[import_tokens_attr] proc macro def
-    let proc_macro = parse_proc_macro_variant(tokens, ProcMacroType::Attribute)?;
-    if proc_macro
-        .proc_fn
-        .attrs
-        .iter()
-        .find(|attr| {
-            if let Some(seg) = attr.meta.path().segments.last() {
-                return seg.ident == import_tokens_attr_name;
-            }
-            false
-        })
-        .is_none()
-    {
-        return Err(Error::new(
-            Span::call_site(),
-            format!(
-                "Can only be attached to an attribute proc macro marked with `#[{}]`",
-                import_tokens_attr_name
-            ),
-        ));
+impl ToTokens for FragmentKind {
+    fn to_tokens(&self, tokens: &mut TokenStream2) {
+        tokens.append(Ident::new(&self.to_string(), Span::call_site()));
     }
+}
 
-    // ensure there is only one `#[with_custom_parsing]`
-    if proc_macro
-        .proc_fn
-        .attrs
-        .iter()
-        .find(|attr| {
-            if let Some(seg) = attr.meta.path().segments.last() {
-                return seg.ident == "with_custom_parsing_internal";
-            }
-            false
+impl std::fmt::Display for FragmentKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            FragmentKind::Expr => "expr",
+            FragmentKind::Type => "ty",
+            FragmentKind::Pat => "pat",
+            FragmentKind::Stmts => "stmts",
         })
-        .is_some()
-    {
-        return Err(Error::new(
-            Span::call_site(),
-            "Only one instance of #[with_custom_parsing] can be attached at a time.",
-        ));
     }
+}
 
-    // parse attr to ensure it is a Path
-    let custom_path = parse2::<Path>(attr.into())?;
-
-    // emit original item unchanged now that parsing has passed
-    let mut item_fn = proc_macro.proc_fn;
-    item_fn
-        .attrs
-        .push(parse_quote!(#[with_custom_parsing(#custom_path)]));
+/// A fragment exported via `export_fragment!`. See [`ExportInfo::fragment`].
+#[derive(Clone)]
+pub enum Fragment {
+    /// An `expr` fragment.
+    Expr(syn::Expr),
+    /// A `ty` fragment.
+    Type(syn::Type),
+    /// A `pat` fragment.
+    Pat(syn::Pat),
+    /// A `stmts` fragment.
+    Stmts(Vec<syn::Stmt>),
+}
 
-    Ok(quote!(#item_fn))
+impl ToTokens for Fragment {
+    fn to_tokens(&self, tokens: &mut TokenStream2) {
+        match self {
+            Fragment::Expr(expr) => expr.to_tokens(tokens),
+            Fragment::Type(ty) => ty.to_tokens(tokens),
+            Fragment::Pat(pat) => pat.to_tokens(tokens),
+            Fragment::Stmts(stmts) => tokens.append_all(stmts),
+        }
+    }
 }
 
-/// Parses the (attribute) args of [`import_tokens_attr_internal`] and
-/// [`import_tokens_proc_internal`], which can now evaluate to either a `Path` or an `Expr`
-/// that is expected to be able to be placed in a `String::from(x)`.
-enum OverridePath {
-    Path(Path),
-    Expr(Expr),
+/// A `#[cfg(..)]` predicate found within an item exported with `#[export_tokens(cfg)]`,
+/// along with whether it was active in the exporting crate.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CfgPredicate {
+    /// The string representation of the tokens of the predicate, i.e. `feature = "std"`.
+    pub predicate: String,
+    /// Whether the predicate was active when the exporting crate was compiled.
+    pub active: bool,
 }
 
-impl syn::parse::Parse for OverridePath {
+impl syn::parse::Parse for CfgPredicate {
     fn parse(input: ParseStream) -> Result<Self> {
-        if input.is_empty() {
-            return Ok(OverridePath::Path(macro_magic_root()));
+        let predicate;
+        syn::parenthesized!(predicate in input);
+        let predicate = predicate.parse::<TokenStream2>()?.to_string();
+        input.parse::<Token![=]>()?;
+        let active = input.parse::<syn::LitBool>()?.value;
+        Ok(CfgPredicate { predicate, active })
+    }
+}
+
+/// Collects the distinct predicates of all `#[cfg(..)]` (and `#![cfg(..)]`) attributes found
+/// anywhere within the specified tokens, in the order in which they first appear.
+fn cfg_predicates(tokens: &TokenStream2) -> Vec<TokenStream2> {
+    fn walk(tokens: TokenStream2, predicates: &mut Vec<TokenStream2>) {
+        let mut after_pound = false;
+        for token in tokens {
+            match token {
+                TokenTree::Group(group) => {
+                    if after_pound && group.delimiter() == Delimiter::Bracket {
+                        let mut attr = group.stream().into_iter();
+                        if let (Some(TokenTree::Ident(ident)), Some(TokenTree::Group(args)), None) =
+                            (attr.next(), attr.next(), attr.next())
+                        {
+                            if ident == "cfg" && args.delimiter() == Delimiter::Parenthesis {
+                                let predicate = args.stream();
+                                let key = predicate.to_string();
+                                if !predicates.iter().any(|other| other.to_string() == key) {
+                                    predicates.push(predicate);
+                                }
+                                after_pound = false;
+                                continue;
+                            }
+                        }
+                    }
+                    walk(group.stream(), predicates);
+                    after_pound = false;
+                }
+                TokenTree::Punct(punct) if punct.as_char() == '#' => after_pound = true,
+                TokenTree::Punct(punct) if punct.as_char() == '!' && after_pound => (),
+                _ => after_pound = false,
+            }
         }
-        let mut remaining = TokenStream2::new();
-        while !input.is_empty() {
-            remaining.extend(input.parse::<TokenTree>()?.to_token_stream());
+    }
+    let mut predicates = Vec::new();
+    walk(tokens.clone(), &mut predicates);
+    predicates
+}
+
+/// Determines whether the specified attributes are active according to the `#[cfg(..)]`
+/// predicates recorded in `info`. Returns [`None`] if none of the attributes is a `#[cfg(..)]`
+/// attribute with a recorded predicate.
+fn cfg_active(attrs: &[Attribute], info: &ExportInfo) -> Option<bool> {
+    let mut result = None;
+    for attr in attrs {
+        let Some(active) = recorded_cfg(attr, info) else {
+            continue;
+        };
+        result = Some(result.unwrap_or(true) && active);
+    }
+    result
+}
+
+/// Looks up whether the specified attribute is a `#[cfg(..)]` attribute whose predicate is
+/// recorded in `info`, returning whether it is active if so.
+fn recorded_cfg(attr: &Attribute, info: &ExportInfo) -> Option<bool> {
+    if !attr.path().is_ident("cfg") {
+        return None;
+    }
+    let syn::Meta::List(list) = &attr.meta else {
+        return None;
+    };
+    let predicate = list.tokens.to_string();
+    info.cfg
+        .iter()
+        .find(|cfg| cfg.predicate == predicate)
+        .map(|cfg| cfg.active)
+}
+
+/// Strips `#[cfg(..)]` attributes whose predicates are recorded in `info` from `attrs`,
+/// returning `false` if any of them was inactive, in which case the owner of the attributes
+/// should be removed.
+fn retain_cfg(attrs: &mut Vec<Attribute>, info: &ExportInfo) -> bool {
+    let active = cfg_active(attrs, info).unwrap_or(true);
+    attrs.retain(|attr| recorded_cfg(attr, info).is_none());
+    active
+}
+
+/// Removes the fields, variants and nested items of `item` that are gated behind `#[cfg(..)]`
+/// predicates that were inactive in the exporting crate, according to the predicates recorded
+/// in `info` by `#[export_tokens(cfg)]`. The `#[cfg(..)]` attributes of everything that is
+/// kept are removed as well, so the item looks the way it did when the exporting crate was
+/// compiled. Predicates that were not recorded are left untouched.
+///
+/// Returns `false` if `item` itself is gated behind an inactive predicate.
+///
+/// ## Example
+///
+/// ```ignore
+/// #[import_tokens_attr]
+/// #[proc_macro_attribute]
+/// pub fn my_macro(attr: TokenStream, tokens: TokenStream) -> TokenStream {
+///     let mut foreign_item = parse_macro_input!(attr as Item);
+///     mm_core::strip_inactive_cfg(&mut foreign_item, &__export_info);
+///     // ..
+/// }
+/// ```
+pub fn strip_inactive_cfg(item: &mut Item, info: &ExportInfo) -> bool {
+    fn strip_fields(fields: &mut syn::Fields, info: &ExportInfo) {
+        let fields = match fields {
+            syn::Fields::Named(fields) => &mut fields.named,
+            syn::Fields::Unnamed(fields) => &mut fields.unnamed,
+            syn::Fields::Unit => return,
+        };
+        *fields = std::mem::take(fields)
+            .into_iter()
+            .filter_map(|mut field| retain_cfg(&mut field.attrs, info).then_some(field))
+            .collect();
+    }
+    fn strip_items(items: &mut Vec<Item>, info: &ExportInfo) {
+        items.retain_mut(|item| strip_inactive_cfg(item, info));
+    }
+    if let Some(attrs) = item_attrs_mut(item) {
+        if !retain_cfg(attrs, info) {
+            return false;
         }
-        if let Ok(path) = parse2::<Path>(remaining.clone()) {
-            return Ok(OverridePath::Path(path));
+    }
+    match item {
+        Item::Struct(item_struct) => strip_fields(&mut item_struct.fields, info),
+        Item::Union(item_union) => {
+            item_union.fields.named = std::mem::take(&mut item_union.fields.named)
+                .into_iter()
+                .filter_map(|mut field| retain_cfg(&mut field.attrs, info).then_some(field))
+                .collect();
         }
-        match parse2::<Expr>(remaining) {
-            Ok(expr) => Ok(OverridePath::Expr(expr)),
-            Err(mut err) => {
-                err.combine(Error::new(
-                    input.span(),
-                    "Expected either a `Path` or an `Expr` that evaluates to something compatible with `Into<String>`."
-                ));
-                Err(err)
+        Item::Enum(item_enum) => {
+            item_enum.variants = std::mem::take(&mut item_enum.variants)
+                .into_iter()
+                .filter_map(|mut variant| {
+                    if !retain_cfg(&mut variant.attrs, info) {
+                        return None;
+                    }
+                    strip_fields(&mut variant.fields, info);
+                    Some(variant)
+                })
+                .collect();
+        }
+        Item::Mod(item_mod) => {
+            if let Some((_, content)) = &mut item_mod.content {
+                strip_items(content, info);
             }
         }
+        Item::Impl(item_impl) => item_impl.items.retain_mut(|impl_item| match impl_item {
+            syn::ImplItem::Const(item) => retain_cfg(&mut item.attrs, info),
+            syn::ImplItem::Fn(item) => retain_cfg(&mut item.attrs, info),
+            syn::ImplItem::Type(item) => retain_cfg(&mut item.attrs, info),
+            syn::ImplItem::Macro(item) => retain_cfg(&mut item.attrs, info),
+            _ => true,
+        }),
+        Item::Trait(item_trait) => item_trait.items.retain_mut(|trait_item| match trait_item {
+            syn::TraitItem::Const(item) => retain_cfg(&mut item.attrs, info),
+            syn::TraitItem::Fn(item) => retain_cfg(&mut item.attrs, info),
+            syn::TraitItem::Type(item) => retain_cfg(&mut item.attrs, info),
+            syn::TraitItem::Macro(item) => retain_cfg(&mut item.attrs, info),
+            _ => true,
+        }),
+        Item::ForeignMod(item_foreign_mod) => {
+            item_foreign_mod
+                .items
+                .retain_mut(|foreign_item| match foreign_item {
+                    syn::ForeignItem::Fn(item) => retain_cfg(&mut item.attrs, info),
+                    syn::ForeignItem::Static(item) => retain_cfg(&mut item.attrs, info),
+                    syn::ForeignItem::Type(item) => retain_cfg(&mut item.attrs, info),
+                    syn::ForeignItem::Macro(item) => retain_cfg(&mut item.attrs, info),
+                    _ => true,
+                })
+        }
+        _ => (),
     }
+    true
 }
 
-impl ToTokens for OverridePath {
-    fn to_tokens(&self, tokens: &mut TokenStream2) {
-        match self {
-            OverridePath::Path(path) => {
-                let path = path.to_token_stream().to_string();
-                tokens.extend(quote!(#path))
+impl syn::parse::Parse for ExportInfo {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let content;
+        syn::bracketed!(content in input);
+        let mut info = ExportInfo::default();
+        while !content.is_empty() {
+            let key = content.parse::<Ident>()?;
+            match key.to_string().as_str() {
+                "group" => info.group = true,
+                "signature_only" => info.signature_only = true,
+                "source" => {
+                    let source;
+                    syn::parenthesized!(source in content);
+                    info.source = source.parse()?;
+                }
+                "hash" => {
+                    content.parse::<Token![=]>()?;
+                    info.hash = content.parse::<syn::LitStr>()?.value();
+                }
+                "version" => {
+                    content.parse::<Token![=]>()?;
+                    info.version = Some(content.parse::<syn::LitInt>()?.base10_parse()?);
+                }
+                "cfg" => {
+                    let cfg;
+                    syn::parenthesized!(cfg in content);
+                    info.cfg = cfg
+                        .parse_terminated(CfgPredicate::parse, Comma)?
+                        .into_iter()
+                        .collect();
+                }
+                "tags" => info.tags = parse_tags(&content)?,
+                "meta" => info.meta = parse_meta(&content)?,
+                "template" => info.placeholders = parse_tags(&content)?,
+                "fragment" => {
+                    content.parse::<Token![=]>()?;
+                    info.fragment_kind = Some(content.parse()?);
+                }
+                _ => return Err(Error::new(key.span(), "unknown export info key")),
+            }
+            if !content.is_empty() {
+                content.parse::<Comma>()?;
             }
-            OverridePath::Expr(expr) => tokens.extend(quote!(#expr)),
         }
+        Ok(info)
     }
 }
 
-/// Internal implementation for the `#[import_tokens_attr]` attribute.
+impl ToTokens for ExportInfo {
+    fn to_tokens(&self, tokens: &mut TokenStream2) {
+        tokens.extend(self.to_tokens_with_cfg(None));
+    }
+}
+
+/// Used to parse the (attribute) args of [`export_tokens_internal`], i.e. the optional
+/// override name and any options specified, such as `#[export_tokens(MyName, private)]`.
 ///
-/// You shouldn't need to use this directly, but it may be useful if you wish to rebrand/rename
-/// the `#[import_tokens_attr]` macro without extra indirection.
-pub fn import_tokens_attr_internal<T1: Into<TokenStream2>, T2: Into<TokenStream2>>(
-    attr: T1,
-    tokens: T2,
-) -> Result<TokenStream2> {
-    let attr = attr.into();
-    let mm_override_path = parse2::<OverridePath>(attr)?;
-    let mm_path = macro_magic_root();
-    let mut proc_macro = parse_proc_macro_variant(tokens, ProcMacroType::Attribute)?;
+/// You shouldn't need to use this directly.
+#[derive(Clone, Default)]
+pub struct ExportTokensArgs {
+    /// The override names that will be used to export the item instead of its regular name,
+    /// if any were specified. All of these refer to the same hidden export macro.
+    pub names: Vec<Ident>,
+    /// Set when `private` is specified. Private exports do not use `#[macro_export]`, and as a
+    /// result can be used inside function bodies and private modules. They are only
+    /// accessible from the same places the exported item itself is accessible from within the
+    /// current crate.
+    pub private: bool,
+    /// Set when `local` is specified. Local exports do not use `#[macro_export]` and are
+    /// instead re-exported via `pub(crate) use`, making them accessible by path from anywhere
+    /// within the current crate without adding anything to the crate root.
+    pub local: bool,
+    /// The version of the exported item, if specified via `version = N`. Importers can
+    /// require a particular version (or range of versions) via a [`VersionReq`].
+    pub version: Option<u64>,
+    /// Set when `recursive` is specified on an inline module, in which case every named child
+    /// item of the module (and of any nested inline modules) is exported individually as well.
+    pub recursive: bool,
+    /// Set when `cfg` is specified, in which case the [`ExportInfo`] of the export records
+    /// which of the `#[cfg(..)]` predicates found within the item were active in the
+    /// exporting crate. See [`strip_inactive_cfg`].
+    pub cfg: bool,
+    /// Controls which parts of the item are exported, as specified via the `strip_docs`,
+    /// `signature_only`, `public_fields_only` and `strip_attrs(..)` options.
+    pub projection: ExportProjection,
+    /// The tags specified via `tags(..)`, which are forwarded in the [`ExportInfo`].
+    pub tags: Vec<String>,
+    /// The key-value metadata specified via `meta(..)`, which is forwarded in the
+    /// [`ExportInfo`].
+    pub meta: BTreeMap<String, String>,
+    /// The prefix to use for the hidden callback macro instead of
+    /// [`MACRO_MAGIC_EXPORT_PREFIX`], if specified via `prefix = ".."`.
+    pub prefix: Option<String>,
+    /// The span of the `prefix = ".."` literal, if a prefix was specified, which errors about
+    /// the prefix are reported at.
+    pub prefix_span: Option<Span>,
+    /// The placeholders of the item if it is a template. These can't be specified directly,
+    /// instead [`export_template_internal`] passes them along via the hidden `__template(..)`
+    /// option.
+    pub placeholders: Vec<String>,
+}
 
-    // params
-    let attr_ident = proc_macro.attr_ident.unwrap();
-    let tokens_ident = proc_macro.tokens_ident;
+impl ExportTokensArgs {
+    /// Returns the prefix that should be used for the hidden callback macro.
+    pub fn prefix(&self) -> &str {
+        self.prefix.as_deref().unwrap_or(MACRO_MAGIC_EXPORT_PREFIX)
+    }
 
-    // handle custom parsing, if applicable
-    let path_resolver = if let Some(index) = proc_macro.proc_fn.attrs.iter().position(|attr| {
-        if let Some(seg) = attr.meta.path().segments.last() {
-            return seg.ident == "with_custom_parsing";
+    /// Sets the prefix to use for the hidden callback macro, as preset by aliases created via
+    /// `export_tokens_alias!(my_export, prefix = "..")`. Returns an error if a prefix was
+    /// already specified at the attribute site.
+    pub fn with_prefix(mut self, prefix: &str) -> Result<Self> {
+        if let Some(rejected) = &self.prefix {
+            return Err(Error::new(
+                self.prefix_span.unwrap_or_else(Span::call_site),
+                format!(
+                    "`prefix = \"{rejected}\"` can't be specified, since this attribute always \
+                    uses the prefix \"{prefix}\""
+                ),
+            ));
         }
-        false
-    }) {
-        let custom_attr = &proc_macro.proc_fn.attrs[index];
-        let custom_struct_path: Path = custom_attr.parse_args()?;
-
-        proc_macro.proc_fn.attrs.remove(index);
-        quote! {
-            let custom_parsed = syn::parse_macro_input!(#attr_ident as #custom_struct_path);
-            let path = (&custom_parsed as &dyn ForeignPath).foreign_path();
-            let _ = (&custom_parsed as &dyn quote::ToTokens);
-        }
-    } else {
-        quote! {
-            let custom_parsed = quote::quote!();
-            let path = syn::parse_macro_input!(#attr_ident as syn::Path);
-        }
-    };
-
-    // outer macro
-    let orig_sig = proc_macro.proc_fn.sig;
-    let orig_stmts = proc_macro.proc_fn.block.stmts;
-    let orig_attrs = proc_macro.proc_fn.attrs;
-    let orig_sig_ident = &orig_sig.ident;
-
-    // inner macro
-    let inner_macro_ident = format_ident!("__import_tokens_attr_{}_inner", orig_sig.ident);
-    let mut inner_sig = orig_sig.clone();
-    inner_sig.ident = inner_macro_ident.clone();
-    inner_sig.inputs.pop().unwrap();
+        self.prefix = Some(prefix.to_string());
+        Ok(self)
+    }
 
-    let pound = Punct::new('#', Spacing::Alone);
+    /// Applies the version, tags, metadata and placeholders specified by these args to `info`.
+    fn apply_to(&self, info: &mut ExportInfo) {
+        info.version = self.version;
+        info.tags = self.tags.clone();
+        info.meta = self.meta.clone();
+        info.placeholders = self.placeholders.clone();
+        info.signature_only = self.projection.signature_only;
+    }
+}
 
-    // final quoted tokens
-    let output = quote! {
-        #(#orig_attrs)
-        *
-        pub #orig_sig {
-            pub #inner_sig {
-                let __combined_args = #mm_path::__private::syn::parse_macro_input!(#attr_ident as #mm_path::mm_core::AttrItemWithExtra);
+/// Controls which parts of an item are included in the tokens exported by
+/// [`export_tokens_internal`], leaving the emitted item itself untouched. This keeps the
+/// generated macros small and avoids leaking implementation details to importers that only
+/// need the shape of an item.
+#[derive(Clone, Default)]
+pub struct ExportProjection {
+    /// Set when `strip_docs` is specified, in which case all doc comments are removed.
+    pub strip_docs: bool,
+    /// Set when `signature_only` is specified, in which case the bodies of functions and
+    /// methods are replaced by empty blocks, keeping them parseable as regular items, and the
+    /// default bodies of trait methods are removed. This is recorded in
+    /// [`ExportInfo::signature_only`], so importers can tell these apart from actual empty
+    /// bodies.
+    pub signature_only: bool,
+    /// Set when `public_fields_only` is specified, in which case only `pub` fields of structs
+    /// and unions are kept. Tuple structs are rejected, since removing some of their fields
+    /// would change the indices of the remaining ones.
+    pub public_fields_only: bool,
+    /// The paths of the attributes that should be removed, as specified via
+    /// `strip_attrs(..)`, i.e. `strip_attrs(derive, serde)`.
+    pub strip_attrs: Vec<Path>,
+}
 
-                let #attr_ident: proc_macro::TokenStream = __combined_args.imported_item.to_token_stream().into();
-                let #tokens_ident: proc_macro::TokenStream = __combined_args.tokens_ident.into();
-                let __source_path: proc_macro::TokenStream = __combined_args.source_path.into();
-                let __custom_tokens: proc_macro::TokenStream = __combined_args.custom_tokens.into();
+impl ExportProjection {
+    /// Returns `true` if this projection leaves items untouched.
+    pub fn is_empty(&self) -> bool {
+        !self.strip_docs
+            && !self.signature_only
+            && !self.public_fields_only
+            && self.strip_attrs.is_empty()
+    }
 
-                #(#orig_stmts)
-                *
+    /// Removes the attributes that are excluded by this projection from `attrs`.
+    fn retain_attrs(&self, attrs: &mut Vec<Attribute>) {
+        attrs.retain(|attr| {
+            let path = attr.path();
+            if self.strip_docs && path.is_ident("doc") {
+                return false;
             }
+            let path = path.to_token_stream().to_string();
+            !self
+                .strip_attrs
+                .iter()
+                .any(|strip| strip.to_token_stream().to_string() == path)
+        });
+    }
 
-            // This is to avoid corrupting the scope with imports below
-            fn isolated_mm_override_path() -> String {
-                String::from(#mm_override_path)
+    /// Applies this projection to the specified fields.
+    fn project_fields(&self, fields: &mut syn::Fields, public_only: bool) -> Result<()> {
+        let fields = match fields {
+            syn::Fields::Named(fields) => &mut fields.named,
+            syn::Fields::Unnamed(fields) if public_only && self.public_fields_only => {
+                return Err(Error::new(
+                    fields.span(),
+                    "`public_fields_only` can't be used with tuple structs, since removing \
+                    fields would change the indices of the remaining ones",
+                ));
             }
+            syn::Fields::Unnamed(fields) => &mut fields.unnamed,
+            syn::Fields::Unit => return Ok(()),
+        };
+        self.project_punctuated_fields(fields, public_only);
+        Ok(())
+    }
 
-            use #mm_path::__private::*;
-            use #mm_path::__private::quote::ToTokens;
-            use #mm_path::mm_core::*;
+    /// Applies this projection to the specified punctuated fields.
+    fn project_punctuated_fields(
+        &self,
+        fields: &mut syn::punctuated::Punctuated<syn::Field, Comma>,
+        public_only: bool,
+    ) {
+        if public_only && self.public_fields_only {
+            let trailing_punct = fields.trailing_punct();
+            *fields = std::mem::take(fields)
+                .into_iter()
+                .filter(|field| matches!(field.vis, Visibility::Public(_)))
+                .collect();
+            if trailing_punct && !fields.empty_or_trailing() {
+                fields.push_punct(Default::default());
+            }
+        }
+        for field in fields.iter_mut() {
+            self.retain_attrs(&mut field.attrs);
+        }
+    }
 
-            syn::custom_keyword!(__private_macro_magic_tokens_forwarded);
+    /// Applies this projection to the specified [`Item`], recursing into the contents of
+    /// `impl`s, `trait`s and inline modules.
+    pub fn project_item(&self, item: &mut Item) -> Result<()> {
+        if self.is_empty() {
+            return Ok(());
+        }
+        if let Some(attrs) = item_attrs_mut(item) {
+            self.retain_attrs(attrs);
+        }
+        match item {
+            Item::Struct(item_struct) => self.project_fields(&mut item_struct.fields, true)?,
+            Item::Union(item_union) => {
+                self.project_punctuated_fields(&mut item_union.fields.named, true)
+            }
+            Item::Enum(item_enum) => {
+                for variant in item_enum.variants.iter_mut() {
+                    self.retain_attrs(&mut variant.attrs);
+                    self.project_fields(&mut variant.fields, false)?;
+                }
+            }
+            Item::Fn(item_fn) if self.signature_only => item_fn.block = parse_quote!({}),
+            Item::Impl(item_impl) => {
+                for impl_item in item_impl.items.iter_mut() {
+                    self.project_impl_item(impl_item);
+                }
+            }
+            Item::Trait(item_trait) => {
+                for trait_item in item_trait.items.iter_mut() {
+                    self.project_trait_item(trait_item);
+                }
+            }
+            Item::Mod(item_mod) => {
+                if let Some((_, content)) = &mut item_mod.content {
+                    for item in content.iter_mut() {
+                        self.project_item(item)?;
+                    }
+                }
+            }
+            Item::ForeignMod(item_foreign_mod) => {
+                for foreign_item in item_foreign_mod.items.iter_mut() {
+                    match foreign_item {
+                        syn::ForeignItem::Fn(item) => self.retain_attrs(&mut item.attrs),
+                        syn::ForeignItem::Static(item) => self.retain_attrs(&mut item.attrs),
+                        syn::ForeignItem::Type(item) => self.retain_attrs(&mut item.attrs),
+                        syn::ForeignItem::Macro(item) => self.retain_attrs(&mut item.attrs),
+                        _ => (),
+                    }
+                }
+            }
+            _ => (),
+        }
+        Ok(())
+    }
 
-            let mut cloned_attr = #attr_ident.clone().into_iter();
-            let first_attr_token = cloned_attr.next();
-            let attr_minus_first_token = proc_macro::TokenStream::from_iter(cloned_attr);
+    /// Applies this projection to the specified [`syn::ImplItem`].
+    pub fn project_impl_item(&self, impl_item: &mut syn::ImplItem) {
+        match impl_item {
+            syn::ImplItem::Const(item) => self.retain_attrs(&mut item.attrs),
+            syn::ImplItem::Fn(item) => {
+                self.retain_attrs(&mut item.attrs);
+                if self.signature_only {
+                    item.block = parse_quote!({});
+                }
+            }
+            syn::ImplItem::Type(item) => self.retain_attrs(&mut item.attrs),
+            syn::ImplItem::Macro(item) => self.retain_attrs(&mut item.attrs),
+            _ => (),
+        }
+    }
 
-            let forwarded = first_attr_token.map_or(false, |token| {
-                syn::parse::<__private_macro_magic_tokens_forwarded>(token.into()).is_ok()
-            });
+    /// Applies this projection to the specified [`syn::TraitItem`].
+    pub fn project_trait_item(&self, trait_item: &mut syn::TraitItem) {
+        match trait_item {
+            syn::TraitItem::Const(item) => self.retain_attrs(&mut item.attrs),
+            syn::TraitItem::Fn(item) => {
+                self.retain_attrs(&mut item.attrs);
+                if self.signature_only && item.default.is_some() {
+                    item.default = None;
+                    item.semi_token = Some(Default::default());
+                }
+            }
+            syn::TraitItem::Type(item) => self.retain_attrs(&mut item.attrs),
+            syn::TraitItem::Macro(item) => self.retain_attrs(&mut item.attrs),
+            _ => (),
+        }
+    }
+}
 
-            if forwarded {
-                #inner_macro_ident(attr_minus_first_token)
+impl syn::parse::Parse for ExportTokensArgs {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let mut args = ExportTokensArgs::default();
+        while !input.is_empty() {
+            if input.peek(keywords::private) || input.peek(keywords::local) {
+                let keyword = input.parse::<Ident>()?;
+                args.private |= keyword == "private";
+                args.local |= keyword == "local";
+                if args.private && args.local {
+                    return Err(Error::new(
+                        keyword.span(),
+                        "`private` and `local` cannot be combined",
+                    ));
+                }
+            } else if input.peek(keywords::recursive) {
+                input.parse::<keywords::recursive>()?;
+                args.recursive = true;
+            } else if input.peek(keywords::cfg) {
+                input.parse::<keywords::cfg>()?;
+                args.cfg = true;
+            } else if input.peek(keywords::strip_docs) {
+                input.parse::<keywords::strip_docs>()?;
+                args.projection.strip_docs = true;
+            } else if input.peek(keywords::signature_only) {
+                input.parse::<keywords::signature_only>()?;
+                args.projection.signature_only = true;
+            } else if input.peek(keywords::public_fields_only) {
+                input.parse::<keywords::public_fields_only>()?;
+                args.projection.public_fields_only = true;
+            } else if input.peek(keywords::tags) && input.peek2(syn::token::Paren) {
+                input.parse::<keywords::tags>()?;
+                args.tags.extend(parse_tags(input)?);
+            } else if input.peek(keywords::meta) && input.peek2(syn::token::Paren) {
+                input.parse::<keywords::meta>()?;
+                args.meta.extend(parse_meta(input)?);
+            } else if input.peek(keywords::__template) && input.peek2(syn::token::Paren) {
+                input.parse::<keywords::__template>()?;
+                args.placeholders.extend(parse_tags(input)?);
+            } else if input.peek(keywords::strip_attrs) && input.peek2(syn::token::Paren) {
+                input.parse::<keywords::strip_attrs>()?;
+                let content;
+                syn::parenthesized!(content in input);
+                args.projection
+                    .strip_attrs
+                    .extend(content.parse_terminated(Path::parse_mod_style, Comma)?);
+            } else if input.peek(keywords::version) && input.peek2(Token![=]) {
+                input.parse::<keywords::version>()?;
+                input.parse::<Token![=]>()?;
+                args.version = Some(input.parse::<syn::LitInt>()?.base10_parse()?);
+            } else if input.peek(keywords::prefix) && input.peek2(Token![=]) {
+                let span = input.span();
+                let prefix = parse_prefix_literal(input)?;
+                if args.prefix.is_some() {
+                    return Err(Error::new(span, "`prefix` can only be specified once"));
+                }
+                args.prefix_span = prefix.as_ref().map(|prefix| prefix.span());
+                args.prefix = prefix.map(|prefix| prefix.value());
+                continue;
             } else {
-                let attached_item = syn::parse_macro_input!(#tokens_ident as syn::Item);
-                let attached_item = attached_item.to_token_stream();
-                #path_resolver
-                let path = path.to_token_stream();
-                let custom_parsed = custom_parsed.to_token_stream();
-                let mm_override_tokenstream = isolated_mm_override_path().parse().unwrap();
-                let resolved_mm_override_path = match syn::parse2::<syn::Path>(mm_override_tokenstream) {
-                    Ok(res) => res,
-                    Err(err) => return err.to_compile_error().into()
-                };
-                quote::quote! {
-                    #pound resolved_mm_override_path::forward_tokens! {
-                        #pound path,
-                        #orig_sig_ident,
-                        #pound resolved_mm_override_path,
-                        {
-                            { #pound attached_item },
-                            { #pound path },
-                            { #pound custom_parsed }
-                        }
-                    }
-                }.into()
+                args.names.push(input.parse::<Ident>()?);
+            }
+            if !input.is_empty() {
+                input.parse::<Comma>()?;
             }
         }
-    };
-    Ok(output)
+        Ok(args)
+    }
 }
 
-/// Internal implementation for the `#[import_tokens_proc]` attribute.
-///
-/// You shouldn't need to use this directly, but it may be useful if you wish to rebrand/rename
-/// the `#[import_tokens_proc]` macro without extra indirection.
-pub fn import_tokens_proc_internal<T1: Into<TokenStream2>, T2: Into<TokenStream2>>(
-    attr: T1,
-    tokens: T2,
-) -> Result<TokenStream2> {
-    let attr = attr.into();
-    let mm_override_path = parse2::<OverridePath>(attr)?;
-    let mm_path = macro_magic_root();
-    let proc_macro = parse_proc_macro_variant(tokens, ProcMacroType::Normal)?;
+/// Delineates the different types of proc macro
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum ProcMacroType {
+    /// Corresponds with `#[proc_macro]`
+    Normal,
+    /// Corresponds with `#[proc_macro_attribute]`
+    Attribute,
+    /// Corresponds with `#[proc_macro_derive]`
+    Derive,
+}
 
-    // outer macro
-    let orig_sig = proc_macro.proc_fn.sig;
-    let orig_stmts = proc_macro.proc_fn.block.stmts;
-    let orig_attrs = proc_macro.proc_fn.attrs;
-    let orig_sig_ident = &orig_sig.ident;
+impl ProcMacroType {
+    /// Gets the `&'static str` representation of this proc macro type
+    pub fn to_str(&self) -> &'static str {
+        match self {
+            ProcMacroType::Normal => "#[proc_macro]",
+            ProcMacroType::Attribute => "#[proc_macro_attribute]",
+            ProcMacroType::Derive => "#[proc_macro_derive]",
+        }
+    }
 
-    // inner macro
-    let inner_macro_ident = format_ident!("__import_tokens_proc_{}_inner", orig_sig.ident);
-    let mut inner_sig = orig_sig.clone();
-    inner_sig.ident = inner_macro_ident.clone();
-    inner_sig.inputs = inner_sig.inputs.iter().rev().cloned().collect();
+    /// Gets the [`Attribute`] representation of this proc macro type
+    pub fn to_attr(&self) -> Attribute {
+        match self {
+            ProcMacroType::Normal => parse_quote!(#[proc_macro]),
+            ProcMacroType::Attribute => parse_quote!(#[proc_macro_attribute]),
+            ProcMacroType::Derive => parse_quote!(#[proc_macro_derive]),
+        }
+    }
+}
 
-    // params
-    let tokens_ident = proc_macro.tokens_ident;
+/// Should be implemented by structs that will be passed to `#[with_custom_parsing(..)]`. Such
+/// structs should also implement [`syn::parse::Parse`].
+///
+/// ## Example
+///
+/// ```ignore
+/// #[derive(derive_syn_parse::Parse)]
+/// struct CustomParsingA {
+///     foreign_path: syn::Path,
+///     _comma: syn::token::Comma,
+///     custom_path: syn::Path,
+/// }
+///
+/// impl ToTokens for CustomParsingA {
+///     fn to_tokens(&self, tokens: &mut proc_macro2::TokenStream) {
+///         tokens.extend(self.foreign_path.to_token_stream());
+///         tokens.extend(self._comma.to_token_stream());
+///         tokens.extend(self.custom_path.to_token_stream());
+///     }
+/// }
+///
+/// impl ForeignPath for CustomParsingA {
+///     fn foreign_path(&self) -> &syn::Path {
+///         &self.foreign_path
+///     }
+/// }
+/// ```
+pub trait ForeignPath {
+    /// Returns the path of the foreign item whose tokens will be imported.
+    ///
+    /// This is used with custom parsing. See [`ForeignPath`] for more info.
+    fn foreign_path(&self) -> &syn::Path;
+}
 
-    let pound = Punct::new('#', Spacing::Alone);
+/// Like [`ForeignPath`], but for structs passed to `#[with_custom_parsing(..)]` that specify
+/// several foreign items whose tokens will be imported. Implemented automatically for all
+/// types that implement [`ForeignPath`].
+///
+/// The items are imported one after another, and the resulting attribute macro receives all
+/// of them, in the order in which their paths are returned.
+///
+/// ## Example
+///
+/// ```ignore
+/// #[derive(derive_syn_parse::Parse)]
+/// struct CustomParsingMulti {
+///     #[call(Punctuated::parse_separated_nonempty)]
+///     foreign_paths: Punctuated<syn::Path, Token![,]>,
+/// }
+///
+/// impl ForeignPaths for CustomParsingMulti {
+///     fn foreign_paths(&self) -> Vec<syn::Path> {
+///         self.foreign_paths.iter().cloned().collect()
+///     }
+/// }
+/// ```
+pub trait ForeignPaths {
+    /// Returns the paths of the foreign items whose tokens will be imported.
+    fn foreign_paths(&self) -> Vec<syn::Path>;
+}
 
-    // TODO: add support for forwarding source_path for these as well
+impl<T: ForeignPath> ForeignPaths for T {
+    fn foreign_paths(&self) -> Vec<syn::Path> {
+        vec![self.foreign_path().clone()]
+    }
+}
 
-    Ok(quote! {
-        #(#orig_attrs)
-        *
-        pub #orig_sig {
-            #inner_sig {
-                #(#orig_stmts)
-                *
-            }
+/// The default parser used for the `attr` of attribute macros generated by
+/// [`import_tokens_attr_internal`] that don't use `#[with_custom_parsing(..)]`, i.e. one or
+/// more comma-separated paths of foreign items, such as `a::Foo, b::Bar`.
+pub struct ForeignPathList {
+    /// The paths of the foreign items whose tokens will be imported.
+    pub paths: syn::punctuated::Punctuated<Path, Comma>,
+}
 
-            // This is to avoid corrupting the scope with imports below
-            fn isolated_mm_override_path() -> String {
-                String::from(#mm_override_path)
+impl syn::parse::Parse for ForeignPathList {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let mut paths = syn::punctuated::Punctuated::new();
+        paths.push_value(parse_item_path(input)?);
+        while let Some(comma) = input.parse::<Option<Comma>>()? {
+            paths.push_punct(comma);
+            if input.is_empty() {
+                break;
             }
+            paths.push_value(parse_item_path(input)?);
+        }
+        Ok(ForeignPathList { paths })
+    }
+}
 
-            use #mm_path::__private::*;
-            use #mm_path::__private::quote::ToTokens;
-
-            syn::custom_keyword!(__private_macro_magic_tokens_forwarded);
-
-            let mut cloned_tokens = #tokens_ident.clone().into_iter();
-            let first_token = cloned_tokens.next();
-            let tokens_minus_first = proc_macro::TokenStream::from_iter(cloned_tokens);
+impl ToTokens for ForeignPathList {
+    fn to_tokens(&self, tokens: &mut TokenStream2) {
+        self.paths.to_tokens(tokens);
+    }
+}
 
-            let forwarded = first_token.map_or(false, |token| {
-                syn::parse::<__private_macro_magic_tokens_forwarded>(token.into()).is_ok()
-            });
+impl ForeignPaths for ForeignPathList {
+    fn foreign_paths(&self) -> Vec<syn::Path> {
+        self.paths.iter().cloned().collect()
+    }
+}
 
-            if forwarded {
-                #inner_macro_ident(tokens_minus_first)
-            } else {
-                use #mm_path::__private::*;
-                use #mm_path::__private::quote::ToTokens;
-                let source_path = match syn::parse::<syn::Path>(#tokens_ident) {
-                    Ok(path) => path,
-                    Err(e) => return e.to_compile_error().into(),
-                };
-                let mm_override_tokenstream = isolated_mm_override_path().parse().unwrap();
-                let resolved_mm_override_path = match syn::parse2::<syn::Path>(mm_override_tokenstream) {
-                    Ok(res) => res,
-                    Err(err) => return err.to_compile_error().into()
+/// Generically parses a proc macro definition with support for all variants.
+#[derive(Clone)]
+pub struct ProcMacro {
+    /// The underlying proc macro function definition
+    pub proc_fn: ItemFn,
+    /// Specified the type of this proc macro, i.e. attribute vs normal vs derive
+    pub macro_type: ProcMacroType,
+    /// Specifies the [`struct@Ident`] for the `tokens` parameter of this proc macro function
+    /// definition. For normal and derive macros this is the only parameter, and for attribute
+    /// macros this is the second parameter.
+    pub tokens_ident: Ident,
+    /// Specifies the [`struct@Ident`] for the `attr` parameter of this proc macro function
+    /// definition, if it is an attribute macro. Otherwise this will be set to [`None`].
+    pub attr_ident: Option<Ident>,
+}
+
+impl ProcMacro {
+    /// Constructs a [`ProcMacro`] from anything compatible with [`TokenStream2`].
+    pub fn from<T: Into<TokenStream2>>(tokens: T) -> Result<Self> {
+        let proc_fn = parse2::<ItemFn>(tokens.into())?;
+        let Visibility::Public(_) = proc_fn.vis else {
+            return Err(Error::new(proc_fn.vis.span(), "Visibility must be public"));
+        };
+        let mut macro_type: Option<ProcMacroType> = None;
+        if proc_fn
+            .attrs
+            .iter()
+            .find(|attr| {
+                if syn::parse2::<keywords::proc_macro>(attr.path().to_token_stream()).is_ok() {
+                    macro_type = Some(ProcMacroType::Normal);
+                } else if syn::parse2::<keywords::proc_macro_attribute>(
+                    attr.path().to_token_stream(),
+                )
+                .is_ok()
+                {
+                    macro_type = Some(ProcMacroType::Attribute);
+                } else if syn::parse2::<keywords::proc_macro_derive>(attr.path().to_token_stream())
+                    .is_ok()
+                {
+                    macro_type = Some(ProcMacroType::Derive);
+                }
+                macro_type.is_some()
+            })
+            .is_none()
+        {
+            return Err(Error::new(
+                proc_fn.sig.ident.span(),
+                "can only be attached to a proc macro function definition",
+            ));
+        };
+        let macro_type = macro_type.unwrap();
+
+        // tokens_ident
+        let Some(FnArg::Typed(tokens_arg)) = proc_fn.sig.inputs.last() else {
+            unreachable!("missing tokens arg");
+        };
+        let Pat::Ident(tokens_ident) = *tokens_arg.pat.clone() else {
+            unreachable!("invalid tokens arg");
+        };
+        let tokens_ident = tokens_ident.ident;
+
+        // attr_ident (if applicable)
+        let attr_ident = match macro_type {
+            ProcMacroType::Attribute => {
+                let Some(FnArg::Typed(attr_arg)) = proc_fn.sig.inputs.first() else {
+                    unreachable!("missing attr arg");
                 };
-                quote::quote! {
-                    #pound resolved_mm_override_path::forward_tokens! {
-                        #pound source_path,
-                        #orig_sig_ident,
-                        #pound resolved_mm_override_path
+                let Pat::Ident(attr_ident) = *attr_arg.pat.clone() else {
+                    unreachable!("invalid attr arg");
+                };
+                Some(attr_ident.ident)
+            }
+            _ => None,
+        };
+        Ok(ProcMacro {
+            proc_fn,
+            macro_type,
+            tokens_ident,
+            attr_ident,
+        })
+    }
+}
+
+/// Parses a proc macro function from a `TokenStream2` expecting only the specified `macro_type`
+pub fn parse_proc_macro_variant<T: Into<TokenStream2>>(
+    tokens: T,
+    macro_type: ProcMacroType,
+) -> Result<ProcMacro> {
+    let proc_macro = ProcMacro::from(tokens.into())?;
+    if proc_macro.macro_type != macro_type {
+        let actual = proc_macro.macro_type.to_str();
+        let desired = macro_type.to_str();
+        return Err(Error::new(
+            proc_macro.proc_fn.sig.ident.span(),
+            format!(
+                "expected a function definition with {} but found {} instead",
+                desired, actual
+            ),
+        ));
+    }
+    Ok(proc_macro)
+}
+
+/// Safely access the `macro_magic` root based on the `MACRO_MAGIC_ROOT` env var, which
+/// defaults to `::macro_magic`, but can be configured via the `[env]` section of
+/// `.cargo/config.toml`
+pub fn macro_magic_root() -> Path {
+    parse2::<Path>(
+        MACRO_MAGIC_ROOT
+            .parse::<TokenStream2>()
+            .expect("environment var `MACRO_MAGIC_ROOT` must parse to a valid TokenStream2"),
+    )
+    .expect("environment variable `MACRO_MAGIC_ROOT` must parse to a valid syn::Path")
+}
+
+/// Safely access a subpath of `macro_magic::__private`
+pub fn private_path<T: Into<TokenStream2> + Clone>(subpath: &T) -> Path {
+    let subpath = subpath.clone().into();
+    let root = macro_magic_root();
+    parse_quote!(#root::__private::#subpath)
+}
+
+/// Safely access a subpath of `macro_magic`
+pub fn macro_magic_path<T: Into<TokenStream2> + Clone>(subpath: &T) -> Path {
+    let subpath = subpath.clone().into();
+    let root = macro_magic_root();
+    parse_quote! {
+        #root::#subpath
+    }
+}
+
+/// Returns the specified string in snake_case
+pub fn to_snake_case(input: impl Into<String>) -> String {
+    let input: String = input.into();
+    if input.is_empty() {
+        return input;
+    }
+    let mut prev_lower = input.chars().next().unwrap().is_lowercase();
+    let mut prev_whitespace = true;
+    let mut first = true;
+    let mut output: Vec<char> = Vec::new();
+    for c in input.chars() {
+        if c == '_' {
+            prev_whitespace = true;
+            output.push('_');
+            continue;
+        }
+        if !c.is_ascii_alphanumeric() && c != '_' && !c.is_whitespace() {
+            continue;
+        }
+        if !first && c.is_whitespace() || c == '_' {
+            if !prev_whitespace {
+                output.push('_');
+            }
+            prev_whitespace = true;
+        } else {
+            let current_lower = c.is_lowercase();
+            if ((prev_lower != current_lower && prev_lower)
+                || (prev_lower == current_lower && !prev_lower))
+                && !first
+                && !prev_whitespace
+            {
+                output.push('_');
+            }
+            output.push(c.to_ascii_lowercase());
+            prev_lower = current_lower;
+            prev_whitespace = false;
+        }
+        first = false;
+    }
+    output.iter().collect::<String>()
+}
+
+/// "Flattens" an [`struct@Ident`] by converting it to snake case.
+///
+/// Used by [`export_tokens_macro_ident`].
+pub fn flatten_ident(ident: &Ident) -> Ident {
+    Ident::new(to_snake_case(ident.to_string()).as_str(), ident.span())
+}
+
+/// Produces the full path for the auto-generated callback-based decl macro that allows us to
+/// forward tokens across crate boundaries.
+///
+/// Used by [`export_tokens_internal`] and several other functions.
+pub fn export_tokens_macro_ident(ident: &Ident) -> Ident {
+    export_tokens_macro_ident_with_prefix(ident, MACRO_MAGIC_EXPORT_PREFIX)
+}
+
+/// Like [`export_tokens_macro_ident`], but uses the specified prefix instead of
+/// [`MACRO_MAGIC_EXPORT_PREFIX`].
+pub fn export_tokens_macro_ident_with_prefix(ident: &Ident, prefix: &str) -> Ident {
+    let ident = flatten_ident(ident);
+    let ident_string = format!("{prefix}{}", ident.to_token_stream());
+    Ident::new(ident_string.as_str(), Span::call_site())
+}
+
+/// Produces the combined [`struct@Ident`] used to export an associated item (a method,
+/// associated const or associated type) that lives inside the `impl` or `trait` whose
+/// [`associated_owner_name`] is `owner`, i.e. `my_type` + `my_method` becomes
+/// `my_type_my_method` and `my_trait` + `CONST` becomes `my_trait_const`.
+///
+/// Used by [`export_tokens_internal`] and [`parse_item_path`].
+pub fn associated_export_ident(owner: &str, ident: &Ident) -> Ident {
+    let name = name_to_snake_case(ident.unraw().to_string());
+    Ident::new(&format!("{owner}_{name}"), ident.span())
+}
+
+/// Returns the name that the associated items of an `impl` or `trait` are exported under, i.e.
+/// `my_type` for `impl MyType`, `display_for_my_type` for `impl Display for MyType` and
+/// `my_trait` for `trait MyTrait`, or [`None`] if the type has no sensible name. Including
+/// the trait keeps the associated items of different trait impls on the same type, such as
+/// the `fmt` of both `Display` and `Debug`, apart. Generic arguments of the trait are included
+/// as well, so `impl From<u8> for MyType` and `impl From<u16> for MyType` become
+/// `from_u8_for_my_type` and `from_u16_for_my_type` respectively, while those of the type are
+/// not, so `impl<T> MyType<T>` becomes `my_type`.
+///
+/// The associated items of a trait definition are exported under the name of the trait, which
+/// is what `trait_path` being [`None`] and `self_ty` naming the trait results in.
+pub fn associated_owner_name(self_ty: &syn::Type, trait_path: Option<&Path>) -> Option<String> {
+    let self_ty = type_name(self_ty, false)?;
+    match trait_path {
+        Some(path) => Some(format!(
+            "{}_for_{self_ty}",
+            segment_name(path.segments.last()?)
+        )),
+        None => Some(self_ty),
+    }
+}
+
+/// Parses the path of an exported item.
+///
+/// This is either a regular [`Path`], such as `my_crate::MyItem`, or the path of an associated
+/// item exported from within an `impl` or `trait`, which must be written using qualified path
+/// syntax, i.e. `<my_crate::MyType>::my_method`, `<my_crate::MyType as Display>::fmt` or
+/// `<my_crate::MyTrait>::CONST`. The latter is desugared into a regular path to the combined
+/// export name of the associated item (see [`associated_export_ident`]), i.e.
+/// `my_crate::display_for_my_type_fmt`, which can then be passed to
+/// [`export_tokens_macro_path`] like any other item path.
+pub fn parse_item_path(input: ParseStream) -> Result<Path> {
+    if !input.peek(Token![<]) {
+        return input.parse();
+    }
+    input.parse::<Token![<]>()?;
+    let self_ty = input.parse::<syn::TypePath>()?;
+    let trait_path = match input.parse::<Option<Token![as]>>()? {
+        Some(_) => Some(input.parse::<Path>()?),
+        None => None,
+    };
+    input.parse::<Token![>]>()?;
+    input.parse::<Token![::]>()?;
+    let ident = input.parse::<Ident>()?;
+    let Some(owner) = associated_owner_name(&syn::Type::Path(self_ty.clone()), trait_path.as_ref())
+    else {
+        return Err(Error::new(
+            self_ty.span(),
+            "expected the path of a type or trait",
+        ));
+    };
+    let mut path = self_ty.path;
+    path.segments.pop();
+    path.segments
+        .push(associated_export_ident(&owner, &ident).into());
+    Ok(path)
+}
+
+/// Resolves to the path of the `#[export_tokens]` macro for the given item path.
+///
+/// Paths to associated items are written using qualified path syntax, i.e.
+/// `<my_crate::MyType>::my_method`, and have to be parsed via [`parse_item_path`] first.
+///
+/// If the specified [`Path`] doesn't exist or there isn't a valid `#[export_tokens]` attribute
+/// on the item at that path, the returned macro path will be invalid.
+pub fn export_tokens_macro_path(item_path: &Path) -> Path {
+    export_tokens_macro_path_with_prefix(item_path, MACRO_MAGIC_EXPORT_PREFIX)
+}
+
+/// Like [`export_tokens_macro_path`], but uses the specified prefix instead of
+/// [`MACRO_MAGIC_EXPORT_PREFIX`], for items that were exported with a custom
+/// `prefix = ".."`.
+pub fn export_tokens_macro_path_with_prefix(item_path: &Path, prefix: &str) -> Path {
+    let mut macro_path = item_path.clone();
+    let Some(last_seg) = macro_path.segments.pop() else {
+        unreachable!("must have at least one segment")
+    };
+    let last_seg = export_tokens_macro_ident_with_prefix(&last_seg.into_value().ident, prefix);
+    macro_path.segments.push(last_seg.into());
+    macro_path
+}
+
+/// Generates a new unique `#[export_tokens]` macro identifier.
+///
+/// The ident is derived deterministically from the crate name and version, the module path
+/// and file of the exported item described by `source`, the name of the item and
+/// `content_hash`, so it is stable across builds, doesn't change when unrelated code is edited
+/// and doesn't depend on any state shared between macro invocations, while still avoiding
+/// collisions between the hidden `#[macro_export]` macros of different crates that are glob
+/// imported into the same scope.
+///
+/// Items generated by a `macro_rules!` macro additionally include the macro expansion they
+/// originate from (see [`span_context`]), so that a macro expanding the same export into
+/// several modules doesn't produce the same hidden macro twice. Identical items written out
+/// by hand in different inline modules of the same file still collide, in which case one of
+/// them needs an override name.
+fn new_unique_export_tokens_ident(
+    ident: &Ident,
+    prefix: &str,
+    source: &SourceInfo,
+    content_hash: &str,
+) -> Ident {
+    let context = span_context(ident.span());
+    let ident = flatten_ident(ident).to_token_stream().to_string();
+    let crate_name = &source.crate_name;
+    let crate_version = &source.crate_version;
+    let module_path = &source.module_path;
+    let file = &source.file;
+    let tag = tokens_hash(&quote! {
+        #ident, #crate_name, #crate_version, #module_path, #file, #content_hash, #context
+    });
+    Ident::new(&format!("{prefix}{tag}_{ident}"), Span::call_site())
+}
+
+/// Returns the id of the hygiene context of the specified [`Span`], which identifies the macro
+/// expansion the tokens it belongs to originate from, or `0` for tokens that were written out
+/// directly in the source (or when running outside of a proc macro context).
+///
+/// There is no stable API for this, so it is read from the [`Debug`] representation of the
+/// span, i.e. `#6 bytes(181..187)`. The ids are assigned in expansion order, which is
+/// deterministic for a given crate.
+fn span_context(span: Span) -> u64 {
+    format!("{span:?}")
+        .strip_prefix('#')
+        .and_then(|rest| rest.split_once(' '))
+        .and_then(|(context, _)| context.parse().ok())
+        .unwrap_or(0)
+}
+
+/// Generates the hidden callback macro containing the specified item tokens, along with a
+/// `use` making it accessible at the module level under each of the normalized `idents`. Any
+/// `idents` that normalize to the same name are only re-exported once.
+///
+/// When `local_vis` is [`None`], the callback macro is a `#[macro_export]` macro and is
+/// re-exported via `pub use`. Otherwise it is a regular `macro_rules!` macro re-exported with
+/// the specified visibility, which keeps it out of the crate root and allows it to be used in
+/// places `#[macro_export]` can't reach, such as function bodies and private modules.
+///
+/// If `cfg` is set, the `#[cfg(..)]` predicates found within the item are evaluated in the
+/// current crate and recorded in the forwarded [`ExportInfo`]. Since a proc macro can't
+/// evaluate predicates itself, this is done by a chain of helper macros with alternative
+/// `#[cfg(..)]`-gated definitions, one per predicate, which accumulate `true` or `false` for
+/// each predicate and finally define the callback macro.
+///
+/// Used by [`export_tokens_internal`].
+fn export_tokens_macro(
+    idents: &[Ident],
+    item: &TokenStream2,
+    info: &ExportInfo,
+    local_vis: Option<&Visibility>,
+    cfg: bool,
+    prefix: &str,
+) -> TokenStream2 {
+    let macro_ident = new_unique_export_tokens_ident(&idents[0], prefix, &info.source, &info.hash);
+    let (macro_export, vis) = match local_vis {
+        Some(vis) => (quote!(), quote!(#vis)),
+        None => (quote!(#[macro_export]), quote!(pub)),
+    };
+    let mut export_idents: Vec<Ident> = Vec::new();
+    let mut uses = Vec::new();
+    for ident in idents {
+        let export_ident = export_tokens_macro_ident_with_prefix(ident, prefix);
+        if !export_idents.contains(&export_ident) {
+            // points "defined multiple times" errors at the colliding names
+            let mut macro_ident = macro_ident.clone();
+            macro_ident.set_span(ident.span());
+            let mut export_ident = export_ident.clone();
+            export_ident.set_span(ident.span());
+            uses.push(quote_spanned!(ident.span()=> #vis use #macro_ident as #export_ident;));
+            uses.push(export_collision_guard(&export_ident));
+            export_idents.push(export_ident);
+        }
+    }
+    let predicates = match cfg {
+        true => cfg_predicates(item),
+        false => Vec::new(),
+    };
+    if predicates.is_empty() {
+        return quote! {
+            #[doc(hidden)]
+            #macro_export
+            macro_rules! #macro_ident {
+                (
+                    $(::)?$($tokens_var:ident)::*,
+                    $(::)?$($callback:ident)::*
+                    $(, $( $extra:tt )*)?
+                ) => {
+                    $($callback)::*! {
+                        $($tokens_var)::*,
+                        #item,
+                        #info
+                        $(, $( $extra )*)?
                     }
-                }.into()
+                };
+            }
+            #(#uses)*
+        };
+    }
+    let helpers: Vec<Ident> = (0..=predicates.len())
+        .map(|i| format_ident!("{}_cfg_{}", macro_ident, i))
+        .collect();
+    let actives: Vec<Ident> = (0..predicates.len())
+        .map(|i| format_ident!("active_{}", i))
+        .collect();
+    let cfg_helpers = predicates.iter().enumerate().map(|(i, predicate)| {
+        let helper = &helpers[i];
+        let next = &helpers[i + 1];
+        quote! {
+            #[cfg(#predicate)]
+            macro_rules! #helper {
+                ($d:tt $($acc:tt)*) => { #next! { $d $($acc)* true } };
+            }
+            #[cfg(not(#predicate))]
+            macro_rules! #helper {
+                ($d:tt $($acc:tt)*) => { #next! { $d $($acc)* false } };
             }
         }
-    })
+    });
+    let info = info.to_tokens_with_cfg(Some(quote!(#((#predicates) = $#actives),*)));
+    let first = &helpers[0];
+    let last = &helpers[predicates.len()];
+    quote! {
+        #(#cfg_helpers)*
+        macro_rules! #last {
+            ($d:tt #($#actives:tt)*) => {
+                #[doc(hidden)]
+                #macro_export
+                macro_rules! #macro_ident {
+                    (
+                        $d(::)?$d($d tokens_var:ident)::*,
+                        $d(::)?$d($d callback:ident)::*
+                        $d(, $d( $d extra:tt )*)?
+                    ) => {
+                        $d($d callback)::*! {
+                            $d($d tokens_var)::*,
+                            #item,
+                            #info
+                            $d(, $d( $d extra )*)?
+                        }
+                    };
+                }
+            };
+        }
+        #first! { $ }
+        #(#uses)*
+    }
+}
+
+/// Generates a hidden `const` named after the specified export ident, such that separate
+/// exports under the same name in the same module (i.e. `MyCoolName` and `my_cool_name`) fail
+/// with an error explaining the collision, pointing at both names. No single expansion can
+/// detect these, and the "defined multiple times" error of the re-export itself (which is
+/// reported alongside) is rather cryptic.
+fn export_collision_guard(export_ident: &Ident) -> TokenStream2 {
+    let guard_ident = format_ident!(
+        "{}_is_exported_more_than_once_in_this_module",
+        export_ident,
+        span = export_ident.span()
+    );
+    quote_spanned! {export_ident.span()=>
+        #[doc(hidden)]
+        #[allow(dead_code, non_upper_case_globals)]
+        const #guard_ident: () = ();
+    }
+}
+
+/// Determines the visibility that should be used to re-export the callback macro of a
+/// `private` export, which mirrors the visibility of the exported item itself. Since
+/// non-`#[macro_export]` macros can't be re-exported outside of the current crate, `pub` is
+/// narrowed to `pub(crate)`.
+fn private_export_vis(item: &Item) -> Visibility {
+    let vis = match item {
+        Item::Const(item) => &item.vis,
+        Item::Enum(item) => &item.vis,
+        Item::ExternCrate(item) => &item.vis,
+        Item::Fn(item) => &item.vis,
+        Item::Mod(item) => &item.vis,
+        Item::Static(item) => &item.vis,
+        Item::Struct(item) => &item.vis,
+        Item::Trait(item) => &item.vis,
+        Item::TraitAlias(item) => &item.vis,
+        Item::Type(item) => &item.vis,
+        Item::Union(item) => &item.vis,
+        Item::Use(item) => &item.vis,
+        _ => &Visibility::Inherited,
+    };
+    match vis {
+        Visibility::Public(_) => parse_quote!(pub(crate)),
+        vis => vis.clone(),
+    }
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+/// Determines the visibility the callback macro of an export should be re-exported with, as
+/// used by [`export_tokens_macro`], based on the `private` and `local` options.
+fn export_local_vis(args: &ExportTokensArgs, item: &Item) -> Option<Visibility> {
+    if args.local {
+        Some(parse_quote!(pub(crate)))
+    } else if args.private {
+        Some(private_export_vis(item))
+    } else {
+        None
+    }
+}
+
+/// Returns the inherent [`struct@Ident`] of the specified [`Item`], if it has one.
+fn item_ident(item: &Item) -> Option<Ident> {
+    match item {
+        Item::Const(item_const) => Some(item_const.ident.clone()),
+        Item::Enum(item_enum) => Some(item_enum.ident.clone()),
+        Item::ExternCrate(item_extern_crate) => Some(item_extern_crate.ident.clone()),
+        Item::Fn(item_fn) => Some(item_fn.sig.ident.clone()),
+        Item::Macro(item_macro) => item_macro.ident.clone(), // note this one might not have an Ident as well
+        Item::Mod(item_mod) => Some(item_mod.ident.clone()),
+        Item::Static(item_static) => Some(item_static.ident.clone()),
+        Item::Struct(item_struct) => Some(item_struct.ident.clone()),
+        Item::Trait(item_trait) => Some(item_trait.ident.clone()),
+        Item::TraitAlias(item_trait_alias) => Some(item_trait_alias.ident.clone()),
+        Item::Type(item_type) => Some(item_type.ident.clone()),
+        Item::Union(item_union) => Some(item_union.ident.clone()),
+        // Item::ForeignMod(item_foreign_mod) => None,
+        // Item::Use(item_use) => None,
+        // Item::Impl(item_impl) => None,
+        // Item::Verbatim(_) => None,
+        _ => None,
+    }
+}
+
+/// Returns the name an item is exported as by [`export_tokens_internal`] when no override
+/// name is specified, or [`None`] if a name can't be derived for it.
+///
+/// Items with an inherent [`struct@Ident`] are exported under that ident. For items without
+/// one, a name is derived as follows:
+///
+/// * `impl Display for Foo` becomes `display_for_foo`.
+/// * `impl From<u8> for Foo` becomes `from_u8_for_foo`.
+/// * `impl<T> Foo<T>` becomes `impl_foo`.
+/// * `use std::fmt::Display;` becomes `use_std_fmt_display`, using the first path in the case
+///   of a group, i.e. `use std::{fmt, io};` becomes `use_std_fmt`.
+/// * `extern "C" { fn foo(); }` becomes `extern_c_foo`, using the ABI (defaulting to `C`) and
+///   the first item of the block.
+///
+/// Importers can use this together with [`export_tokens_macro_path`] to compute the path of
+/// the export of an item they have the tokens of.
+pub fn export_ident_for_item(item: &Item) -> Option<Ident> {
+    if let Some(ident) = item_ident(item) {
+        return Some(ident);
+    }
+    let name = match item {
+        Item::Impl(item_impl) => {
+            let trait_path = item_impl.trait_.as_ref().map(|(_, path, _)| path);
+            let owner = associated_owner_name(&item_impl.self_ty, trait_path)?;
+            match trait_path {
+                Some(_) => owner,
+                None => format!("impl_{owner}"),
+            }
+        }
+        Item::Use(item_use) => {
+            let mut segments = Vec::new();
+            let mut tree = &item_use.tree;
+            loop {
+                match tree {
+                    syn::UseTree::Path(path) => {
+                        segments.push(path.ident.unraw().to_string());
+                        tree = &path.tree;
+                    }
+                    syn::UseTree::Name(name) => {
+                        break segments.push(name.ident.unraw().to_string())
+                    }
+                    syn::UseTree::Rename(rename) => {
+                        break segments.push(rename.rename.unraw().to_string())
+                    }
+                    syn::UseTree::Glob(_) => break,
+                    syn::UseTree::Group(group) => tree = group.items.first()?,
+                }
+            }
+            if segments.is_empty() {
+                return None;
+            }
+            let segments: Vec<String> = segments.into_iter().map(name_to_snake_case).collect();
+            format!("use_{}", segments.join("_"))
+        }
+        Item::ForeignMod(item_foreign_mod) => {
+            let abi = match &item_foreign_mod.abi.name {
+                Some(name) => name
+                    .value()
+                    .replace(|c: char| !c.is_ascii_alphanumeric(), "_"),
+                None => String::from("C"),
+            };
+            let first = match item_foreign_mod.items.first()? {
+                syn::ForeignItem::Fn(item) => &item.sig.ident,
+                syn::ForeignItem::Static(item) => &item.ident,
+                syn::ForeignItem::Type(item) => &item.ident,
+                _ => return None,
+            };
+            format!(
+                "extern_{}_{}",
+                name_to_snake_case(abi),
+                name_to_snake_case(first.unraw().to_string())
+            )
+        }
+        _ => return None,
+    };
+    Some(Ident::new(&name, item.span()))
+}
+
+/// Like [`to_snake_case`], but lowercases names that are entirely uppercase (such as the names
+/// of constants and statics) instead of separating each of their letters.
+fn name_to_snake_case(name: String) -> String {
+    match name.chars().any(char::is_lowercase) {
+        true => to_snake_case(name),
+        false => name.to_lowercase(),
+    }
+}
+
+/// Returns the snake_case name of the specified type, i.e. `foo` for `&'a Foo`, or [`None`] if
+/// the type has no sensible name. If `generics` is `true`, the generic arguments of the type
+/// are included as well, i.e. `foo_t` for `Foo<T>` rather than `foo`.
+fn type_name(ty: &syn::Type, generics: bool) -> Option<String> {
+    let name = |elem: &syn::Type| type_name(elem, generics);
+    match ty {
+        syn::Type::Path(type_path) => {
+            let segment = type_path.path.segments.last()?;
+            Some(match generics {
+                true => segment_name(segment),
+                false => ident_name(&segment.ident),
+            })
+        }
+        syn::Type::Reference(reference) => name(&reference.elem),
+        syn::Type::Ptr(ptr) => name(&ptr.elem),
+        syn::Type::Paren(paren) => name(&paren.elem),
+        syn::Type::Group(group) => name(&group.elem),
+        syn::Type::Slice(slice) => name(&slice.elem).map(|name| format!("{name}_slice")),
+        syn::Type::Array(array) => name(&array.elem).map(|name| format!("{name}_array")),
+        _ => None,
+    }
+}
+
+/// Returns the snake_case name of the specified [`struct@Ident`]. Names that are already
+/// lowercase, such as those of primitives, are kept as they are.
+fn ident_name(ident: &Ident) -> String {
+    let ident = ident.unraw().to_string();
+    match ident.chars().any(char::is_uppercase) {
+        true => name_to_snake_case(ident),
+        false => ident,
+    }
+}
+
+/// Returns the snake_case name of the specified [`PathSegment`](syn::PathSegment), including
+/// its generic arguments, i.e. `from_u8` for `From<u8>` and `fn_u32_u64` for
+/// `Fn(u32) -> u64`. Lifetimes and arguments without a sensible name are skipped, and names
+/// that are already lowercase, such as those of primitives, are kept as they are.
+fn segment_name(segment: &syn::PathSegment) -> String {
+    let mut names = vec![ident_name(&segment.ident)];
+    match &segment.arguments {
+        syn::PathArguments::None => (),
+        syn::PathArguments::AngleBracketed(args) => {
+            names.extend(args.args.iter().filter_map(|arg| match arg {
+                syn::GenericArgument::Type(ty) => type_name(ty, true),
+                syn::GenericArgument::AssocType(assoc) => type_name(&assoc.ty, true),
+                syn::GenericArgument::Const(Expr::Lit(syn::ExprLit {
+                    lit: syn::Lit::Int(int),
+                    ..
+                })) => Some(int.base10_digits().to_string()),
+                syn::GenericArgument::Const(Expr::Path(path)) => {
+                    let ident = path.path.segments.last()?.ident.unraw();
+                    Some(name_to_snake_case(ident.to_string()))
+                }
+                _ => None,
+            }))
+        }
+        syn::PathArguments::Parenthesized(args) => {
+            names.extend(args.inputs.iter().filter_map(|ty| type_name(ty, true)));
+            if let syn::ReturnType::Type(_, ty) = &args.output {
+                names.extend(type_name(ty, true));
+            }
+        }
+    }
+    names.join("_")
+}
+
+/// Returns the attributes of the specified [`Item`].
+fn item_attrs(item: &Item) -> &[Attribute] {
+    match item {
+        Item::Const(item) => &item.attrs,
+        Item::Enum(item) => &item.attrs,
+        Item::ExternCrate(item) => &item.attrs,
+        Item::Fn(item) => &item.attrs,
+        Item::ForeignMod(item) => &item.attrs,
+        Item::Impl(item) => &item.attrs,
+        Item::Macro(item) => &item.attrs,
+        Item::Mod(item) => &item.attrs,
+        Item::Static(item) => &item.attrs,
+        Item::Struct(item) => &item.attrs,
+        Item::Trait(item) => &item.attrs,
+        Item::TraitAlias(item) => &item.attrs,
+        Item::Type(item) => &item.attrs,
+        Item::Union(item) => &item.attrs,
+        Item::Use(item) => &item.attrs,
+        _ => &[],
+    }
+}
+
+/// Returns the attributes of the specified [`Item`] mutably, if it has any.
+fn item_attrs_mut(item: &mut Item) -> Option<&mut Vec<Attribute>> {
+    match item {
+        Item::Const(item) => Some(&mut item.attrs),
+        Item::Enum(item) => Some(&mut item.attrs),
+        Item::ExternCrate(item) => Some(&mut item.attrs),
+        Item::Fn(item) => Some(&mut item.attrs),
+        Item::ForeignMod(item) => Some(&mut item.attrs),
+        Item::Impl(item) => Some(&mut item.attrs),
+        Item::Macro(item) => Some(&mut item.attrs),
+        Item::Mod(item) => Some(&mut item.attrs),
+        Item::Static(item) => Some(&mut item.attrs),
+        Item::Struct(item) => Some(&mut item.attrs),
+        Item::Trait(item) => Some(&mut item.attrs),
+        Item::TraitAlias(item) => Some(&mut item.attrs),
+        Item::Type(item) => Some(&mut item.attrs),
+        Item::Union(item) => Some(&mut item.attrs),
+        Item::Use(item) => Some(&mut item.attrs),
+        _ => None,
+    }
+}
+
+/// Exports every named child item of the specified inline module individually, by inserting
+/// their callback macros into the module itself so they can be accessed via paths like
+/// `my_crate::my_mod::ChildItem`. Nested inline modules are handled recursively. Child items
+/// that are already marked with `#[export_tokens]` are skipped, since they export themselves.
+///
+/// Used by [`export_tokens_internal`] when `recursive` is specified, in which case the
+/// `transform` of the export is run on each child item as well.
+fn insert_recursive_exports<F>(
+    item: &mut Item,
+    args: &ExportTokensArgs,
+    transform: &F,
+) -> Result<()>
+where
+    F: Fn(Item) -> Result<Item>,
+{
+    let Item::Mod(item_mod) = item else {
+        return Err(Error::new(
+            item.span(),
+            "`recursive` can only be used with modules",
+        ));
+    };
+    let span = item_mod.span();
+    let Some((_, content)) = &mut item_mod.content else {
+        return Err(Error::new(
+            span,
+            "`recursive` can only be used with inline modules, i.e. `mod my_mod { .. }`",
+        ));
+    };
+    let exports = named_item_exports(content, args, None, transform)?;
+    for child in content.iter_mut() {
+        if let Item::Mod(syn::ItemMod {
+            content: Some(_),
+            attrs,
+            ..
+        }) = child
+        {
+            if !attrs.iter().any(is_export_tokens_attr) {
+                insert_recursive_exports(child, args, transform)?;
+            }
+        }
+    }
+    content.extend(exports.into_iter().map(Item::Verbatim));
+    Ok(())
+}
+
+/// Generates the callback macros exporting each of the specified items that has an inherent
+/// name individually, skipping items that are already marked with `#[export_tokens]`. If
+/// `source` is specified, it contains the [`SourceInfo`] of the file the items were parsed
+/// from along with its contents, and is used as the [`SourceInfo`] of each item instead of
+/// the one derived from its span, with the line and column of each item located via
+/// [`locate_item_name`]. The `transform` is run on the exported copy of each item, after any
+/// projections have been applied.
+///
+/// Used by [`insert_recursive_exports`] and [`export_tokens_file_internal_with_transform`].
+fn named_item_exports<F>(
+    items: &[Item],
+    args: &ExportTokensArgs,
+    source: Option<(&SourceInfo, &str)>,
+    transform: &F,
+) -> Result<Vec<TokenStream2>>
+where
+    F: Fn(Item) -> Result<Item>,
+{
+    let items: Vec<(&Item, Ident)> = items
+        .iter()
+        .filter(|item| !item_attrs(item).iter().any(is_export_tokens_attr))
+        .filter_map(|item| Some((item, item_ident(item)?)))
+        .collect();
+    let names: Vec<&[Ident]> = items
+        .iter()
+        .map(|(_, ident)| std::slice::from_ref(ident))
+        .collect();
+    check_export_collisions(&names, args.prefix())?;
+    let mut exports = Vec::new();
+    let mut offset = 0;
+    for (item, ident) in items {
+        let local_vis = export_local_vis(args, item);
+        let mut item = item.clone();
+        args.projection.project_item(&mut item)?;
+        let item = transform(item)?;
+        let mut info = ExportInfo::for_item(&item);
+        args.apply_to(&mut info);
+        if let Some((source, contents)) = source {
+            info.source = source.clone();
+            if let Some((end, line, column)) = locate_item_name(contents, &ident, offset) {
+                (offset, info.source.line, info.source.column) = (end, line, column);
+            }
+        }
+        let tokens = item.to_token_stream();
+        exports.push(export_tokens_macro(
+            std::slice::from_ref(&ident),
+            &tokens,
+            &info,
+            local_vis.as_ref(),
+            args.cfg,
+            args.prefix(),
+        ));
+    }
+    Ok(exports)
+}
+
+/// Locates the name of the top-level item named `ident` in the specified source file
+/// `contents`, searching from the byte `offset` onwards, and returns the byte offset just past
+/// the name along with the (1-based) line and (0-based) column the name is on.
+///
+/// Tokens parsed from a string within a proc macro are all spanned at the call site, so this
+/// is the only way to recover where the items of a file exported via
+/// [`export_tokens_file_internal`] are defined. Only occurrences directly following an item
+/// keyword (i.e. `struct`, `fn` or `macro_rules!`) are considered, and since the items of a
+/// file are visited in order, a name mentioned within an earlier item is skipped as well.
+fn locate_item_name(contents: &str, ident: &Ident, offset: usize) -> Option<(usize, usize, usize)> {
+    const KEYWORDS: [&str; 11] = [
+        "const",
+        "crate",
+        "enum",
+        "fn",
+        "macro_rules!",
+        "mod",
+        "static",
+        "struct",
+        "trait",
+        "type",
+        "union",
+    ];
+    let is_ident_char = |c: char| c == '_' || c.is_alphanumeric();
+    let name = ident.to_string();
+    let start = contents[offset..]
+        .match_indices(&name)
+        .map(|(start, _)| offset + start)
+        .find(|&start| {
+            let before = contents[..start].trim_end();
+            !contents[..start].ends_with(is_ident_char)
+                && !contents[start + name.len()..].starts_with(is_ident_char)
+                && KEYWORDS.iter().any(|keyword| {
+                    before
+                        .strip_suffix(keyword)
+                        .is_some_and(|rest| !rest.ends_with(is_ident_char))
+                })
+        })?;
+    let line_start = contents[..start]
+        .rfind('\n')
+        .map_or(0, |newline| newline + 1);
+    let line = contents[..start].matches('\n').count() + 1;
+    let column = contents[line_start..start].chars().count();
+    Some((start + name.len(), line, column))
+}
+
+/// Returns a friendly error naming both items if any two of the specified exports collide
+/// once normalized via [`to_snake_case`], such as `MyCoolName` and `my_cool_name`. Each entry
+/// of `exports` contains the names of a single export, which may normalize to the same name
+/// among themselves.
+///
+/// Only the exports generated by a single expansion can be checked this way, i.e. an `impl`
+/// along with its associated items or the child items of a module exported via `recursive`.
+/// Collisions between separate expansions are caught by the [`export_collision_guard`] emitted
+/// along with each export instead.
+fn check_export_collisions(exports: &[&[Ident]], prefix: &str) -> Result<()> {
+    let mut exported: Vec<(Ident, &Ident)> = Vec::new();
+    for names in exports {
+        let mut own = Vec::new();
+        for ident in names.iter() {
+            let export_ident = export_tokens_macro_ident_with_prefix(ident, prefix);
+            if let Some((_, existing)) = exported.iter().find(|(other, _)| *other == export_ident) {
+                return Err(Error::new(
+                    ident.span(),
+                    format!(
+                        "`{ident}` collides with `{existing}` when exported, since both are \
+                        exported as `{export_ident}`, consider specifying a different export \
+                        name via `#[export_tokens(SomeOtherName)]`"
+                    ),
+                ));
+            }
+            own.push((export_ident, ident));
+        }
+        exported.extend(own);
+    }
+    Ok(())
+}
+
+/// Returns `true` if the specified [`Attribute`] is an `#[export_tokens]` attribute.
+fn is_export_tokens_attr(attr: &Attribute) -> bool {
+    attr.path()
+        .segments
+        .last()
+        .is_some_and(|seg| seg.ident == "export_tokens")
+}
+
+/// Removes the first `#[export_tokens]` attribute found in `attrs` (if any), returning the
+/// override names specified by its argument, or `ident` if no override name was specified.
+fn take_export_tokens_attr(
+    attrs: &mut Vec<Attribute>,
+    ident: &Ident,
+) -> Result<Option<Vec<Ident>>> {
+    let Some(index) = attrs.iter().position(is_export_tokens_attr) else {
+        return Ok(None);
+    };
+    let attr = attrs.remove(index);
+    let args = match attr.meta {
+        syn::Meta::Path(_) => ExportTokensArgs::default(),
+        _ => attr.parse_args::<ExportTokensArgs>()?,
+    };
+    match args.names.is_empty() {
+        true => Ok(Some(vec![ident.clone()])),
+        false => Ok(Some(args.names)),
+    }
+}
+
+/// Finds any associated items (methods, associated consts and associated types) within the
+/// specified `impl` or `trait` that have been marked with `#[export_tokens]`, removing these
+/// inner attributes and returning the combined export [`struct@Ident`]s, tokens and
+/// [`ExportInfo`] for each.
+fn take_associated_exports(
+    item: &mut Item,
+    projection: &ExportProjection,
+) -> Result<Vec<(Vec<Ident>, TokenStream2, ExportInfo)>> {
+    let mut exports = Vec::new();
+    match item {
+        Item::Impl(item_impl) => {
+            let trait_path = item_impl.trait_.as_ref().map(|(_, path, _)| path);
+            let Some(owner) = associated_owner_name(&item_impl.self_ty, trait_path) else {
+                return Ok(exports);
+            };
+            for impl_item in item_impl.items.iter_mut() {
+                let (attrs, ident) = match impl_item {
+                    syn::ImplItem::Const(item) => (&mut item.attrs, &item.ident),
+                    syn::ImplItem::Fn(item) => (&mut item.attrs, &item.sig.ident),
+                    syn::ImplItem::Type(item) => (&mut item.attrs, &item.ident),
+                    _ => continue,
+                };
+                if let Some(names) = take_export_tokens_attr(attrs, ident)? {
+                    let idents = names
+                        .iter()
+                        .map(|name| associated_export_ident(&owner, name))
+                        .collect();
+                    let mut impl_item = impl_item.clone();
+                    projection.project_impl_item(&mut impl_item);
+                    let mut info = ExportInfo::for_item(&impl_item);
+                    info.signature_only = projection.signature_only;
+                    exports.push((idents, impl_item.to_token_stream(), info));
+                }
+            }
+        }
+        Item::Trait(item_trait) => {
+            let owner = name_to_snake_case(item_trait.ident.unraw().to_string());
+            for trait_item in item_trait.items.iter_mut() {
+                let (attrs, ident) = match trait_item {
+                    syn::TraitItem::Const(item) => (&mut item.attrs, &item.ident),
+                    syn::TraitItem::Fn(item) => (&mut item.attrs, &item.sig.ident),
+                    syn::TraitItem::Type(item) => (&mut item.attrs, &item.ident),
+                    _ => continue,
+                };
+                if let Some(names) = take_export_tokens_attr(attrs, ident)? {
+                    let idents = names
+                        .iter()
+                        .map(|name| associated_export_ident(&owner, name))
+                        .collect();
+                    let mut trait_item = trait_item.clone();
+                    projection.project_trait_item(&mut trait_item);
+                    let mut info = ExportInfo::for_item(&trait_item);
+                    info.signature_only = projection.signature_only;
+                    exports.push((idents, trait_item.to_token_stream(), info));
+                }
+            }
+        }
+        _ => (),
+    }
+    Ok(exports)
+}
+
+/// The internal code behind the `#[export_tokens]` attribute macro.
+///
+/// The `attr` variable contains the tokens for the optional naming [`struct@Ident`] (necessary
+/// on [`Item`]s for which [`export_ident_for_item`] can't derive a name), and the `tokens`
+/// variable is the tokens for the [`Item`] the attribute macro can be attached to. The `attr`
+/// variable can be blank tokens for supported items, which include every valid [`syn::Item`]
+/// except for [`Item::Verbatim`] and unnamed macro invocations, which require `attr` to be
+/// specified.
+///
+/// If the [`Item`] is an `impl` or `trait`, any associated items within it that are marked
+/// with `#[export_tokens]` (optionally specifying an override name) will also be exported
+/// individually, such that they can be accessed via qualified paths like
+/// `<my_crate::MyType>::my_method` (see [`parse_item_path`]).
+///
+/// The generated macro forwards an [`ExportInfo`] along with the tokens of the item, which
+/// captures the [`SourceInfo`] of the item.
+///
+/// An empty [`TokenStream2`] is sufficient for opting out of using `attr`
+pub fn export_tokens_internal<T: Into<TokenStream2>, E: Into<TokenStream2>>(
+    attr: T,
+    tokens: E,
+    emit: bool,
+) -> Result<TokenStream2> {
+    export_tokens_internal_with_transform(attr, tokens, emit, Ok)
+}
+
+/// Like [`export_tokens_internal`], but runs the specified `transform` on the exported copy of
+/// the item (after any projections have been applied) before it is embedded in the generated
+/// export macro. The item that is emitted locally is left untouched. Associated items that are
+/// exported individually are taken from the transformed copy of their `impl` or `trait`, and
+/// the child items of `recursive` exports are transformed individually as well.
+///
+/// This is what aliases created via `export_tokens_alias!(my_export, transform = ..)` use to
+/// let frameworks enforce their own conventions on every item exported through them. Errors
+/// returned by the transform are reported at the attribute site.
+pub fn export_tokens_internal_with_transform<T, E, F>(
+    attr: T,
+    tokens: E,
+    emit: bool,
+    transform: F,
+) -> Result<TokenStream2>
+where
+    T: Into<TokenStream2>,
+    E: Into<TokenStream2>,
+    F: Fn(Item) -> Result<Item>,
+{
+    let args = parse2::<ExportTokensArgs>(attr.into())?;
+    export_tokens_internal_with_args(args, tokens, emit, transform)
+}
+
+/// Like [`export_tokens_internal_with_transform`], but takes already parsed
+/// [`ExportTokensArgs`] rather than the tokens of the attribute. This is what aliases created
+/// via `export_tokens_alias!` use to apply the args preset by the alias.
+pub fn export_tokens_internal_with_args<E, F>(
+    args: ExportTokensArgs,
+    tokens: E,
+    emit: bool,
+    transform: F,
+) -> Result<TokenStream2>
+where
+    E: Into<TokenStream2>,
+    F: Fn(Item) -> Result<Item>,
+{
+    let mut item: Item = parse2(tokens.into())?;
+    let ident = export_ident_for_item(&item);
+    let idents = match (args.names.is_empty(), ident) {
+        (false, _) => args.names.clone(),
+        (true, Some(ident)) => vec![ident],
+        (true, None) => {
+            return Err(Error::new(
+                item.span(),
+                "an override name must be specified for this type of item, \
+                i.e. `#[export_tokens(SomeName)]`",
+            ))
+        }
+    };
+    if args.recursive && !emit {
+        return Err(Error::new(
+            item.span(),
+            "`recursive` exports require the module to be emitted",
+        ));
+    }
+    let local_vis = export_local_vis(&args, &item);
+    let mut exported_item = item.clone();
+    args.projection.project_item(&mut exported_item)?;
+    let mut exported_item = transform(exported_item)?;
+    // associated items are exported from the transformed copy so that the transform applies
+    // to them as well, but their `#[export_tokens]` attributes are removed from both copies
+    let associated_exports = take_associated_exports(&mut exported_item, &args.projection)?;
+    take_associated_exports(&mut item, &args.projection)?;
+    let names: Vec<&[Ident]> = std::iter::once(idents.as_slice())
+        .chain(
+            associated_exports
+                .iter()
+                .map(|(idents, _, _)| idents.as_slice()),
+        )
+        .collect();
+    check_export_collisions(&names, args.prefix())?;
+    let associated_exports = associated_exports
+        .into_iter()
+        .map(|(idents, tokens, mut info)| {
+            args.apply_to(&mut info);
+            export_tokens_macro(
+                &idents,
+                &tokens,
+                &info,
+                local_vis.as_ref(),
+                args.cfg,
+                args.prefix(),
+            )
+        });
+    let mut info = ExportInfo::for_item(&exported_item);
+    args.apply_to(&mut info);
+    let export = export_tokens_macro(
+        &idents,
+        &exported_item.to_token_stream(),
+        &info,
+        local_vis.as_ref(),
+        args.cfg,
+        args.prefix(),
+    );
+    if args.recursive {
+        insert_recursive_exports(&mut item, &args, &transform)?;
+    }
+    let item_emit = match emit {
+        true => quote! {
+            #[allow(unused)]
+            #item
+        },
+        false => quote!(),
+    };
+    let output = quote! {
+        #export
+        #(#associated_exports)*
+        #item_emit
+    };
+    Ok(output)
+}
+
+/// The internal implementation for the `export_tokens_file!` macro.
+///
+/// Reads the Rust source file at the specified path (relative to `CARGO_MANIFEST_DIR`),
+/// parses it as a [`syn::File`], and generates the same hidden callback macros
+/// [`export_tokens_internal`] would generate for each named top-level item in the file, such
+/// that they can be imported from the module the macro was invoked in. The items themselves
+/// are not emitted. An `include_str!` of the file is emitted as well so that the exports are
+/// regenerated whenever the file changes.
+///
+/// The [`SourceInfo`] of each exported item refers to the file at the specified path and the
+/// line and column of the name of the item within it, along with the module path of the module
+/// the macro was invoked in. The same file can be exported from several modules.
+pub fn export_tokens_file_internal<T: Into<TokenStream2>>(tokens: T) -> Result<TokenStream2> {
+    export_tokens_file_internal_with_transform(tokens, Ok)
+}
+
+/// Like [`export_tokens_file_internal`], but runs the specified `transform` on the exported
+/// copy of each item, like [`export_tokens_internal_with_transform`] does.
+pub fn export_tokens_file_internal_with_transform<T, F>(
+    tokens: T,
+    transform: F,
+) -> Result<TokenStream2>
+where
+    T: Into<TokenStream2>,
+    F: Fn(Item) -> Result<Item>,
+{
+    let (path, prefix) =
+        (|input: ParseStream| Ok((input.parse::<syn::LitStr>()?, parse_prefix_option(input)?)))
+            .parse2(tokens.into())?;
+    let manifest_dir = std::env::var("CARGO_MANIFEST_DIR").unwrap_or_default();
+    let full_path = std::path::Path::new(&manifest_dir).join(path.value());
+    let source = std::fs::read_to_string(&full_path).map_err(|err| {
+        Error::new(
+            path.span(),
+            format!("failed to read `{}`: {err}", full_path.display()),
+        )
+    })?;
+    let file = syn::parse_file(&source).map_err(|err| {
+        Error::new(
+            path.span(),
+            format!("failed to parse `{}`: {err}", full_path.display()),
+        )
+    })?;
+    // the module path is that of the invoking module, the line and column are filled in per
+    // item. Since the items are parsed from a string, their idents are spanned at the call
+    // site and thus carry the hygiene context of this invocation (see `span_context`), which
+    // keeps the hidden macros of the same file exported from several modules apart.
+    let mut source_info = SourceInfo::from_span(path.span());
+    source_info.file = path.value();
+    source_info.line = 0;
+    source_info.column = 0;
+    let args = ExportTokensArgs {
+        prefix,
+        ..Default::default()
+    };
+    let exports = named_item_exports(
+        &file.items,
+        &args,
+        Some((&source_info, &source)),
+        &transform,
+    )?;
+    let full_path = full_path.to_string_lossy();
+    Ok(quote! {
+        const _: &str = include_str!(#full_path);
+        #(#exports)*
+    })
+}
+
+/// Used to parse the args of [`export_group_internal`], i.e.
+/// `api_types = [crate::User, crate::Account, crate::billing::Invoice]`.
+///
+/// You shouldn't need to use this directly.
+#[derive(Parse)]
+pub struct ExportGroupArgs {
+    /// The name the group is exported as
+    pub name: Ident,
+    _eq: Token![=],
+    #[bracket]
+    _bracket: syn::token::Bracket,
+    /// The paths of the items that belong to the group
+    #[inside(_bracket)]
+    #[call(syn::punctuated::Punctuated::parse_terminated)]
+    pub members: syn::punctuated::Punctuated<Path, Comma>,
+    /// The prefix used by the group and its members instead of
+    /// [`MACRO_MAGIC_EXPORT_PREFIX`], if specified via a trailing `prefix = ".."`.
+    #[call(parse_prefix_option)]
+    pub prefix: Option<String>,
+}
+
+/// Ensures that the path of a member of an export group is absolute, i.e. starts with `crate`
+/// or is a global path, since the `#[macro_export]` macro generated by
+/// [`export_group_internal`] can be invoked from anywhere and a proc macro has no way of
+/// knowing which module it was invoked in.
+fn check_group_member(path: &Path) -> Result<()> {
+    let is_absolute = path.leading_colon.is_some()
+        || path
+            .segments
+            .first()
+            .is_some_and(|segment| segment.ident == "crate" || segment.ident == "$crate");
+    match is_absolute {
+        true => Ok(()),
+        false => Err(Error::new(
+            path.span(),
+            "export group members must be referred to via absolute paths, i.e. \
+            `crate::my_module::Item` or `::other_crate::Item`",
+        )),
+    }
+}
+
+/// Generates the tokens of the specified export macro path for use within the body of a
+/// `#[macro_export]` macro, replacing a leading `crate` with `$crate`.
+fn dollar_crate_path(path: &Path) -> TokenStream2 {
+    let mut segments = path.segments.iter();
+    match segments.next() {
+        Some(first) if path.leading_colon.is_none() && first.ident == "crate" => {
+            quote!($crate #(::#segments)*)
+        }
+        _ => path.to_token_stream(),
+    }
+}
+
+/// The internal implementation for the `export_group!` macro.
+///
+/// Generates a single hidden callback macro (exported under the name of the group) that
+/// collects the tokens of each member of the group one after another via
+/// [`export_group_inner_internal`], and then forwards them all at once as an inline module
+/// containing the items of the group, along with an [`ExportInfo`] with
+/// [`ExportInfo::group`] set. Importers can use [`ExportInfo::group_items`] to get at the
+/// individual items.
+///
+/// Member paths must be absolute, i.e. start with `crate` or be global paths such as
+/// `::other_crate::Item`, since the generated macro can be invoked from anywhere.
+pub fn export_group_internal<T: Into<TokenStream2>>(tokens: T) -> Result<TokenStream2> {
+    let args = parse2::<ExportGroupArgs>(tokens.into())?;
+    let name = &args.name;
+    if args.members.is_empty() {
+        return Err(Error::new(
+            name.span(),
+            "an export group must contain at least one item",
+        ));
+    }
+    let source = SourceInfo::from_span(name.span());
+    let prefix = args.prefix.as_deref().unwrap_or(MACRO_MAGIC_EXPORT_PREFIX);
+    let members = args
+        .members
+        .iter()
+        .map(|member| {
+            check_group_member(member)?;
+            let path = export_tokens_macro_path_with_prefix(member, prefix);
+            Ok(dollar_crate_path(&path))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let info = ExportInfo {
+        source,
+        group: true,
+        ..Default::default()
+    };
+    let members_hash = tokens_hash(&quote!(#(#members),*));
+    let macro_ident = new_unique_export_tokens_ident(name, prefix, &info.source, &members_hash);
+    let export_ident = export_tokens_macro_ident_with_prefix(name, prefix);
+    let guard = export_collision_guard(&export_ident);
+    let inner_macro_path = private_path(&quote!(export_group_inner));
+    let first = &members[0];
+    let rest = &members[1..];
+    Ok(quote! {
+        #[doc(hidden)]
+        #[macro_export]
+        macro_rules! #macro_ident {
+            (
+                $(::)?$($tokens_var:ident)::*,
+                $(::)?$($callback:ident)::*
+                $(, $( $extra:tt )*)?
+            ) => {
+                #first! {
+                    __mm_group,
+                    #inner_macro_path,
+                    #name,
+                    [#(#rest),*],
+                    [],
+                    #info,
+                    { $($tokens_var)::* },
+                    { $($callback)::* },
+                    { $($( $extra )*)? }
+                }
+            };
+        }
+        pub use #macro_ident as #export_ident;
+        #guard
+    })
+}
+
+/// Used to parse the args passed to [`export_group_inner_internal`] by the callback macro of
+/// each member of an export group.
+///
+/// You shouldn't need to use this directly.
+#[derive(Parse)]
+pub struct ExportGroupState {
+    _tokens_var: Ident,
+    _comma1: Comma,
+    /// The tokens of the member of the group that was just forwarded
+    pub item: Item,
+    _comma2: Comma,
+    _member_info: ExportInfo,
+    _comma3: Comma,
+    /// The name of the group
+    pub name: Ident,
+    _comma4: Comma,
+    #[bracket]
+    _remaining_bracket: syn::token::Bracket,
+    /// The comma-separated export macro paths of the members of the group that have yet to
+    /// be forwarded. These are kept as raw tokens since they may start with `$crate`.
+    #[inside(_remaining_bracket)]
+    pub remaining: TokenStream2,
+    _comma5: Comma,
+    #[bracket]
+    _collected_bracket: syn::token::Bracket,
+    /// The items of the group that have been forwarded so far
+    #[inside(_collected_bracket)]
+    pub collected: TokenStream2,
+    _comma6: Comma,
+    /// The [`ExportInfo`] of the group
+    pub info: ExportInfo,
+    _comma7: Comma,
+    #[brace]
+    _tokens_var_brace: Brace,
+    /// The tokens variable originally passed to the group
+    #[inside(_tokens_var_brace)]
+    pub tokens_var: TokenStream2,
+    _comma8: Comma,
+    #[brace]
+    _callback_brace: Brace,
+    /// The callback originally passed to the group
+    #[inside(_callback_brace)]
+    pub callback: TokenStream2,
+    _comma9: Comma,
+    #[brace]
+    _extra_brace: Brace,
+    /// The extra tokens originally passed to the group, if any
+    #[inside(_extra_brace)]
+    pub extra: TokenStream2,
+}
+
+/// Internal implementation behind the hidden `export_group_inner!` macro, which is invoked by
+/// the callback macro of each member of an export group in turn, collecting their tokens. Once
+/// every member has been collected, the original callback is invoked with an inline module
+/// containing all of the collected items. See [`export_group_internal`].
+pub fn export_group_inner_internal<T: Into<TokenStream2>>(tokens: T) -> Result<TokenStream2> {
+    let state = parse2::<ExportGroupState>(tokens.into())?;
+    let name = &state.name;
+    let item = &state.item;
+    let collected = &state.collected;
+    let collected = quote!(#collected #item);
+    let mut remaining = Vec::new();
+    let mut path = TokenStream2::new();
+    for token in state.remaining {
+        match token {
+            TokenTree::Punct(punct) if punct.as_char() == ',' => {
+                remaining.push(std::mem::take(&mut path))
+            }
+            token => path.append(token),
+        }
+    }
+    if !path.is_empty() {
+        remaining.push(path);
+    }
+    let mut remaining = remaining.into_iter();
+    if let Some(next) = remaining.next() {
+        let info = &state.info;
+        let tokens_var = &state.tokens_var;
+        let callback = &state.callback;
+        let extra = &state.extra;
+        let inner_macro_path = private_path(&quote!(export_group_inner));
+        return Ok(quote! {
+            #next! {
+                __mm_group,
+                #inner_macro_path,
+                #name,
+                [#(#remaining),*],
+                [#collected],
+                #info,
+                { #tokens_var },
+                { #callback },
+                { #extra }
+            }
+        });
+    }
+    let group_item = quote!(mod #name { #collected });
+    let mut info = state.info;
+    info.hash = tokens_hash(&group_item);
+    let tokens_var = &state.tokens_var;
+    let callback = &state.callback;
+    let extra = &state.extra;
+    let extra = (!extra.is_empty()).then(|| quote!(, #extra));
+    Ok(quote! {
+        #callback! {
+            #tokens_var,
+            #group_item,
+            #info
+            #extra
+        }
+    })
+}
+
+/// Used to parse the (attribute) args of [`export_template_internal`], i.e.
+/// `T, Field, name = StorageTemplate`.
+///
+/// You shouldn't need to use this directly.
+pub struct ExportTemplateArgs {
+    /// The placeholders that are substituted when the template is instantiated.
+    pub placeholders: Vec<Ident>,
+    /// The name the template is exported as, if specified via `name = ..`.
+    pub name: Option<Ident>,
+    /// The prefix to use instead of [`MACRO_MAGIC_EXPORT_PREFIX`], if specified via
+    /// `prefix = ".."`.
+    pub prefix: Option<String>,
+}
+
+impl syn::parse::Parse for ExportTemplateArgs {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let mut placeholders: Vec<Ident> = Vec::new();
+        let mut name = None;
+        let mut prefix = None;
+        while !input.is_empty() {
+            if input.peek(keywords::prefix) && input.peek2(Token![=]) {
+                prefix = parse_prefix_option(input)?;
+                continue;
+            }
+            let ident = input.call(Ident::parse_any)?;
+            if ident == "name" && input.peek(Token![=]) {
+                input.parse::<Token![=]>()?;
+                name = Some(input.parse::<Ident>()?);
+            } else if placeholders.contains(&ident) {
+                return Err(Error::new(
+                    ident.span(),
+                    format!("duplicate placeholder `{ident}`"),
+                ));
+            } else {
+                placeholders.push(ident);
+            }
+            if !input.is_empty() {
+                input.parse::<Comma>()?;
+            }
+        }
+        if placeholders.is_empty() {
+            return Err(Error::new(
+                Span::call_site(),
+                "at least one placeholder must be specified, i.e. `#[export_template(T)]`",
+            ));
+        }
+        Ok(ExportTemplateArgs {
+            placeholders,
+            name,
+            prefix,
+        })
+    }
+}
+
+/// The internal code behind the `#[export_template(..)]` attribute macro.
+///
+/// Exports the attached item as a template whose placeholders (the idents specified in
+/// `attr`) are substituted by [`instantiate_template_internal`]. The item itself is not
+/// emitted, since it usually refers to placeholders that don't exist. It otherwise behaves
+/// like [`export_tokens_internal`], with the placeholders being recorded in the
+/// [`ExportInfo`] of the export.
+///
+/// Templates are exported under the name of the item, or, for `impl` blocks, under the name of
+/// the implemented trait, i.e. `impl Storage for T` is exported as `Storage`. A different name
+/// can be specified via `name = ..`.
+pub fn export_template_internal<T: Into<TokenStream2>, E: Into<TokenStream2>>(
+    attr: T,
+    tokens: E,
+) -> Result<TokenStream2> {
+    let args = parse2::<ExportTemplateArgs>(attr.into())?;
+    let item = parse2::<Item>(tokens.into())?;
+    let name = match (args.name, item_ident(&item), &item) {
+        (Some(name), _, _) => name,
+        (None, Some(ident), _) => ident,
+        (
+            None,
+            None,
+            Item::Impl(syn::ItemImpl {
+                trait_: Some((_, path, _)),
+                ..
+            }),
+        ) => match path.segments.last() {
+            Some(segment) => segment.ident.clone(),
+            None => unreachable!("must have at least one segment"),
+        },
+        (None, None, item) => {
+            return Err(Error::new(
+                item.span(),
+                "a name must be specified for this type of template, \
+                i.e. `#[export_template(T, name = SomeName)]`",
+            ))
+        }
+    };
+    let placeholders = args.placeholders;
+    let prefix = args.prefix.map(|prefix| quote!(, prefix = #prefix));
+    export_tokens_internal(
+        quote!(#name, __template(#(#placeholders),*) #prefix),
+        item.to_token_stream(),
+        false,
+    )
+}
+
+/// Used to parse the args of [`instantiate_template_internal`] and
+/// [`instantiate_template_inner_internal`], i.e. `T = MyType, Field = id`.
+///
+/// Values can be any type or expression, and are substituted verbatim.
+///
+/// You shouldn't need to use this directly.
+pub struct TemplateArgs {
+    /// The placeholders being substituted, along with the tokens they are substituted with,
+    /// in the order they were specified.
+    pub args: Vec<(Ident, TokenStream2)>,
+    /// The prefix the template was exported with, if specified via `prefix = ".."`.
+    pub prefix: Option<String>,
+}
+
+impl syn::parse::Parse for TemplateArgs {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let mut args: Vec<(Ident, TokenStream2)> = Vec::new();
+        let mut prefix = None;
+        while !input.is_empty() {
+            if input.peek(keywords::prefix) && input.peek2(Token![=]) && input.peek3(syn::LitStr) {
+                prefix = parse_prefix_option(input)?;
+                continue;
+            }
+            let placeholder = input.call(Ident::parse_any)?;
+            input.parse::<Token![=]>()?;
+            let fork = input.fork();
+            let value = match fork.parse::<syn::Type>() {
+                Ok(_) if fork.is_empty() || fork.peek(Comma) => {
+                    input.parse::<syn::Type>()?.to_token_stream()
+                }
+                _ => input.parse::<syn::Expr>()?.to_token_stream(),
+            };
+            if args.iter().any(|(other, _)| *other == placeholder) {
+                return Err(Error::new(
+                    placeholder.span(),
+                    format!("placeholder `{placeholder}` is specified more than once"),
+                ));
+            }
+            args.push((placeholder, value));
+            if !input.is_empty() {
+                input.parse::<Comma>()?;
+            }
+        }
+        Ok(TemplateArgs { args, prefix })
+    }
+}
+
+/// Replaces every occurrence of the specified placeholders within `tokens` with the tokens
+/// they are mapped to, including within nested groups.
+fn substitute_placeholders(tokens: TokenStream2, args: &[(Ident, TokenStream2)]) -> TokenStream2 {
+    let mut substituted = TokenStream2::new();
+    for token in tokens {
+        match token {
+            TokenTree::Ident(ident) => match args.iter().find(|(other, _)| *other == ident) {
+                // keeps values such as `3 + 4` intact when substituted into `N * 2`
+                Some((_, value))
+                    if parse2::<Expr>(value.clone()).is_ok()
+                        && parse2::<syn::Type>(value.clone()).is_err() =>
+                {
+                    let mut group = Group::new(Delimiter::Parenthesis, value.clone());
+                    group.set_span(ident.span());
+                    substituted.append(group);
+                }
+                Some((_, value)) => substituted.extend(value.clone()),
+                None => substituted.append(ident),
+            },
+            TokenTree::Group(group) => {
+                let mut new_group = Group::new(
+                    group.delimiter(),
+                    substitute_placeholders(group.stream(), args),
+                );
+                new_group.set_span(group.span());
+                substituted.append(new_group);
+            }
+            token => substituted.append(token),
+        }
+    }
+    substituted
+}
+
+/// The internal implementation for the `instantiate_template!` macro.
+///
+/// Expects the path of a template exported via `#[export_template(..)]`, followed by a value
+/// for each of its placeholders, i.e. `my_crate::Storage, T = MyType, Field = id`. Expands to
+/// a call to the hidden export macro of the template that forwards the tokens of the template
+/// to [`instantiate_template_inner_internal`], which performs the actual substitution.
+pub fn instantiate_template_internal<T: Into<TokenStream2>>(tokens: T) -> Result<TokenStream2> {
+    let (source, args) = (|input: ParseStream| {
+        let source = parse_item_path(input)?;
+        let args = match input.parse::<Option<Comma>>()? {
+            Some(_) => input.parse::<TemplateArgs>()?,
+            None => TemplateArgs {
+                args: Vec::new(),
+                prefix: None,
+            },
+        };
+        Ok((source, args))
+    })
+    .parse2(tokens.into())?;
+    let prefix = args.prefix.as_deref().unwrap_or(MACRO_MAGIC_EXPORT_PREFIX);
+    let source_path = export_tokens_macro_path_with_prefix(&source, prefix);
+    let inner_macro_path = private_path(&quote!(instantiate_template_inner));
+    let args = args
+        .args
+        .iter()
+        .map(|(placeholder, value)| quote!(#placeholder = #value));
+    Ok(quote! {
+        #source_path! { __mm_template, #inner_macro_path, { #(#args),* } }
+    })
+}
+
+/// Used by [`instantiate_template_internal`]. Substitutes the placeholders of the forwarded
+/// template, ensuring that a value was specified for each of them.
+pub fn instantiate_template_inner_internal<T: Into<TokenStream2>>(
+    tokens: T,
+) -> Result<TokenStream2> {
+    let (item, info, args) = (|input: ParseStream| {
+        input.parse::<Ident>()?;
+        input.parse::<Comma>()?;
+        let item = input.parse::<Item>()?;
+        input.parse::<Comma>()?;
+        let info = input.parse::<ExportInfo>()?;
+        input.parse::<Comma>()?;
+        let content;
+        syn::braced!(content in input);
+        Ok((item, info, content.parse::<TemplateArgs>()?))
+    })
+    .parse2(tokens.into())?;
+    if info.placeholders.is_empty() {
+        return Err(Error::new(
+            Span::call_site(),
+            "the specified item is not a template, consider exporting it via \
+            `#[export_template(..)]` or importing it via `import_tokens!` instead",
+        ));
+    }
+    for (placeholder, _) in &args.args {
+        if !info.placeholders.iter().any(|name| placeholder == name) {
+            return Err(Error::new(
+                placeholder.span(),
+                format!(
+                    "unknown placeholder `{placeholder}`, expected one of: {}",
+                    info.placeholders.join(", ")
+                ),
+            ));
+        }
+    }
+    let missing: Vec<&str> = info
+        .placeholders
+        .iter()
+        .filter(|name| {
+            !args
+                .args
+                .iter()
+                .any(|(placeholder, _)| placeholder == *name)
+        })
+        .map(String::as_str)
+        .collect();
+    if !missing.is_empty() {
+        return Err(Error::new(
+            Span::call_site(),
+            format!(
+                "missing value for placeholder(s): {}, i.e. `{} = ..`",
+                missing.join(", "),
+                missing[0]
+            ),
+        ));
+    }
+    Ok(substitute_placeholders(item.to_token_stream(), &args.args))
+}
+
+/// Used to parse the args of [`export_fragment_internal`], i.e.
+/// `default_timeout: expr = Duration::from_secs(30)`, optionally preceded by
+/// `prefix = "..",`.
+///
+/// You shouldn't need to use this directly.
+pub struct ExportFragmentArgs {
+    /// The prefix of the hidden export macro, if specified via a leading `prefix = ".."`.
+    pub prefix: Option<String>,
+    /// The name the fragment is exported as.
+    pub name: Ident,
+    /// The kind of the fragment.
+    pub kind: FragmentKind,
+    /// The tokens of the fragment.
+    pub tokens: TokenStream2,
+}
+
+impl syn::parse::Parse for ExportFragmentArgs {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let prefix = match input.peek(keywords::prefix) && input.peek2(Token![=]) {
+            true => parse_prefix_option(input)?,
+            false => None,
+        };
+        let name = input.parse::<Ident>()?;
+        input.parse::<Token![:]>()?;
+        let kind = input.parse::<FragmentKind>()?;
+        input.parse::<Token![=]>()?;
+        let tokens = input.parse::<TokenStream2>()?;
+        kind.parse(tokens.clone())?;
+        Ok(ExportFragmentArgs {
+            prefix,
+            name,
+            kind,
+            tokens,
+        })
+    }
+}
+
+/// The internal implementation for the `export_fragment!` macro.
+///
+/// Exports an expression, type, pattern or sequence of statements under the specified name,
+/// such that it can be imported by path just like an item marked with `#[export_tokens]`.
+/// Since the forwarding machinery only deals in items, the fragment is wrapped in an item
+/// macro invocation named after the fragment, and its kind is recorded in the [`ExportInfo`]
+/// of the export. See [`ExportInfo::fragment`] and [`import_fragment_internal`].
+pub fn export_fragment_internal<T: Into<TokenStream2>>(tokens: T) -> Result<TokenStream2> {
+    let args = parse2::<ExportFragmentArgs>(tokens.into())?;
+    let name = args.name;
+    let fragment = args.tokens;
+    let item = quote!(#name! { #fragment });
+    let mut info = ExportInfo::for_item(&name);
+    info.hash = tokens_hash(&item);
+    info.fragment_kind = Some(args.kind);
+    let idents = [name];
+    Ok(export_tokens_macro(
+        &idents,
+        &item,
+        &info,
+        None,
+        false,
+        args.prefix.as_deref().unwrap_or(MACRO_MAGIC_EXPORT_PREFIX),
+    ))
+}
+
+/// Used to parse the args of [`import_fragment_internal`], i.e.
+/// `let timeout: expr = other_crate::default_timeout`.
+///
+/// You shouldn't need to use this directly.
+pub struct ImportFragmentArgs {
+    /// The name of the variable the fragment is assigned to.
+    pub tokens_var_ident: Ident,
+    /// The kind of fragment that is expected, as written by the caller.
+    pub kind: Ident,
+    /// The path of the exported fragment.
+    pub source_path: Path,
+    /// The prefix the fragment was exported with, if specified via a trailing
+    /// `prefix = ".."`.
+    pub prefix: Option<String>,
+}
+
+impl syn::parse::Parse for ImportFragmentArgs {
+    fn parse(input: ParseStream) -> Result<Self> {
+        input.parse::<Token![let]>()?;
+        let tokens_var_ident = input.parse::<Ident>()?;
+        input.parse::<Token![:]>()?;
+        let kind = input.parse::<Ident>()?;
+        parse2::<FragmentKind>(kind.to_token_stream())?;
+        input.parse::<Token![=]>()?;
+        let source_path = parse_item_path(input)?;
+        let prefix = parse_prefix_option(input)?;
+        Ok(ImportFragmentArgs {
+            tokens_var_ident,
+            kind,
+            source_path,
+            prefix,
+        })
+    }
+}
+
+/// The internal implementation for the `import_fragment!` macro.
+///
+/// Like [`import_tokens_internal`], but for fragments exported via `export_fragment!`. The
+/// resulting variable is of the `syn` type corresponding to the requested kind of fragment,
+/// and a compile error is issued if the fragment was exported as a different kind.
+pub fn import_fragment_internal<T: Into<TokenStream2>>(tokens: T) -> Result<TokenStream2> {
+    let args = parse2::<ImportFragmentArgs>(tokens.into())?;
+    let prefix = args.prefix.as_deref().unwrap_or(MACRO_MAGIC_EXPORT_PREFIX);
+    let source_path = export_tokens_macro_path_with_prefix(&args.source_path, prefix);
+    let inner_macro_path = private_path(&quote!(import_fragment_inner));
+    let tokens_var_ident = args.tokens_var_ident;
+    let kind = args.kind;
+    Ok(quote! {
+        #source_path! { #tokens_var_ident, #inner_macro_path, { #kind } }
+    })
+}
+
+/// The internal implementation for the `import_fragment_inner` macro.
+///
+/// You shouldn't need to call this in any circumstances but it is provided just in case.
+pub fn import_fragment_inner_internal<T: Into<TokenStream2>>(tokens: T) -> Result<TokenStream2> {
+    let (tokens_var_ident, item, info, kind) = (|input: ParseStream| {
+        let tokens_var_ident = input.parse::<Ident>()?;
+        input.parse::<Comma>()?;
+        let item = input.parse::<Item>()?;
+        input.parse::<Comma>()?;
+        let info = input.parse::<ExportInfo>()?;
+        input.parse::<Comma>()?;
+        let content;
+        syn::braced!(content in input);
+        Ok((tokens_var_ident, item, info, content.parse::<Ident>()?))
+    })
+    .parse2(tokens.into())?;
+    let expected = parse2::<FragmentKind>(kind.to_token_stream())?;
+    match info.fragment_kind {
+        Some(actual) if actual != expected => {
+            return Err(Error::new(
+                kind.span(),
+                format!(
+                    "expected a `{expected}` fragment, but the specified fragment was exported \
+                    as `{actual}`"
+                ),
+            ))
+        }
+        Some(_) => (),
+        None => {
+            return Err(Error::new(
+                kind.span(),
+                "the specified item is not a fragment exported via `export_fragment!`",
+            ))
+        }
+    }
+    let fragment = info.fragment(item)?;
+    let fragment_string = fragment.to_token_stream().to_string();
+    let syn_path = private_path(&quote!(syn));
+    let (ty, parser) = match expected {
+        FragmentKind::Expr => (
+            quote!(#syn_path::Expr),
+            quote!(<#syn_path::Expr as #syn_path::parse::Parse>::parse),
+        ),
+        FragmentKind::Type => (
+            quote!(#syn_path::Type),
+            quote!(<#syn_path::Type as #syn_path::parse::Parse>::parse),
+        ),
+        FragmentKind::Pat => (
+            quote!(#syn_path::Pat),
+            quote!(#syn_path::Pat::parse_multi_with_leading_vert),
+        ),
+        FragmentKind::Stmts => (
+            quote!(::std::vec::Vec<#syn_path::Stmt>),
+            quote!(#syn_path::Block::parse_within),
+        ),
+    };
+    Ok(quote! {
+        let #tokens_var_ident: #ty = #syn_path::parse::Parser::parse_str(#parser, #fragment_string)
+            .expect("failed to parse quoted tokens");
+    })
+}
+
+/// Used to parse the args of [`export_tokens_alias_internal`], i.e.
+/// `my_export, transform = my_crate::normalize, options(strip_docs), prefix = "__my_"`.
+///
+/// You shouldn't need to use this directly.
+pub struct ExportTokensAliasArgs {
+    /// The name of the attribute macro that should be created
+    pub alias: Ident,
+    /// The path of a `fn(syn::Item) -> syn::Result<syn::Item>` that is run on every item
+    /// exported via the alias, if specified via `transform = ..`
+    pub transform: Option<Path>,
+    /// The [`ExportTokensArgs`] specified via `options(..)`, which are applied to every item
+    /// exported via the alias in addition to the args specified at the attribute site
+    pub options: TokenStream2,
+    /// The prefix to use instead of [`MACRO_MAGIC_EXPORT_PREFIX`], if specified via
+    /// `prefix = ".."`
+    pub prefix: Option<String>,
+}
+
+impl syn::parse::Parse for ExportTokensAliasArgs {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let alias = input.parse::<Ident>()?;
+        let mut transform = None;
+        let mut options = TokenStream2::new();
+        let mut prefix = None;
+        while !input.is_empty() {
+            input.parse::<Comma>()?;
+            if input.is_empty() {
+                break;
+            }
+            if input.peek(keywords::transform) {
+                input.parse::<keywords::transform>()?;
+                input.parse::<Token![=]>()?;
+                transform = Some(input.parse::<Path>()?);
+            } else if input.peek(keywords::options) {
+                input.parse::<keywords::options>()?;
+                let content;
+                syn::parenthesized!(content in input);
+                options = content.parse::<TokenStream2>()?;
+                // surface invalid options where the alias is defined rather than where it is used
+                parse2::<ExportTokensArgs>(options.clone())?;
+            } else if input.peek(keywords::prefix) {
+                input.parse::<keywords::prefix>()?;
+                input.parse::<Token![=]>()?;
+                let value = input.parse::<syn::LitStr>()?;
+                validate_export_prefix(&value.value(), value.span())?;
+                if prefix.is_some() {
+                    return Err(Error::new(
+                        value.span(),
+                        "`prefix` can only be specified once",
+                    ));
+                }
+                prefix = Some(value.value());
+            } else {
+                return Err(Error::new(
+                    input.span(),
+                    "expected `transform = ..`, `options(..)` or `prefix = \"..\"`",
+                ));
+            }
+        }
+        Ok(ExportTokensAliasArgs {
+            alias,
+            transform,
+            options,
+            prefix,
+        })
+    }
+}
+
+/// Internal implementation of `export_tokens_alias!`. Allows creating a renamed/rebranded
+/// macro that does the same thing as `#[export_tokens]`, optionally with preset
+/// [`ExportTokensArgs`] and a transform that is run on every exported item. See
+/// [`ExportTokensAliasArgs`] and [`export_tokens_internal_with_transform`].
+pub fn export_tokens_alias_internal<T: Into<TokenStream2>>(
+    tokens: T,
+    emit: bool,
+) -> Result<TokenStream2> {
+    let args = parse2::<ExportTokensAliasArgs>(tokens.into())?;
+    let alias = args.alias;
+    let export_tokens_internal_path =
+        macro_magic_path(&quote!(mm_core::export_tokens_internal_with_args));
+    let export_tokens_args_path = macro_magic_path(&quote!(mm_core::ExportTokensArgs));
+    let syn_path = private_path(&quote!(syn));
+    let token_stream_2 = private_path(&quote!(TokenStream2));
+    let quote_path = private_path(&quote!(quote::quote));
+    let options = args.options;
+    let attr = match options.is_empty() {
+        false => quote! {
+            let attr: #token_stream_2 = #quote_path!(#options,)
+                .into_iter()
+                .chain(#token_stream_2::from(attr))
+                .collect();
+        },
+        true => quote!(),
+    };
+    let with_prefix = args
+        .prefix
+        .map(|prefix| quote!(.and_then(|args| args.with_prefix(#prefix))));
+    let transform = match args.transform {
+        Some(transform) => quote!(#transform),
+        None => quote!(Ok),
+    };
+    Ok(quote! {
+        #[proc_macro_attribute]
+        pub fn #alias(attr: proc_macro::TokenStream, tokens: proc_macro::TokenStream) -> proc_macro::TokenStream {
+            #attr
+            let args = #syn_path::parse2::<#export_tokens_args_path>(attr.into())#with_prefix;
+            match args.and_then(|args| #export_tokens_internal_path(args, tokens, #emit, #transform)) {
+                Ok(tokens) => tokens.into(),
+                Err(err) => err.to_compile_error().into(),
+            }
+        }
+    })
+}
+
+/// The internal implementation for the `import_tokens` macro.
+///
+/// You can call this in your own proc macros to make use of the `import_tokens` functionality
+/// directly, though this approach is limited. The arguments should be a [`TokenStream2`] that
+/// can parse into an [`ImportTokensArgs`] successfully. That is a valid `let` variable
+/// declaration set to equal a path where an `#[export_tokens]` with the specified ident can be
+/// found.
+///
+/// ### Example:
+/// ```
+/// use macro_magic_core::*;
+/// use quote::quote;
+///
+/// let some_ident = quote!(my_tokens);
+/// let some_path = quote!(other_crate::exported_item);
+/// let tokens = import_tokens_internal(quote!(let #some_ident = other_crate::ExportedItem)).unwrap();
+/// assert_eq!(
+///     tokens.to_string(),
+///     "other_crate :: __export_tokens_tt_exported_item ! { my_tokens , \
+///     :: macro_magic :: __private :: import_tokens_inner }");
+/// ```
+/// If these tokens were emitted as part of a proc macro, they would expand to a variable
+/// declaration like:
+/// ```ignore
+/// let my_tokens: TokenStream2;
+/// ```
+/// where `my_tokens` contains the tokens of `ExportedItem`.
+pub fn import_tokens_internal<T: Into<TokenStream2>>(tokens: T) -> Result<TokenStream2> {
+    let args = parse2::<ImportTokensArgs>(tokens.into())?;
+    let prefix = args.prefix.as_deref().unwrap_or(MACRO_MAGIC_EXPORT_PREFIX);
+    let source_path = export_tokens_macro_path_with_prefix(&args.source_path, prefix);
+    let inner_macro_path = private_path(&quote!(import_tokens_inner));
+    let tokens_var_ident = args.tokens_var_ident;
+    let version = args.version.map(|req| quote!(version = #req));
+    let ty = args.ty.map(|ty| quote!(type = #ty));
+    let extra = match (version, ty) {
+        (None, None) => quote!(),
+        (Some(version), None) => quote!(, { #version }),
+        (None, Some(ty)) => quote!(, { #ty }),
+        (Some(version), Some(ty)) => quote!(, { #version, #ty }),
+    };
+    Ok(quote! {
+        #source_path! { #tokens_var_ident, #inner_macro_path #extra }
+    })
+}
+
+/// The internal implementation for the `import_tokens_inner` macro.
+///
+/// You shouldn't need to call this in any circumstances but it is provided just in case.
+pub fn import_tokens_inner_internal<T: Into<TokenStream2>>(tokens: T) -> Result<TokenStream2> {
+    let parsed = parse2::<ImportedTokens>(tokens.into())?;
+    if let Some(req) = parsed.version {
+        req.check(&parsed.export_info, Span::call_site())?;
+    }
+    let tokens_string = parsed.item.to_token_stream().to_string();
+    let ident = parsed.tokens_var_ident;
+    let Some(ty) = parsed.ty else {
+        let token_stream_2 = private_path(&quote!(TokenStream2));
+        return Ok(quote! {
+            let #ident = #tokens_string.parse::<#token_stream_2>().expect("failed to parse quoted tokens");
+        });
+    };
+    if let Some(expected) = ImportedItemType::from_type(&ty) {
+        if !expected.accepts(&parsed.item) {
+            let (actual, description) = item_kind(&parsed.item);
+            return Err(Error::new(
+                ty.span(),
+                format!(
+                    "expected the imported item to be parsed as `{}`, but it is {description} \
+                    (`syn::{actual}`)",
+                    ty.to_token_stream().to_string().replace(' ', ""),
+                ),
+            ));
+        }
+    }
+    let syn_path = private_path(&quote!(syn));
+    Ok(quote! {
+        let #ident: #ty = #syn_path::parse_str::<#ty>(#tokens_string)
+            .expect("failed to parse quoted tokens");
+    })
+}
+
+/// The internal implementation for the `forward_tokens` macro.
+///
+/// You shouldn't need to call this in any circumstances but it is provided just in case.
+pub fn forward_tokens_internal<T: Into<TokenStream2>>(tokens: T) -> Result<TokenStream2> {
+    let args = parse2::<ForwardTokensArgs>(tokens.into())?;
+    let mm_path = match args.mm_path {
+        Some(path) => path,
+        None => macro_magic_root(),
+    };
+    let prefix = args.prefix.as_deref().unwrap_or(MACRO_MAGIC_EXPORT_PREFIX);
+    let source_path = export_tokens_macro_path_with_prefix(&args.source, prefix);
+    let target_path = args.target;
+    if let Some(remaining) = args.batch {
+        let chain = ForwardChain {
+            remaining,
+            collected: Vec::new(),
+        };
+        return Ok(forward_batch_tokens(
+            &source_path,
+            &target_path,
+            &mm_path,
+            &chain,
+            args.prefix.as_deref(),
+        ));
+    }
+    let extra = args.extra.map(|extra| quote!(, #extra));
+    let proc_macro = args.proc_macro.then(|| quote!(, proc_macro));
+    let derive = args.derive.then(|| quote!(, derive));
+    Ok(quote! {
+        #source_path! {
+            #target_path,
+            #mm_path::__private::forward_tokens_inner
+            #extra
+            #proc_macro
+            #derive
+        }
+    })
+}
+
+/// Generates a call to the export macro at `source_path` that passes the [`ForwardChain`] of
+/// a batched [`forward_tokens_internal`] call on to [`forward_tokens_batch_internal`].
+fn forward_batch_tokens(
+    source_path: &Path,
+    target_path: &Path,
+    mm_path: &Path,
+    chain: &ForwardChain,
+    prefix: Option<&str>,
+) -> TokenStream2 {
+    let prefix = prefix.map(|prefix| quote!(, prefix = #prefix));
+    quote! {
+        #source_path! {
+            #target_path,
+            #mm_path::__private::forward_tokens_batch,
+            { #mm_path },
+            #chain
+            #prefix
+        }
+    }
+}
+
+/// Used by [`forward_tokens_internal`] when forwarding several items at once. Collects the
+/// forwarded item and either forwards the next of the remaining items or, once all of them
+/// have been collected, calls the target with all of the items.
+pub fn forward_tokens_batch_internal<T: Into<TokenStream2>>(tokens: T) -> Result<TokenStream2> {
+    let parsed = parse2::<ForwardedBatch>(tokens.into())?;
+    let mut chain = parsed.chain;
+    let target_path = parsed.target_path;
+    if let Some(next) = chain.advance(parsed.item, parsed.export_info) {
+        let prefix = parsed.prefix.as_deref();
+        let source_path = export_tokens_macro_path_with_prefix(
+            &next,
+            prefix.unwrap_or(MACRO_MAGIC_EXPORT_PREFIX),
+        );
+        return Ok(forward_batch_tokens(
+            &source_path,
+            &target_path,
+            &parsed.mm_path,
+            &chain,
+            prefix,
+        ));
+    }
+    let collected = chain.collected.into_iter().map(|(item, _)| item);
+    let tokens_forwarded_keyword = keywords::__private_macro_magic_tokens_forwarded::default();
+    Ok(quote! {
+        #target_path! {
+            #tokens_forwarded_keyword
+            #(#collected)*
+        }
+    })
+}
+
+/// Used by [`forward_tokens_internal`].
+pub fn forward_tokens_inner_internal<T: Into<TokenStream2>>(tokens: T) -> Result<TokenStream2> {
+    let parsed = parse2::<ForwardedTokens>(tokens.into())?;
+    let target_path = parsed.target_path;
+    let imported_tokens = parsed.item;
+    let export_info = parsed.export_info;
+    let tokens_forwarded_keyword = keywords::__private_macro_magic_tokens_forwarded::default();
+    let pound = Punct::new('#', Spacing::Alone);
+    match parsed.extra {
+        // used by derive, so invoke the derive on a hidden struct with a helper attribute
+        Some(extra) if parsed.derive => {
+            let (helper, _, derive_input) = parse_derive_extra.parse2(extra.to_token_stream())?;
+            let Some(derive_name) = target_path.segments.last() else {
+                unreachable!("must have at least one segment")
+            };
+            let dummy = format_ident!(
+                "__MacroMagicDerive_{}_{}",
+                derive_name.ident,
+                derive_input.ident
+            );
+            // the anonymous const keeps the hidden struct out of the caller's namespace
+            Ok(quote! {
+                const _: () = {
+                    #[derive(#target_path)]
+                    #pound [#helper(
+                        #tokens_forwarded_keyword
+                        #imported_tokens,
+                        #extra,
+                        #export_info
+                    )]
+                    #[allow(dead_code, non_camel_case_types)]
+                    #[doc(hidden)]
+                    struct #dummy;
+                };
+            })
+        }
+        // used by proc, so expand to proc macro that also receives the export info
+        extra if parsed.proc_macro => {
+            let extra = extra.map(|extra| quote!(#extra,));
+            Ok(quote! {
+                #target_path! {
+                    #tokens_forwarded_keyword
+                    #imported_tokens,
+                    #extra
+                    #export_info
+                }
+            })
+        }
+        // some extra, used by attr, so expand to attribute macro
+        Some(extra) => Ok(quote! {
+            #pound [#target_path(
+                #tokens_forwarded_keyword
+                #imported_tokens,
+                #extra,
+                #export_info
+            )] type __Discarded = ();
+        }),
+        // no extra, used by forward_tokens, etc, so expand to proc macro
+        None => Ok(quote! {
+            #target_path! {
+                #tokens_forwarded_keyword
+                #imported_tokens
+            }
+        }),
+    }
+}
+
+/// Used to parse the args of [`assert_tokens_hash_internal`].
+///
+/// You shouldn't need to use this directly.
+#[derive(Parse)]
+pub struct AssertTokensHashArgs {
+    /// The path of the exported item whose tokens are being checked
+    #[call(parse_item_path)]
+    pub source: Path,
+    _comma: Comma,
+    /// The expected [`tokens_hash`] of the exported item
+    pub expected: syn::LitStr,
+    /// The prefix the item was exported with, if specified via a trailing `prefix = ".."`.
+    #[call(parse_prefix_option)]
+    pub prefix: Option<String>,
+}
+
+/// Used to parse args that were passed to [`assert_tokens_hash_inner_internal`].
+///
+/// You shouldn't need to use this directly.
+#[derive(Parse)]
+pub struct AssertedTokens {
+    /// The path of the exported item whose tokens are being checked
+    pub source: Path,
+    _comma1: Comma,
+    /// The exported item whose tokens are being checked
+    pub item: Item,
+    _comma2: Comma,
+    /// The [`ExportInfo`] that was forwarded along with the item, containing its actual hash
+    pub export_info: ExportInfo,
+    _comma3: Comma,
+    #[brace]
+    _brace: Brace,
+    /// The expected [`tokens_hash`] of the exported item
+    #[inside(_brace)]
+    pub expected: syn::LitStr,
+}
+
+/// The internal implementation for the `assert_tokens_hash!` macro.
+///
+/// Expands to nothing if the [`tokens_hash`] of the specified exported item matches the
+/// expected hash, and to a compile error otherwise.
+pub fn assert_tokens_hash_internal<T: Into<TokenStream2>>(tokens: T) -> Result<TokenStream2> {
+    let args = parse2::<AssertTokensHashArgs>(tokens.into())?;
+    let prefix = args.prefix.as_deref().unwrap_or(MACRO_MAGIC_EXPORT_PREFIX);
+    let source_path = export_tokens_macro_path_with_prefix(&args.source, prefix);
+    let inner_macro_path = private_path(&quote!(assert_tokens_hash_inner));
+    let source = args.source;
+    let expected = args.expected;
+    Ok(quote! {
+        #source_path! { #source, #inner_macro_path, { #expected } }
+    })
+}
+
+/// Used by [`assert_tokens_hash_internal`].
+pub fn assert_tokens_hash_inner_internal<T: Into<TokenStream2>>(tokens: T) -> Result<TokenStream2> {
+    let parsed = parse2::<AssertedTokens>(tokens.into())?;
+    let expected = parsed.expected.value();
+    let info = parsed.export_info;
+    if expected == info.hash {
+        return Ok(quote!());
+    }
+    let source = parsed.source.to_token_stream().to_string().replace(' ', "");
+    Err(Error::new(
+        parsed.expected.span(),
+        format!(
+            "the tokens of `{source}` (defined at {}:{}) have changed, expected hash \"{expected}\" \
+            but found \"{}\"",
+            info.source.file, info.source.line, info.hash
+        ),
+    ))
+}
+
+/// The internal implementation for the `#[with_custom_parsing(..)` attribute macro.
+///
+/// Note that this implementation just does parsing and re-orders the attributes of the
+/// attached proc macro attribute definition such that the `#[import_tokens_attr]` attribute
+/// comes before this attribute. The real implementation for `#[with_custom_parsing(..)]` can
+/// be found in [`import_tokens_attr_internal`]. The purpose of this is to allow programmers to
+/// use either ordering and still have the proper compiler errors when something is invalid.
+///
+/// The `import_tokens_att_name` argument is used when generating error messages and matching
+/// against the `#[import_tokens_attr]` macro this is to be used with. If you use a
+/// renamed/rebranded version of `#[import_tokens_attr]`, you should change this value to match
+/// the name of your macro.
+pub fn with_custom_parsing_internal<T1: Into<TokenStream2>, T2: Into<TokenStream2>>(
+    attr: T1,
+    tokens: T2,
+    import_tokens_attr_name: &'static str,
+) -> Result<TokenStream2> {
+    // verify that we are attached to a valid #[import_tokens_attr] proc macro def
+    let proc_macro = parse_proc_macro_variant(tokens, ProcMacroType::Attribute)?;
+    if proc_macro
+        .proc_fn
+        .attrs
+        .iter()
+        .find(|attr| {
+            if let Some(seg) = attr.meta.path().segments.last() {
+                return seg.ident == import_tokens_attr_name;
+            }
+            false
+        })
+        .is_none()
+    {
+        return Err(Error::new(
+            Span::call_site(),
+            format!(
+                "Can only be attached to an attribute proc macro marked with `#[{}]`",
+                import_tokens_attr_name
+            ),
+        ));
+    }
+
+    // ensure there is only one `#[with_custom_parsing]`
+    if proc_macro
+        .proc_fn
+        .attrs
+        .iter()
+        .find(|attr| {
+            if let Some(seg) = attr.meta.path().segments.last() {
+                return seg.ident == "with_custom_parsing_internal";
+            }
+            false
+        })
+        .is_some()
+    {
+        return Err(Error::new(
+            Span::call_site(),
+            "Only one instance of #[with_custom_parsing] can be attached at a time.",
+        ));
+    }
+
+    // parse attr to ensure it is a Path
+    let custom_path = parse2::<Path>(attr.into())?;
+
+    // emit original item unchanged now that parsing has passed
+    let mut item_fn = proc_macro.proc_fn;
+    item_fn
+        .attrs
+        .push(parse_quote!(#[with_custom_parsing(#custom_path)]));
+
+    Ok(quote!(#item_fn))
+}
+
+/// Parses the (attribute) args of [`import_tokens_attr_internal`] and
+/// [`import_tokens_proc_internal`], which can now evaluate to either a `Path` or an `Expr`
+/// that is expected to be able to be placed in a `String::from(x)`.
+enum OverridePath {
+    Path(Path),
+    Expr(Expr),
+}
+
+impl syn::parse::Parse for OverridePath {
+    fn parse(input: ParseStream) -> Result<Self> {
+        if input.is_empty() {
+            return Ok(OverridePath::Path(macro_magic_root()));
+        }
+        let mut remaining = TokenStream2::new();
+        while !input.is_empty() {
+            remaining.extend(input.parse::<TokenTree>()?.to_token_stream());
+        }
+        if let Ok(path) = parse2::<Path>(remaining.clone()) {
+            return Ok(OverridePath::Path(path));
+        }
+        match parse2::<Expr>(remaining) {
+            Ok(expr) => Ok(OverridePath::Expr(expr)),
+            Err(mut err) => {
+                err.combine(Error::new(
+                    input.span(),
+                    "Expected either a `Path` or an `Expr` that evaluates to something compatible with `Into<String>`."
+                ));
+                Err(err)
+            }
+        }
+    }
+}
+
+/// Parses the full (attribute) args of [`import_tokens_attr_internal`] and
+/// [`import_tokens_proc_internal`], i.e. an optional [`OverridePath`] followed by an optional
+/// `version = ..` requirement and/or `prefix = ".."`.
+struct ImportTokensMacroArgs {
+    mm_override_path: OverridePath,
+    version: Option<VersionReq>,
+    prefix: Option<String>,
+}
+
+impl syn::parse::Parse for ImportTokensMacroArgs {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let mut override_tokens = TokenStream2::new();
+        let is_option = |input: ParseStream| {
+            (input.peek(keywords::version) || input.peek(keywords::prefix))
+                && input.peek2(Token![=])
+        };
+        while !input.is_empty() {
+            if is_option(input) {
+                break;
+            }
+            if input.peek(Comma) {
+                let fork = input.fork();
+                fork.parse::<Comma>()?;
+                if is_option(&fork) {
+                    break;
+                }
+            }
+            override_tokens.extend(input.parse::<TokenTree>()?.to_token_stream());
+        }
+        let (version, prefix) = parse_import_options(input)?;
+        Ok(ImportTokensMacroArgs {
+            mm_override_path: parse2::<OverridePath>(override_tokens)?,
+            version,
+            prefix,
+        })
+    }
+}
+
+impl ToTokens for OverridePath {
+    fn to_tokens(&self, tokens: &mut TokenStream2) {
+        match self {
+            OverridePath::Path(path) => {
+                let path = path.to_token_stream().to_string();
+                tokens.extend(quote!(#path))
+            }
+            OverridePath::Expr(expr) => tokens.extend(quote!(#expr)),
+        }
+    }
+}
+
+/// Internal implementation for the `#[import_tokens_attr]` attribute.
+///
+/// You shouldn't need to use this directly, but it may be useful if you wish to rebrand/rename
+/// the `#[import_tokens_attr]` macro without extra indirection.
+pub fn import_tokens_attr_internal<T1: Into<TokenStream2>, T2: Into<TokenStream2>>(
+    attr: T1,
+    tokens: T2,
+) -> Result<TokenStream2> {
+    let attr = attr.into();
+    let args = parse2::<ImportTokensMacroArgs>(attr)?;
+    let mm_override_path = args.mm_override_path;
+    let mm_path = macro_magic_root();
+    let mut proc_macro = parse_proc_macro_variant(tokens, ProcMacroType::Attribute)?;
+
+    // params
+    let attr_ident = proc_macro.attr_ident.unwrap();
+    let tokens_ident = proc_macro.tokens_ident;
+
+    // handle custom parsing, if applicable
+    let path_resolver = if let Some(index) = proc_macro.proc_fn.attrs.iter().position(|attr| {
+        if let Some(seg) = attr.meta.path().segments.last() {
+            return seg.ident == "with_custom_parsing";
+        }
+        false
+    }) {
+        let custom_attr = &proc_macro.proc_fn.attrs[index];
+        let custom_struct_path: Path = custom_attr.parse_args()?;
+
+        proc_macro.proc_fn.attrs.remove(index);
+        quote! {
+            let custom_parsed = syn::parse_macro_input!(#attr_ident as #custom_struct_path);
+            let paths = ForeignPaths::foreign_paths(&custom_parsed);
+            let _ = (&custom_parsed as &dyn quote::ToTokens);
+        }
+    } else {
+        quote! {
+            let custom_parsed = quote::quote!();
+            let paths = ForeignPaths::foreign_paths(&syn::parse_macro_input!(#attr_ident as ForeignPathList));
+        }
+    };
+
+    // outer macro
+    let orig_sig = proc_macro.proc_fn.sig;
+    let orig_stmts = proc_macro.proc_fn.block.stmts;
+    let orig_attrs = proc_macro.proc_fn.attrs;
+    let orig_sig_ident = &orig_sig.ident;
+
+    // inner macro
+    let inner_macro_ident = format_ident!("__import_tokens_attr_{}_inner", orig_sig.ident);
+    let mut inner_sig = orig_sig.clone();
+    inner_sig.ident = inner_macro_ident.clone();
+    inner_sig.inputs.pop().unwrap();
+
+    let pound = Punct::new('#', Spacing::Alone);
+    let prefix_option = match &args.prefix {
+        Some(prefix) => quote!(Some(#prefix)),
+        None => quote!(None),
+    };
+    let prefix = args.prefix.map(|prefix| quote!(, prefix = #prefix));
+
+    // version requirement, if applicable
+    let version_check = args.version.map(|req| {
+        let req = req.to_string();
+        quote! {
+            let __version_req = #mm_path::__private::syn::parse_str::<#mm_path::mm_core::VersionReq>(#req).unwrap();
+            let __version_span = #mm_path::__private::syn::spanned::Spanned::span(&__combined_args.source_path);
+            for (_, export_info) in &__imported_items {
+                if let Err(err) = __version_req.check(export_info, __version_span) {
+                    return err.to_compile_error().into();
+                }
+            }
+        }
+    });
+
+    // final quoted tokens
+    let output = quote! {
+        #(#orig_attrs)
+        *
+        pub #orig_sig {
+            pub #inner_sig {
+                let __combined_args = #mm_path::__private::syn::parse_macro_input!(#attr_ident as #mm_path::mm_core::AttrItemWithExtra);
+                let __imported_items: Vec<(#mm_path::__private::syn::Item, #mm_path::mm_core::ExportInfo)> = __combined_args.imported_items();
+                #version_check
+
+                let #attr_ident: proc_macro::TokenStream = __imported_items
+                    .iter()
+                    .map(|(item, _)| item.to_token_stream())
+                    .collect::<#mm_path::__private::TokenStream2>()
+                    .into();
+                let #tokens_ident: proc_macro::TokenStream = __combined_args.tokens_ident.into();
+                let __source_path: proc_macro::TokenStream = __combined_args.source_path.into();
+                let __custom_tokens: proc_macro::TokenStream = __combined_args.custom_tokens.into();
+                let __export_info: #mm_path::mm_core::ExportInfo = __imported_items[0].1.clone();
+                let __source_info: #mm_path::mm_core::SourceInfo = __export_info.source.clone();
+                let __tokens_hash: String = __export_info.hash.clone();
+                let __group_items: Vec<#mm_path::__private::syn::Item> = __imported_items
+                    .iter()
+                    .flat_map(|(item, export_info)| export_info.group_items(item.clone()))
+                    .collect();
+
+                #(#orig_stmts)
+                *
+            }
+
+            // This is to avoid corrupting the scope with imports below
+            fn isolated_mm_override_path() -> String {
+                String::from(#mm_override_path)
+            }
+
+            use #mm_path::__private::*;
+            use #mm_path::__private::quote::ToTokens;
+            use #mm_path::mm_core::*;
+
+            syn::custom_keyword!(__private_macro_magic_tokens_forwarded);
+
+            let mut cloned_attr = #attr_ident.clone().into_iter();
+            let first_attr_token = cloned_attr.next();
+            let attr_minus_first_token = proc_macro::TokenStream::from_iter(cloned_attr);
+
+            let forwarded = first_attr_token.map_or(false, |token| {
+                syn::parse::<__private_macro_magic_tokens_forwarded>(token.into()).is_ok()
+            });
+
+            let mm_override_tokenstream = isolated_mm_override_path().parse().unwrap();
+            let resolved_mm_override_path = match syn::parse2::<syn::Path>(mm_override_tokenstream) {
+                Ok(res) => res,
+                Err(err) => return err.to_compile_error().into()
+            };
+
+            if forwarded {
+                let combined_args = attr_minus_first_token.clone();
+                let combined_args = syn::parse_macro_input!(combined_args as AttrItemWithExtra);
+                let target: syn::Path = syn::parse_quote!(#orig_sig_ident);
+                match combined_args.forward_next(&target, &resolved_mm_override_path, #prefix_option) {
+                    Some(next) => next.into(),
+                    None => #inner_macro_ident(attr_minus_first_token),
+                }
+            } else {
+                let attached_item = syn::parse_macro_input!(#tokens_ident as syn::Item);
+                let attached_item = attached_item.to_token_stream();
+                #path_resolver
+                let Some((first_path, remaining_paths)) = paths.split_first() else {
+                    return syn::Error::new(
+                        proc_macro2::Span::call_site(),
+                        "expected the path of at least one foreign item",
+                    )
+                    .to_compile_error()
+                    .into();
+                };
+                let path = quote::quote!(#pound (#pound paths),*);
+                let custom_parsed = custom_parsed.to_token_stream();
+                quote::quote! {
+                    #pound resolved_mm_override_path::forward_tokens! {
+                        #pound first_path,
+                        #orig_sig_ident,
+                        #pound resolved_mm_override_path,
+                        {
+                            { #pound attached_item },
+                            { #pound path },
+                            { #pound custom_parsed },
+                            { #pound (#pound remaining_paths),* },
+                            {}
+                        }
+                        #prefix
+                    }
+                }.into()
+            }
+        }
+    };
+    Ok(output)
+}
+
+/// Internal implementation for the `#[import_tokens_proc]` attribute.
+///
+/// You shouldn't need to use this directly, but it may be useful if you wish to rebrand/rename
+/// the `#[import_tokens_proc]` macro without extra indirection.
+pub fn import_tokens_proc_internal<T1: Into<TokenStream2>, T2: Into<TokenStream2>>(
+    attr: T1,
+    tokens: T2,
+) -> Result<TokenStream2> {
+    let attr = attr.into();
+    let args = parse2::<ImportTokensMacroArgs>(attr)?;
+    let mm_override_path = args.mm_override_path;
+    let mm_path = macro_magic_root();
+    let proc_macro = parse_proc_macro_variant(tokens, ProcMacroType::Normal)?;
+
+    // outer macro
+    let orig_sig = proc_macro.proc_fn.sig;
+    let orig_stmts = proc_macro.proc_fn.block.stmts;
+    let orig_attrs = proc_macro.proc_fn.attrs;
+    let orig_sig_ident = &orig_sig.ident;
+
+    // inner macro
+    let inner_macro_ident = format_ident!("__import_tokens_proc_{}_inner", orig_sig.ident);
+    let mut inner_sig = orig_sig.clone();
+    inner_sig.ident = inner_macro_ident.clone();
+    inner_sig.inputs = inner_sig.inputs.iter().rev().cloned().collect();
+
+    // params
+    let tokens_ident = proc_macro.tokens_ident;
+
+    let pound = Punct::new('#', Spacing::Alone);
+    let prefix = args.prefix.map(|prefix| quote!(, prefix = #prefix));
+
+    // version requirement, if applicable
+    let version_check = args.version.map(|req| {
+        let req = req.to_string();
+        quote! {
+            let __version_req = #mm_path::__private::syn::parse_str::<#mm_path::mm_core::VersionReq>(#req).unwrap();
+            let __version_span = #mm_path::__private::syn::spanned::Spanned::span(&__combined_args.source_path);
+            if let Err(err) = __version_req.check(&__combined_args.export_info, __version_span) {
+                return err.to_compile_error().into();
+            }
+        }
+    });
+
+    Ok(quote! {
+        #(#orig_attrs)
+        *
+        pub #orig_sig {
+            #inner_sig {
+                let __combined_args = #mm_path::__private::syn::parse_macro_input!(#tokens_ident as #mm_path::mm_core::ProcItemWithExtra);
+                #version_check
+
+                let #tokens_ident: proc_macro::TokenStream = __combined_args.imported_item.to_token_stream().into();
+                let __source_path: proc_macro::TokenStream = __combined_args.source_path.into();
+                let __export_info: #mm_path::mm_core::ExportInfo = __combined_args.export_info.clone();
+                let __source_info: #mm_path::mm_core::SourceInfo = __combined_args.export_info.source;
+                let __tokens_hash: String = __combined_args.export_info.hash;
+
+                #(#orig_stmts)
+                *
+            }
+
+            // This is to avoid corrupting the scope with imports below
+            fn isolated_mm_override_path() -> String {
+                String::from(#mm_override_path)
+            }
+
+            use #mm_path::__private::*;
+            use #mm_path::__private::quote::ToTokens;
+
+            syn::custom_keyword!(__private_macro_magic_tokens_forwarded);
+
+            let mut cloned_tokens = #tokens_ident.clone().into_iter();
+            let first_token = cloned_tokens.next();
+            let tokens_minus_first = proc_macro::TokenStream::from_iter(cloned_tokens);
+
+            let forwarded = first_token.map_or(false, |token| {
+                syn::parse::<__private_macro_magic_tokens_forwarded>(token.into()).is_ok()
+            });
+
+            if forwarded {
+                #inner_macro_ident(tokens_minus_first)
+            } else {
+                use #mm_path::__private::*;
+                use #mm_path::__private::quote::ToTokens;
+                let source_path = match syn::parse::Parser::parse(#mm_path::mm_core::parse_item_path, #tokens_ident) {
+                    Ok(path) => path,
+                    Err(e) => return e.to_compile_error().into(),
+                };
+                let mm_override_tokenstream = isolated_mm_override_path().parse().unwrap();
+                let resolved_mm_override_path = match syn::parse2::<syn::Path>(mm_override_tokenstream) {
+                    Ok(res) => res,
+                    Err(err) => return err.to_compile_error().into()
+                };
+                quote::quote! {
+                    #pound resolved_mm_override_path::forward_tokens! {
+                        #pound source_path,
+                        #orig_sig_ident,
+                        #pound resolved_mm_override_path,
+                        { #pound source_path },
+                        proc_macro
+                        #prefix
+                    }
+                }.into()
+            }
+        }
+    })
+}
+
+/// Used to parse the args of [`import_tokens_derive_internal`], i.e. the name of the helper
+/// attribute followed by the same args as `#[import_tokens_proc]`.
+struct ImportTokensDeriveArgs {
+    helper: Ident,
+    macro_args: ImportTokensMacroArgs,
+}
+
+impl syn::parse::Parse for ImportTokensDeriveArgs {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let helper = input.parse::<Ident>()?;
+        input.parse::<Option<Comma>>()?;
+        Ok(ImportTokensDeriveArgs {
+            helper,
+            macro_args: input.parse()?,
+        })
+    }
+}
+
+/// Used to parse the args of the `#[proc_macro_derive(..)]` attribute, i.e. the name of the
+/// derive macro and its helper attributes.
+#[derive(Parse)]
+struct ProcMacroDeriveArgs {
+    name: Ident,
+    _comma: Option<Comma>,
+    #[peek(keywords::attributes)]
+    helpers: Option<DeriveHelpers>,
+}
+
+#[derive(Parse)]
+struct DeriveHelpers {
+    _attributes: keywords::attributes,
+    #[paren]
+    _paren: syn::token::Paren,
+    #[inside(_paren)]
+    #[call(syn::punctuated::Punctuated::parse_terminated)]
+    helpers: syn::punctuated::Punctuated<Ident, Comma>,
+}
+
+/// Internal implementation for the `#[import_tokens_derive]` attribute.
+///
+/// You shouldn't need to use this directly, but it may be useful if you wish to rebrand/rename
+/// the `#[import_tokens_derive]` macro without extra indirection.
+pub fn import_tokens_derive_internal<T1: Into<TokenStream2>, T2: Into<TokenStream2>>(
+    attr: T1,
+    tokens: T2,
+) -> Result<TokenStream2> {
+    let args = parse2::<ImportTokensDeriveArgs>(attr.into())?;
+    let helper = args.helper;
+    let mm_override_path = args.macro_args.mm_override_path;
+    let mm_path = macro_magic_root();
+    let proc_macro = parse_proc_macro_variant(tokens, ProcMacroType::Derive)?;
+
+    // derive name and helper attributes
+    let Some(derive_attr) = proc_macro
+        .proc_fn
+        .attrs
+        .iter()
+        .find(|attr| attr.path().is_ident("proc_macro_derive"))
+    else {
+        unreachable!("must have a `#[proc_macro_derive(..)]` attribute");
+    };
+    let derive_args = derive_attr.parse_args::<ProcMacroDeriveArgs>()?;
+    let derive_name = derive_args.name;
+    if !derive_args
+        .helpers
+        .is_some_and(|helpers| helpers.helpers.iter().any(|ident| ident == &helper))
+    {
+        return Err(Error::new(
+            derive_attr.span(),
+            format!("`{helper}` must be declared as a helper attribute via `attributes({helper})`"),
+        ));
+    }
+    let helper_str = helper.to_string();
+
+    // outer macro
+    let orig_sig = proc_macro.proc_fn.sig;
+    let orig_stmts = proc_macro.proc_fn.block.stmts;
+    let orig_attrs = proc_macro.proc_fn.attrs;
+
+    // inner macro
+    let inner_macro_ident = format_ident!("__import_tokens_derive_{}_inner", orig_sig.ident);
+    let mut inner_sig = orig_sig.clone();
+    inner_sig.ident = inner_macro_ident.clone();
+
+    // params
+    let tokens_ident = proc_macro.tokens_ident;
+
+    let pound = Punct::new('#', Spacing::Alone);
+    let prefix = args
+        .macro_args
+        .prefix
+        .map(|prefix| quote!(, prefix = #prefix));
+
+    // version requirement, if applicable
+    let version_check = args.macro_args.version.map(|req| {
+        let req = req.to_string();
+        quote! {
+            let __version_req = #mm_path::__private::syn::parse_str::<#mm_path::mm_core::VersionReq>(#req).unwrap();
+            let __version_span = #mm_path::__private::syn::spanned::Spanned::span(&__combined_args.source_path);
+            if let Err(err) = __version_req.check(&__combined_args.export_info, __version_span) {
+                return err.to_compile_error().into();
+            }
+        }
+    });
+
+    Ok(quote! {
+        #(#orig_attrs)
+        *
+        pub #orig_sig {
+            #inner_sig {
+                let __combined_args = #mm_path::__private::syn::parse_macro_input!(#tokens_ident as #mm_path::mm_core::DeriveItemWithExtra);
+                #version_check
+
+                let #tokens_ident: proc_macro::TokenStream = __combined_args.derive_input.to_token_stream().into();
+                let __foreign_tokens: proc_macro::TokenStream = __combined_args.imported_item.to_token_stream().into();
+                let __source_path: proc_macro::TokenStream = __combined_args.source_path.into();
+                let __export_info: #mm_path::mm_core::ExportInfo = __combined_args.export_info.clone();
+                let __source_info: #mm_path::mm_core::SourceInfo = __combined_args.export_info.source;
+                let __tokens_hash: String = __combined_args.export_info.hash;
+
+                #(#orig_stmts)
+                *
+            }
+
+            // This is to avoid corrupting the scope with imports below
+            fn isolated_mm_override_path() -> String {
+                String::from(#mm_override_path)
+            }
+
+            use #mm_path::__private::*;
+            use #mm_path::__private::quote::ToTokens;
+
+            syn::custom_keyword!(__private_macro_magic_tokens_forwarded);
+
+            let derive_input = syn::parse_macro_input!(#tokens_ident as syn::DeriveInput);
+            let helper_args = match #mm_path::mm_core::derive_helper_args(&derive_input, #helper_str) {
+                Ok(args) => args,
+                Err(err) => return err.to_compile_error().into(),
+            };
+
+            let mut helper_tokens = helper_args.clone().into_iter();
+            let forwarded = helper_tokens.next().map_or(false, |token| {
+                syn::parse2::<__private_macro_magic_tokens_forwarded>(token.into()).is_ok()
+            });
+
+            if forwarded {
+                #inner_macro_ident(TokenStream2::from_iter(helper_tokens).into())
+            } else {
+                let source_path = match syn::parse::Parser::parse2(#mm_path::mm_core::parse_item_path, helper_args) {
+                    Ok(path) => path,
+                    Err(e) => return e.to_compile_error().into(),
+                };
+                let mm_override_tokenstream = isolated_mm_override_path().parse().unwrap();
+                let resolved_mm_override_path = match syn::parse2::<syn::Path>(mm_override_tokenstream) {
+                    Ok(res) => res,
+                    Err(err) => return err.to_compile_error().into()
+                };
+                quote::quote! {
+                    #pound resolved_mm_override_path::forward_tokens! {
+                        #pound source_path,
+                        #derive_name,
+                        #pound resolved_mm_override_path,
+                        {
+                            #helper,
+                            { #pound source_path },
+                            #pound derive_input
+                        },
+                        derive
+                        #prefix
+                    }
+                }.into()
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn export_tokens_internal_missing_ident() {
+        assert!(
+            export_tokens_internal(quote!(), quote!(impl MyTrait for Something), true).is_err()
+        );
+    }
+
+    #[test]
+    fn export_ident_for_item_derived_names() {
+        let name = |item: Item| export_ident_for_item(&item).map(|ident| ident.to_string());
+        let impl_name = |item: syn::ItemImpl| name(Item::Impl(item));
+        assert_eq!(
+            name(parse_quote!(
+                struct MyStruct {}
+            )),
+            Some(String::from("MyStruct"))
+        );
+        assert_eq!(
+            impl_name(parse_quote!(impl Display for Foo {})),
+            Some(String::from("display_for_foo"))
+        );
+        assert_eq!(
+            impl_name(parse_quote!(
+                impl<'a, T> fmt::Debug for &'a MyType<T> {}
+            )),
+            Some(String::from("debug_for_my_type"))
+        );
+        assert_eq!(
+            impl_name(parse_quote!(
+                impl<T> Foo<T> {}
+            )),
+            Some(String::from("impl_foo"))
+        );
+        assert_eq!(
+            impl_name(parse_quote!(
+                impl<T: Clone> Foo<T> where T: Copy {}
+            )),
+            Some(String::from("impl_foo"))
+        );
+        assert_eq!(
+            impl_name(parse_quote!(impl From<u8> for Foo {})),
+            Some(String::from("from_u8_for_foo"))
+        );
+        assert_eq!(
+            impl_name(parse_quote!(
+                impl<'a> From<[u16; 4]> for Foo<'a, 4> {}
+            )),
+            Some(String::from("from_u16_array_for_foo"))
+        );
+        assert_eq!(
+            impl_name(parse_quote!(
+                impl<F: Fn(u32) -> u64> Callback<F> for Box<F> {}
+            )),
+            Some(String::from("callback_f_for_box"))
+        );
+        assert_eq!(
+            impl_name(parse_quote!(
+                impl<F> Handler for Wrapper<F> where F: Fn(u32) -> u64 {}
+            )),
+            Some(String::from("handler_for_wrapper"))
+        );
+        assert_eq!(
+            impl_name(parse_quote!(
+                impl Iterator<Item = Vec<u8>> for MyIter {}
+            )),
+            Some(String::from("iterator_vec_u8_for_my_iter"))
+        );
+        assert_eq!(
+            impl_name(parse_quote!(
+                impl (u8, u16) {}
+            )),
+            None
+        );
+        assert_eq!(
+            name(parse_quote!(
+                use std::fmt::Display;
+            )),
+            Some(String::from("use_std_fmt_display"))
+        );
+        assert_eq!(
+            name(parse_quote!(
+                use std::{fmt, io};
+            )),
+            Some(String::from("use_std_fmt"))
+        );
+        assert_eq!(
+            name(parse_quote!(
+                use std::io::Result as IoResult;
+            )),
+            Some(String::from("use_std_io_io_result"))
+        );
+        assert_eq!(
+            name(parse_quote!(
+                use std::*;
+            )),
+            Some(String::from("use_std"))
+        );
+        assert_eq!(
+            name(parse_quote!(
+                extern "C" {
+                    fn foo();
+                }
+            )),
+            Some(String::from("extern_c_foo"))
+        );
+        assert_eq!(
+            name(parse_quote!(
+                extern "C-unwind" {
+                    static BAR_VALUE: u32;
+                }
+            )),
+            Some(String::from("extern_c_unwind_bar_value"))
+        );
+        assert_eq!(
+            name(parse_quote!(
+                extern "C" {}
+            )),
+            None
+        );
+        assert_eq!(name(parse_quote!(some_macro!();)), None);
+    }
+
+    #[test]
+    fn export_tokens_internal_derived_name() {
+        let tokens = export_tokens_internal(
+            quote!(),
+            quote!(
+                impl Display for Foo {}
+            ),
+            true,
+        )
+        .unwrap()
+        .to_string();
+        assert!(tokens.contains("as __export_tokens_tt_display_for_foo"));
+        let err = export_tokens_internal(quote!(), quote!(some_macro!();), true)
+            .unwrap_err()
+            .to_string();
+        assert!(err.starts_with("an override name must be specified"));
+    }
+
+    #[test]
+    fn export_tokens_internal_normal_no_ident() {
+        assert!(export_tokens_internal(
+            quote!(),
+            quote!(
+                struct MyStruct {}
+            ),
+            true
+        )
+        .unwrap()
+        .to_string()
+        .contains("my_struct"));
+    }
+
+    #[test]
+    fn export_tokens_internal_normal_ident() {
+        assert!(export_tokens_internal(
+            quote!(some_name),
+            quote!(
+                struct Something {}
+            ),
+            true,
+        )
+        .unwrap()
+        .to_string()
+        .contains("some_name"));
+    }
+
+    #[test]
+    fn export_tokens_internal_generics_no_ident() {
+        assert!(export_tokens_internal(
+            quote!(),
+            quote!(
+                struct MyStruct<T> {}
+            ),
+            true,
+        )
+        .unwrap()
+        .to_string()
+        .contains("__export_tokens_tt_my_struct"));
+    }
+
+    #[test]
+    fn export_tokens_internal_bad_ident() {
+        assert!(export_tokens_internal(
+            quote!(Something<T>),
+            quote!(
+                struct MyStruct {}
+            ),
+            true,
+        )
+        .is_err());
+        assert!(export_tokens_internal(
+            quote!(some::path),
+            quote!(
+                struct MyStruct {}
+            ),
+            true,
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_export_tokens_no_emit() {
+        assert!(export_tokens_internal(
+            quote!(some_name),
+            quote!(
+                struct Something {}
+            ),
+            false,
+        )
+        .unwrap()
+        .to_string()
+        .contains("some_name"));
+    }
+
+    #[test]
+    fn export_tokens_internal_associated_items() {
+        let tokens = export_tokens_internal(
+            quote!(impl_my_type),
+            quote! {
+                impl MyType {
+                    #[export_tokens]
+                    fn my_method(&self) -> u32 {
+                        3
+                    }
+
+                    #[export_tokens(SomeConst)]
+                    const MY_CONST: u32 = 4;
+
+                    fn not_exported() {}
+                }
+            },
+            true,
+        )
+        .unwrap()
+        .to_string();
+        assert!(tokens.contains("__export_tokens_tt_impl_my_type"));
+        assert!(tokens.contains("__export_tokens_tt_my_type_my_method"));
+        assert!(tokens.contains("__export_tokens_tt_my_type_some_const"));
+        assert!(!tokens.contains("not_exported_"));
+        assert!(!tokens.contains("# [export_tokens"));
+    }
+
+    #[test]
+    fn export_tokens_internal_trait_associated_items() {
+        let tokens = export_tokens_internal(
+            quote!(),
+            quote! {
+                trait MyTrait {
+                    #[export_tokens]
+                    type Output;
+
+                    #[export_tokens]
+                    fn required(&self);
+                }
+            },
+            true,
+        )
+        .unwrap()
+        .to_string();
+        assert!(tokens.contains("__export_tokens_tt_my_trait_output"));
+        assert!(tokens.contains("__export_tokens_tt_my_trait_required"));
+    }
+
+    #[test]
+    fn export_tokens_macro_path_associated_items() {
+        let macro_path = |tokens: TokenStream2| {
+            let path = parse_item_path.parse2(tokens).unwrap();
+            export_tokens_macro_path(&path)
+                .to_token_stream()
+                .to_string()
+        };
+        assert_eq!(
+            macro_path(quote!(<my_crate::MyType>::my_method)),
+            "my_crate :: __export_tokens_tt_my_type_my_method"
+        );
+        assert_eq!(
+            macro_path(quote!(<MyTrait>::CONST)),
+            "__export_tokens_tt_my_trait_const"
+        );
+        assert_eq!(
+            macro_path(quote!(<my_crate::MyType as core::fmt::Display>::fmt)),
+            "my_crate :: __export_tokens_tt_display_for_my_type_fmt"
+        );
+        // without qualified path syntax, paths always refer to items in modules
+        assert_eq!(
+            macro_path(quote!(my_crate::MyType::my_method)),
+            "my_crate :: MyType :: __export_tokens_tt_my_method"
+        );
+        assert_eq!(
+            macro_path(quote!(my_crate::some_mod::MyType)),
+            "my_crate :: some_mod :: __export_tokens_tt_my_type"
+        );
+    }
+
+    #[test]
+    fn export_tokens_internal_trait_impl_associated_items() {
+        let export = |trait_name: TokenStream2| {
+            export_tokens_internal(
+                quote!(),
+                quote! {
+                    impl #trait_name for MyType {
+                        #[export_tokens]
+                        fn fmt(&self, f: &mut Formatter) -> Result {
+                            Ok(())
+                        }
+                    }
+                },
+                true,
+            )
+            .unwrap()
+            .to_string()
+        };
+        assert!(export(quote!(Display)).contains("__export_tokens_tt_display_for_my_type_fmt"));
+        assert!(export(quote!(Debug)).contains("__export_tokens_tt_debug_for_my_type_fmt"));
+    }
+
+    #[test]
+    fn export_tokens_internal_private() {
+        let tokens = export_tokens_internal(
+            quote!(private),
+            quote!(
+                pub struct MyStruct {}
+            ),
+            true,
+        )
+        .unwrap()
+        .to_string();
+        assert!(!tokens.contains("macro_export"));
+        assert!(tokens.contains("pub (crate) use"));
+        assert!(tokens.contains("__export_tokens_tt_my_struct"));
+        let tokens = export_tokens_internal(
+            quote!(SomeName, private),
+            quote!(
+                struct MyStruct {}
+            ),
+            true,
+        )
+        .unwrap()
+        .to_string();
+        assert!(!tokens.contains("pub"));
+        assert!(tokens.contains("__export_tokens_tt_some_name"));
+    }
+
+    #[test]
+    fn export_tokens_internal_local() {
+        let tokens = export_tokens_internal(
+            quote!(local),
+            quote!(
+                struct MyStruct {}
+            ),
+            true,
+        )
+        .unwrap()
+        .to_string();
+        assert!(!tokens.contains("macro_export"));
+        assert!(tokens.contains("pub (crate) use"));
+        assert!(export_tokens_internal(
+            quote!(private, local),
+            quote!(
+                struct MyStruct {}
+            ),
+            true,
+        )
+        .is_err());
+        assert!(parse2::<ExportTokensArgs>(quote!(local, cfg, private)).is_err());
+        assert!(parse2::<ExportTokensArgs>(quote!(private, private)).is_ok());
+    }
+
+    #[test]
+    fn export_tokens_internal_multiple_names() {
+        let tokens = export_tokens_internal(
+            quote!(SomeName, other_name, LegacyName, some_name),
+            quote!(
+                struct MyStruct {}
+            ),
+            true,
+        )
+        .unwrap()
+        .to_string();
+        assert_eq!(tokens.matches("macro_rules !").count(), 1);
+        assert_eq!(tokens.matches("pub use").count(), 3);
+        assert!(tokens.contains("as __export_tokens_tt_some_name ;"));
+        assert!(tokens.contains("as __export_tokens_tt_other_name ;"));
+        assert!(tokens.contains("as __export_tokens_tt_legacy_name ;"));
+    }
+
+    #[test]
+    fn export_tokens_internal_recursive() {
+        let tokens = export_tokens_internal(
+            quote!(recursive),
+            quote! {
+                mod my_mod {
+                    struct ChildStruct {}
+                    impl ChildStruct {}
+                    #[export_tokens(Renamed)]
+                    fn already_exported() {}
+                    mod nested {
+                        const NESTED: u8 = 1;
+                    }
+                }
+            },
+            true,
+        )
+        .unwrap()
+        .to_string();
+        assert_eq!(tokens.matches("macro_rules !").count(), 4);
+        assert!(tokens.contains("as __export_tokens_tt_my_mod ;"));
+        assert!(tokens.contains("as __export_tokens_tt_child_struct ;"));
+        assert!(tokens.contains("as __export_tokens_tt_nested ;"));
+        assert!(tokens.contains("as __export_tokens_tt_n_e_s_t_e_d ;"));
+        assert!(!tokens.contains("as __export_tokens_tt_already_exported ;"));
+    }
+
+    #[test]
+    fn export_tokens_tags_and_meta() {
+        let args = parse2::<ExportTokensArgs>(quote! {
+            MyName, tags(db, api), meta(table = "users", shards = 4, sharded = true)
+        })
+        .unwrap();
+        assert_eq!(args.names.len(), 1);
+        assert_eq!(args.tags, ["db", "api"]);
+        assert_eq!(args.meta.len(), 3);
+        let tokens = export_tokens_internal(
+            quote!(tags(db, api), meta(table = "users", shards = 4)),
+            quote!(
+                struct MyStruct {}
+            ),
+            false,
+        )
+        .unwrap()
+        .to_string();
+        assert!(tokens.contains("tags (db , api) , meta (shards = \"4\" , table = \"users\")"));
+        let info = parse2::<ExportInfo>(quote! {
+            [source(), hash = "", tags(db, api), meta(table = "users", shards = 4)]
+        })
+        .unwrap();
+        assert!(info.has_tag("db"));
+        assert!(!info.has_tag("cache"));
+        assert_eq!(info.meta("table"), Some("users"));
+        assert_eq!(info.meta("shards"), Some("4"));
+        assert_eq!(info.meta("missing"), None);
+        assert!(parse2::<ExportTokensArgs>(quote!(meta(table = "a", table = "b"))).is_err());
+    }
+
+    #[test]
+    fn export_group_members() {
+        let check = |path: TokenStream2| check_group_member(&parse2(path).unwrap());
+        assert!(check(quote!(crate::billing::Invoice)).is_ok());
+        assert!(check(quote!(::other_crate::Item)).is_ok());
+        assert!(check(quote!(User)).is_err());
+        assert!(check(quote!(self::User)).is_err());
+        assert!(check(quote!(super::Account)).is_err());
+        let path = export_tokens_macro_path(&parse_quote!(crate::billing::Invoice));
+        assert_eq!(
+            dollar_crate_path(&path).to_string(),
+            "$ crate :: billing :: __export_tokens_tt_invoice"
+        );
+        assert!(export_group_internal(quote!(empty = [])).is_err());
+        assert!(export_group_internal(quote!(api_types = [User, crate::Account])).is_err());
+        let tokens = export_group_internal(quote!(api_types = [crate::User, crate::Account]))
+            .unwrap()
+            .to_string();
+        assert!(tokens.contains("pub use __export_tokens_tt_"));
+        assert!(tokens.contains("as __export_tokens_tt_api_types"));
+        assert!(tokens.contains("[$ crate :: __export_tokens_tt_account] , [] ,"));
+    }
+
+    #[test]
+    fn export_group_inner_collects_items() {
+        let info = ExportInfo {
+            group: true,
+            ..Default::default()
+        };
+        let member_info = ExportInfo::default();
+        let tokens = export_group_inner_internal(quote! {
+            __mm_group, struct User {}, #member_info, api_types, [crate::__export_tokens_tt_account],
+            [], #info, { tokens }, { my_callback }, {}
+        })
+        .unwrap()
+        .to_string();
+        assert!(tokens.starts_with("crate :: __export_tokens_tt_account ! { __mm_group ,"));
+        assert!(tokens.contains("api_types , [] , [struct User { }] ,"));
+        let tokens = export_group_inner_internal(quote! {
+            __mm_group, struct Account {}, #member_info, api_types, [],
+            [struct User {}], #info, { tokens }, { my_callback }, { extra }
+        })
+        .unwrap();
+        let item: Item = parse_quote!(
+            mod api_types {
+                struct User {}
+                struct Account {}
+            }
+        );
+        let mut expected_info = info.clone();
+        expected_info.hash = tokens_hash(&item.to_token_stream());
+        assert_eq!(
+            tokens.to_string(),
+            quote!(my_callback! { tokens, #item, #expected_info, extra }).to_string()
+        );
+        let items = expected_info.group_items(item);
+        assert_eq!(items.len(), 2);
+        let item: Item = parse_quote!(
+            mod not_a_group {
+                struct User {}
+            }
+        );
+        assert_eq!(ExportInfo::default().group_items(item).len(), 1);
+    }
+
+    #[test]
+    fn export_tokens_projections() {
+        let args = parse2::<ExportTokensArgs>(quote! {
+            MyName, strip_docs, signature_only, public_fields_only, strip_attrs(derive, serde::rename)
+        })
+        .unwrap();
+        assert_eq!(args.names.len(), 1);
+        assert!(args.projection.strip_docs);
+        assert!(args.projection.signature_only);
+        assert!(args.projection.public_fields_only);
+        assert_eq!(args.projection.strip_attrs.len(), 2);
+        let mut item: Item = parse_quote! {
+            /// Some docs
+            #[derive(Debug)]
+            #[serde::rename = "other"]
+            pub struct MyStruct {
+                /// A field
+                #[serde(default)]
+                pub a: u32,
+                b: u32,
+                pub(crate) c: u32,
+            }
+        };
+        args.projection.project_item(&mut item).unwrap();
+        let expected =
+            syn::parse_str::<Item>("pub struct MyStruct { #[serde(default)] pub a: u32, }")
+                .unwrap();
+        assert_eq!(
+            item.to_token_stream().to_string(),
+            expected.to_token_stream().to_string()
+        );
+        let mut item: Item = parse_quote! {
+            impl MyTrait for MyStruct {
+                /// Docs
+                fn my_method(&self) -> u32 {
+                    self.a
+                }
+            }
+        };
+        args.projection.project_item(&mut item).unwrap();
+        assert_eq!(
+            item.to_token_stream().to_string(),
+            quote!(impl MyTrait for MyStruct { fn my_method(&self) -> u32 {} }).to_string()
+        );
+        let mut item: Item = parse_quote! {
+            trait MyTrait {
+                fn with_default(&self) -> u32 {
+                    2
+                }
+            }
+        };
+        args.projection.project_item(&mut item).unwrap();
+        assert_eq!(
+            item.to_token_stream().to_string(),
+            quote!(
+                trait MyTrait {
+                    fn with_default(&self) -> u32;
+                }
+            )
+            .to_string()
+        );
+        let item = quote! {
+            /// Some docs
+            pub fn my_function(a: u32) -> u32 {
+                a + 1
+            }
+        };
+        let tokens = export_tokens_internal(quote!(signature_only), item.clone(), true)
+            .unwrap()
+            .to_string();
+        assert!(tokens
+            .contains("# [doc = r\" Some docs\"] pub fn my_function (a : u32) -> u32 { } , ["));
+        assert!(tokens.contains(", signature_only] $"));
+        assert!(tokens.ends_with(&quote!(#[allow(unused)] #item).to_string()));
+        assert!(ExportProjection::default().is_empty());
+        let mut item: Item = parse_quote!(
+            pub struct MyTuple(pub u32, u32);
+        );
+        assert!(args.projection.project_item(&mut item).is_err());
+        let mut item: Item = parse_quote!(
+            enum MyEnum {
+                A(u32, u32),
+            }
+        );
+        assert!(args.projection.project_item(&mut item).is_ok());
+    }
+
+    #[test]
+    fn export_tokens_internal_recursive_errors() {
+        let non_mod = export_tokens_internal(
+            quote!(recursive),
+            quote!(
+                struct MyStruct {}
+            ),
+            true,
+        );
+        assert!(non_mod.is_err());
+        let no_emit = export_tokens_internal(
+            quote!(recursive),
+            quote!(
+                mod my_mod {}
+            ),
+            false,
+        );
+        assert!(no_emit.is_err());
+        let collision = export_tokens_internal(
+            quote!(recursive),
+            quote! {
+                mod my_mod {
+                    struct Foo {}
+                    fn foo() {}
+                }
+            },
+            true,
+        );
+        assert!(collision
+            .unwrap_err()
+            .to_string()
+            .contains("`foo` collides with `Foo`"));
+    }
+
+    #[test]
+    fn export_tokens_deterministic_idents() {
+        let source = SourceInfo {
+            crate_name: String::from("my_crate"),
+            crate_version: String::from("1.0.0"),
+            module_path: String::from("my_crate::deterministic"),
+            file: String::from("src/deterministic.rs"),
+            line: 3,
+            column: 4,
+        };
+        let ident = format_ident!("DeterministicStruct");
+        let first = new_unique_export_tokens_ident(
+            &ident,
+            MACRO_MAGIC_EXPORT_PREFIX,
+            &source,
+            "0123456789abcdef",
+        );
+        let tag = tokens_hash(&quote! {
+            "deterministic_struct", "my_crate", "1.0.0", "my_crate::deterministic",
+            "src/deterministic.rs", "0123456789abcdef", 0u64
+        });
+        assert_eq!(
+            first.to_string(),
+            format!("__export_tokens_tt_{tag}_deterministic_struct")
+        );
+        let second = new_unique_export_tokens_ident(
+            &ident,
+            MACRO_MAGIC_EXPORT_PREFIX,
+            &source,
+            "0123456789abcdef",
+        );
+        assert_eq!(second, first);
+        let mut other_module = source.clone();
+        other_module.module_path = String::from("my_crate::other");
+        let other_module = new_unique_export_tokens_ident(
+            &ident,
+            MACRO_MAGIC_EXPORT_PREFIX,
+            &other_module,
+            "0123456789abcdef",
+        );
+        assert_ne!(other_module, first);
+        // editing code above the item doesn't change the name
+        let mut other_line = source.clone();
+        other_line.line = 30;
+        other_line.column = 0;
+        let other_line = new_unique_export_tokens_ident(
+            &ident,
+            MACRO_MAGIC_EXPORT_PREFIX,
+            &other_line,
+            "0123456789abcdef",
+        );
+        assert_eq!(other_line, first);
+        let other_content = new_unique_export_tokens_ident(
+            &ident,
+            MACRO_MAGIC_EXPORT_PREFIX,
+            &source,
+            "fedcba9876543210",
+        );
+        assert!(!other_content.to_string().contains(&tag));
+        let mut other_crate = source.clone();
+        other_crate.crate_name = String::from("other_crate");
+        let other_crate = new_unique_export_tokens_ident(
+            &ident,
+            MACRO_MAGIC_EXPORT_PREFIX,
+            &other_crate,
+            "0123456789abcdef",
+        );
+        assert!(!other_crate.to_string().contains(&tag));
+        assert_eq!(span_context(Span::call_site()), 0);
+    }
+
+    #[test]
+    fn export_tokens_collisions() {
+        let names = [format_ident!("MyCoolName"), format_ident!("my_cool_name")];
+        // names of the same export may normalize to the same name
+        assert!(check_export_collisions(&[&names], MACRO_MAGIC_EXPORT_PREFIX).is_ok());
+        let err = check_export_collisions(&[&names[..1], &names[1..]], MACRO_MAGIC_EXPORT_PREFIX)
+            .unwrap_err()
+            .to_string();
+        assert!(err.starts_with(
+            "`my_cool_name` collides with `MyCoolName` when exported, since both are exported \
+            as `__export_tokens_tt_my_cool_name`"
+        ));
+        assert!(err.ends_with("`#[export_tokens(SomeOtherName)]`"));
+        // the same raw name exported twice collides as well
+        assert!(check_export_collisions(&[&names[..1], &names[..1]], "__my_prefix_").is_err());
+        let err = export_tokens_internal(
+            quote!(),
+            quote! {
+                impl MyType {
+                    #[export_tokens]
+                    fn my_method() {}
+
+                    #[export_tokens(my_method)]
+                    fn other_method() {}
+                }
+            },
+            true,
+        )
+        .unwrap_err()
+        .to_string();
+        assert!(err.starts_with("`my_type_my_method` collides with `my_type_my_method`"));
+    }
+
+    #[test]
+    fn export_tokens_file_internal_missing_file() {
+        let err = export_tokens_file_internal(quote!("schemas/missing.rs"))
+            .unwrap_err()
+            .to_string();
+        assert!(err.starts_with("failed to read"));
+        assert!(export_tokens_file_internal(quote!(schemas::user)).is_err());
+    }
+
+    #[test]
+    fn test_locate_item_name() {
+        let contents = "/// Made by `make_user`.\npub fn make_user() -> User {}\n\n\
+            pub struct User;\nmacro_rules! user {}\n";
+        let user = format_ident!("User");
+        assert_eq!(locate_item_name(contents, &user, 0), Some((71, 4, 11)));
+        let make_user = format_ident!("make_user");
+        assert_eq!(locate_item_name(contents, &make_user, 0), Some((41, 2, 7)));
+        assert_eq!(locate_item_name(contents, &make_user, 41), None);
+        let macro_user = format_ident!("user");
+        assert_eq!(
+            locate_item_name(contents, &macro_user, 71),
+            Some((90, 5, 13))
+        );
+        assert_eq!(locate_item_name(contents, &format_ident!("Use"), 0), None);
+    }
+
+    #[test]
+    fn import_tokens_internal_simple_path() {
+        assert!(
+            import_tokens_internal(quote!(let tokens = my_crate::SomethingCool))
+                .unwrap()
+                .to_string()
+                .contains("__export_tokens_tt_something_cool")
+        );
+    }
+
+    #[test]
+    fn import_tokens_internal_flatten_long_paths() {
+        assert!(import_tokens_internal(
+            quote!(let tokens = my_crate::some_mod::complex::SomethingElse)
+        )
+        .unwrap()
+        .to_string()
+        .contains("__export_tokens_tt_something_else"));
+    }
+
+    #[test]
+    fn import_tokens_internal_invalid_token_ident() {
+        assert!(import_tokens_internal(quote!(let 3 * 2 = my_crate::something)).is_err());
+    }
+
+    #[test]
+    fn import_tokens_internal_invalid_path() {
+        assert!(import_tokens_internal(quote!(let my_tokens = 2 - 2)).is_err());
+    }
+
+    #[test]
+    fn import_tokens_inner_internal_basic() {
+        assert!(import_tokens_inner_internal(quote! {
+            my_ident,
+            fn my_function() -> u32 {
+                33
+            }, [source(crate_name = "my_crate", line = 3)]
+        })
+        .unwrap()
+        .to_string()
+        .contains("my_ident"));
+    }
+
+    #[test]
+    fn import_tokens_inner_internal_impl() {
+        assert!(import_tokens_inner_internal(quote! {
+            another_ident,
+            impl Something for MyThing {
+                fn something() -> CoolStuff {
+                    CoolStuff {}
+                }
+            },
+            [source()]
+        })
+        .unwrap()
+        .to_string()
+        .contains("something ()"));
+    }
+
+    #[test]
+    fn import_tokens_inner_internal_missing_comma() {
+        assert!(import_tokens_inner_internal(quote! {
+            {
+                another_ident
+                impl Something for MyThing {
+                    fn something() -> CoolStuff {
+                        CoolStuff {}
+                    }
+                }
+            }
+        })
+        .is_err());
+    }
+
+    #[test]
+    fn import_tokens_inner_internal_non_item() {
+        assert!(import_tokens_inner_internal(quote! {
+            {
+                another_ident,
+                2 + 2
+            }
+        })
+        .is_err());
+    }
+
+    #[test]
+    fn export_tokens_cfg_predicates() {
+        let item = quote! {
+            struct MyStruct {
+                #[cfg(feature = "std")]
+                a: u32,
+                #[cfg(not(feature = "std"))]
+                b: u32,
+                #[cfg(feature = "std")]
+                c: u32,
+                #[cfg_attr(test, derive(Debug))]
+                d: u32,
+            }
+        };
+        let predicates: Vec<String> = cfg_predicates(&item)
+            .iter()
+            .map(|predicate| predicate.to_string())
+            .collect();
+        assert_eq!(
+            predicates,
+            [
+                quote!(feature = "std").to_string(),
+                quote!(not(feature = "std")).to_string()
+            ]
+        );
+        let tokens = export_tokens_internal(quote!(cfg), item.clone(), true)
+            .unwrap()
+            .to_string();
+        assert!(tokens.contains("# [cfg (feature = \"std\")] macro_rules !"));
+        assert!(tokens.contains("# [cfg (not (feature = \"std\"))] macro_rules !"));
+        assert!(tokens.contains(
+            "cfg ((feature = \"std\") = $ active_0 , (not (feature = \"std\")) = $ active_1)"
+        ));
+        let tokens = export_tokens_internal(quote!(), item, true)
+            .unwrap()
+            .to_string();
+        assert!(!tokens.contains("active_0"));
+    }
+
+    #[test]
+    fn strip_inactive_cfg_item() {
+        let mut item: Item = parse_quote! {
+            struct MyStruct {
+                #[cfg(feature = "std")]
+                a: u32,
+                #[cfg(not(feature = "std"))]
+                b: u32,
+                #[cfg(unrecorded)]
+                c: u32,
+            }
+        };
+        let info = ExportInfo {
+            cfg: vec![
+                CfgPredicate {
+                    predicate: quote!(feature = "std").to_string(),
+                    active: true,
+                },
+                CfgPredicate {
+                    predicate: quote!(not(feature = "std")).to_string(),
+                    active: false,
+                },
+            ],
+            ..Default::default()
+        };
+        assert!(strip_inactive_cfg(&mut item, &info));
+        let expected =
+            syn::parse_str::<Item>("struct MyStruct { a: u32, #[cfg(unrecorded)] c: u32 }")
+                .unwrap();
+        assert_eq!(
+            item.to_token_stream().to_string(),
+            expected.to_token_stream().to_string()
+        );
+        let mut item: Item = parse_quote! {
+            mod my_mod {
+                #[cfg(feature = "std")]
+                enum MyEnum {
+                    #[cfg(not(feature = "std"))]
+                    A,
+                    B(#[cfg(feature = "std")] u8, #[cfg(not(feature = "std"))] u16),
+                }
+                #[cfg(not(feature = "std"))]
+                fn removed() {}
+            }
+        };
+        assert!(strip_inactive_cfg(&mut item, &info));
+        let expected = syn::parse_str::<Item>("mod my_mod { enum MyEnum { B(u8) } }").unwrap();
+        assert_eq!(
+            item.to_token_stream().to_string(),
+            expected.to_token_stream().to_string()
+        );
+        let mut item: Item = parse_quote! {
+            #[cfg(not(feature = "std"))]
+            struct Removed;
+        };
+        assert!(!strip_inactive_cfg(&mut item, &info));
+    }
+
+    #[test]
+    fn export_info_round_trip() {
+        let info = ExportInfo {
+            source: SourceInfo {
+                crate_name: String::from("my_crate"),
+                crate_version: String::from("1.2.3"),
+                module_path: String::from("my_crate::some_module"),
+                file: String::from("src/some_module.rs"),
+                line: 12,
+                column: 4,
+            },
+            hash: String::from("0123456789abcdef"),
+            version: Some(2),
+            cfg: vec![
+                CfgPredicate {
+                    predicate: quote!(feature = "std").to_string(),
+                    active: true,
+                },
+                CfgPredicate {
+                    predicate: quote!(not(test)).to_string(),
+                    active: false,
+                },
+            ],
+            tags: vec![String::from("db"), String::from("api")],
+            meta: BTreeMap::from([
+                (String::from("table"), String::from("users")),
+                (String::from("shards"), String::from("4")),
+            ]),
+            group: true,
+            placeholders: vec![String::from("T"), String::from("Field")],
+            fragment_kind: Some(FragmentKind::Stmts),
+            signature_only: true,
+        };
+        assert_eq!(parse2::<ExportInfo>(info.to_token_stream()).unwrap(), info);
+        assert!(parse2::<ExportInfo>(quote!([source(crate_nom = "my_crate")])).is_err());
+    }
+
+    #[test]
+    fn export_tokens_internal_source_info() {
+        let tokens = export_tokens_internal(
+            quote!(),
+            quote!(
+                struct MyStruct {}
+            ),
+            true,
+        )
+        .unwrap()
+        .to_string();
+        let crate_version = format!("crate_version = \"{}\"", env!("CARGO_PKG_VERSION"));
+        assert!(tokens.contains("crate_name = \"macro_magic_core\""));
+        assert!(tokens.contains(&crate_version));
+        assert!(tokens.contains("module_path = \"macro_magic_core\""));
+    }
+
+    #[test]
+    fn forward_tokens_inner_internal_proc_macro() {
+        let tokens = forward_tokens_inner_internal(quote! {
+            my_macro,
+            struct MyStruct {},
+            [source(crate_name = "my_crate")],
+            proc_macro
+        })
+        .unwrap()
+        .to_string();
+        assert!(tokens.starts_with("my_macro ! { __private_macro_magic_tokens_forwarded struct"));
+        assert!(tokens.contains("crate_name = \"my_crate\""));
+        let tokens = forward_tokens_inner_internal(quote! {
+            my_macro,
+            struct MyStruct {},
+            [source(crate_name = "my_crate")],
+            { my_crate::MyStruct },
+            proc_macro
+        })
+        .unwrap()
+        .to_string();
+        assert!(tokens.contains("struct MyStruct { } , { my_crate :: MyStruct } , [source"));
+        let tokens = tokens
+            .trim_start_matches("my_macro ! { __private_macro_magic_tokens_forwarded")
+            .trim_end_matches('}');
+        let parsed = parse2::<ProcItemWithExtra>(tokens.parse().unwrap()).unwrap();
+        assert_eq!(parsed.source_path.to_string(), "my_crate :: MyStruct");
+        assert_eq!(parsed.export_info.source.crate_name, "my_crate");
+    }
+
+    #[test]
+    fn forward_tokens_derive() {
+        let tokens = forward_tokens_internal(quote! {
+            a::Foo, MyDerive, ::macro_magic, { from, { a::Foo }, struct Bar; }, derive
+        })
+        .unwrap()
+        .to_string();
+        assert!(tokens.ends_with("{ from , { a :: Foo } , struct Bar ; } , derive }"));
+        assert!(forward_tokens_internal(quote!(a::Foo, MyDerive, ::macro_magic, derive)).is_err());
+        let tokens = forward_tokens_inner_internal(quote! {
+            MyDerive,
+            struct Foo {},
+            [source(crate_name = "my_crate")],
+            { from, { a::Foo }, struct Bar; },
+            derive
+        })
+        .unwrap()
+        .to_string();
+        assert!(tokens.starts_with(
+            "const _ : () = { # [derive (MyDerive)] # [from (__private_macro_magic_tokens_forwarded \
+            struct Foo"
+        ));
+        assert!(tokens.ends_with("struct __MacroMagicDerive_MyDerive_Bar ; } ;"));
+    }
+
+    #[test]
+    fn import_tokens_derive_internal_checks() {
+        let derive_fn = quote! {
+            #[proc_macro_derive(MyDerive, attributes(from))]
+            pub fn my_derive(tokens: TokenStream) -> TokenStream {
+                tokens
+            }
+        };
+        let proc_macro = ProcMacro::from(derive_fn.clone()).unwrap();
+        assert_eq!(proc_macro.macro_type, ProcMacroType::Derive);
+        let tokens = import_tokens_derive_internal(quote!(from, version = 2), derive_fn.clone())
+            .unwrap()
+            .to_string();
+        assert!(tokens.contains("fn __import_tokens_derive_my_derive_inner"));
+        assert!(tokens.contains("derive_helper_args (& derive_input , \"from\")"));
+        let err = import_tokens_derive_internal(quote!(source), derive_fn)
+            .unwrap_err()
+            .to_string();
+        assert_eq!(
+            err,
+            "`source` must be declared as a helper attribute via `attributes(source)`"
+        );
+        let err = import_tokens_derive_internal(
+            quote!(from),
+            quote! {
+                #[proc_macro]
+                pub fn my_macro(tokens: TokenStream) -> TokenStream {
+                    tokens
+                }
+            },
+        )
+        .unwrap_err()
+        .to_string();
+        assert_eq!(
+            err,
+            "expected a function definition with #[proc_macro_derive] but found #[proc_macro] \
+            instead"
+        );
+    }
+
+    #[test]
+    fn derive_helper_args_lookup() {
+        let input: syn::DeriveInput = parse_quote! {
+            #[from(a::Foo)]
+            struct Bar;
+        };
+        assert_eq!(
+            derive_helper_args(&input, "from").unwrap().to_string(),
+            "a :: Foo"
+        );
+        assert!(derive_helper_args(&input, "source").is_err());
+        let input: syn::DeriveInput = parse_quote! {
+            #[from(a::Foo)]
+            #[from(b::Foo)]
+            struct Bar;
+        };
+        assert!(derive_helper_args(&input, "from").is_err());
+    }
 
     #[test]
-    fn export_tokens_internal_missing_ident() {
-        assert!(
-            export_tokens_internal(quote!(), quote!(impl MyTrait for Something), true).is_err()
+    fn forward_tokens_batch() {
+        let tokens = forward_tokens_internal(quote!([a::Foo, Bar, c::Baz], my_macro))
+            .unwrap()
+            .to_string();
+        assert!(tokens.starts_with(
+            "a :: __export_tokens_tt_foo ! { my_macro , :: macro_magic :: __private :: \
+            forward_tokens_batch , { :: macro_magic } , { Bar , c :: Baz } , { } }"
+        ));
+        assert!(forward_tokens_internal(quote!([], my_macro)).is_err());
+        assert!(forward_tokens_internal(quote!([Foo, Bar], my_macro, proc_macro)).is_err());
+        let info = ExportInfo::for_item(&format_ident!("Foo"));
+        let tokens = forward_tokens_batch_internal(quote! {
+            my_macro,
+            struct Bar {},
+            [source(crate_name = "my_crate"), hash = "bar"],
+            { ::macro_magic },
+            { c::Baz },
+            { { struct Foo {}, #info } },
+            prefix = "__fw_"
+        })
+        .unwrap();
+        assert!(tokens
+            .to_string()
+            .starts_with("c :: __fw_baz ! { my_macro ,"));
+        assert!(tokens.to_string().ends_with(", prefix = \"__fw_\" }"));
+        let chain = (|input: ParseStream| {
+            input.parse::<Path>()?;
+            input.parse::<Token![!]>()?;
+            let content;
+            syn::braced!(content in input);
+            content.parse::<Path>()?;
+            content.parse::<Comma>()?;
+            content.parse::<Path>()?;
+            content.parse::<Comma>()?;
+            let mm_path;
+            syn::braced!(mm_path in content);
+            mm_path.parse::<Path>()?;
+            content.parse::<Comma>()?;
+            let chain = content.parse::<ForwardChain>()?;
+            content.parse::<TokenStream2>()?;
+            Ok(chain)
+        })
+        .parse2(tokens)
+        .unwrap();
+        assert!(chain.remaining.is_empty());
+        assert_eq!(chain.collected.len(), 2);
+        assert_eq!(item_ident(&chain.collected[0].0).unwrap(), "Foo");
+        assert_eq!(chain.collected[0].1.hash, info.hash);
+        assert_eq!(chain.collected[1].1.hash, "bar");
+        let tokens = forward_tokens_batch_internal(quote! {
+            my_macro,
+            struct Baz {},
+            [source(crate_name = "my_crate")],
+            { ::macro_magic },
+            {},
+            { { struct Foo {}, #info } { struct Bar {}, #info } }
+        })
+        .unwrap()
+        .to_string();
+        assert_eq!(
+            tokens,
+            "my_macro ! { __private_macro_magic_tokens_forwarded struct Foo { } struct Bar { } \
+            struct Baz { } }"
         );
     }
 
     #[test]
-    fn export_tokens_internal_normal_no_ident() {
-        assert!(export_tokens_internal(
-            quote!(),
+    fn test_version_req() {
+        let req = |tokens: &str| parse2::<VersionReq>(tokens.parse().unwrap()).unwrap();
+        assert!(req("2").matches(2));
+        assert!(!req("2").matches(3));
+        assert!(req("1..3").matches(2));
+        assert!(!req("1..3").matches(3));
+        assert!(req("1..=3").matches(3));
+        assert!(req("2..").matches(7));
+        assert!(!req("..2").matches(2));
+        assert!(req("..=2").matches(2));
+        for req_str in ["2", "1..3", "1..=3", "2..", "..2", "..=2"] {
+            assert_eq!(req(req_str).to_string(), req_str);
+        }
+        assert!(parse2::<VersionReq>(quote!(latest)).is_err());
+    }
+
+    #[test]
+    fn export_tokens_internal_version() {
+        let tokens = export_tokens_internal(
+            quote!(version = 3),
             quote!(
                 struct MyStruct {}
             ),
-            true
+            true,
         )
         .unwrap()
-        .to_string()
-        .contains("my_struct"));
-    }
-
-    #[test]
-    fn export_tokens_internal_normal_ident() {
-        assert!(export_tokens_internal(
-            quote!(some_name),
+        .to_string();
+        assert!(tokens.contains(" version = 3"));
+        let tokens = export_tokens_internal(
+            quote!(version),
             quote!(
-                struct Something {}
+                struct MyStruct {}
             ),
             true,
         )
         .unwrap()
-        .to_string()
-        .contains("some_name"));
+        .to_string();
+        assert!(tokens.contains("__export_tokens_tt_version"));
+        assert!(!tokens.contains(" version ="));
     }
 
     #[test]
-    fn export_tokens_internal_generics_no_ident() {
-        assert!(export_tokens_internal(
-            quote!(),
-            quote!(
-                struct MyStruct<T> {}
-            ),
-            true,
+    fn attr_item_with_extra_multiple_paths() {
+        let list = parse2::<ForeignPathList>(quote!(a::Foo, b::Bar,)).unwrap();
+        let paths = list.foreign_paths();
+        assert_eq!(paths.len(), 2);
+        assert_eq!(paths[1].to_token_stream().to_string(), "b :: Bar");
+        assert!(parse2::<ForeignPathList>(quote!()).is_err());
+        let info = ExportInfo::default();
+        let args = parse2::<AttrItemWithExtra>(quote! {
+            struct Bar {},
+            {
+                { tokens },
+                { a::Foo, b::Bar, c::Baz },
+                {},
+                { c::Baz },
+                { { struct Foo {}, #info } }
+            },
+            #info
+        })
+        .unwrap();
+        assert_eq!(args.chain.collected.len(), 1);
+        let items = args.imported_items();
+        assert_eq!(items.len(), 2);
+        assert_eq!(item_ident(&items[0].0).unwrap(), "Foo");
+        assert_eq!(item_ident(&items[1].0).unwrap(), "Bar");
+        let tokens = args
+            .forward_next(
+                &parse_quote!(my_attr),
+                &parse_quote!(::macro_magic),
+                Some("__fw_"),
+            )
+            .unwrap()
+            .to_string();
+        assert!(tokens.starts_with(":: macro_magic :: forward_tokens ! { c :: Baz , my_attr ,"));
+        assert!(tokens.contains("{ } , { { struct Foo { } ,"));
+        assert!(tokens.ends_with(", prefix = \"__fw_\" }"));
+        let last = parse2::<AttrItemWithExtra>(quote! {
+            struct Baz {}, { { tokens }, { c::Baz }, {} }, #info
+        })
+        .unwrap();
+        assert!(last
+            .forward_next(&parse_quote!(my_attr), &parse_quote!(mm), None)
+            .is_none());
+        assert_eq!(last.imported_items().len(), 1);
+    }
+
+    #[test]
+    fn import_tokens_typed() {
+        let tokens = import_tokens_internal(
+            quote!(let item: syn::ItemStruct = my_crate::MyStruct, version = 2..),
         )
         .unwrap()
+        .to_string();
+        assert!(tokens
+            .ends_with("import_tokens_inner , { version = 2 .. , type = syn :: ItemStruct } }"));
+        let tokens = import_tokens_internal(quote!(let item: syn::Item = my_crate::MyStruct))
+            .unwrap()
+            .to_string();
+        assert!(tokens.ends_with("import_tokens_inner , { type = syn :: Item } }"));
+        let info = ExportInfo {
+            version: Some(2),
+            ..Default::default()
+        };
+        let tokens = import_tokens_inner_internal(quote! {
+            item, struct MyStruct {}, #info, { version = 2.., type = syn::ItemStruct }
+        })
+        .unwrap()
+        .to_string();
+        assert!(tokens.starts_with(
+            "let item : syn :: ItemStruct = :: macro_magic :: __private :: syn :: parse_str :: \
+            < syn :: ItemStruct >"
+        ));
+        for ty in [
+            quote!(syn::Item),
+            quote!(DeriveInput),
+            quote!(MyCustomParse),
+        ] {
+            assert!(import_tokens_inner_internal(quote! {
+                item, struct MyStruct {}, #info, { type = #ty }
+            })
+            .is_ok());
+        }
+        let err = import_tokens_inner_internal(quote! {
+            item, enum MyEnum {}, #info, { type = syn::ItemStruct }
+        })
+        .unwrap_err()
+        .to_string();
+        assert_eq!(
+            err,
+            "expected the imported item to be parsed as `syn::ItemStruct`, but it is an enum \
+            (`syn::ItemEnum`)"
+        );
+        assert!(import_tokens_inner_internal(quote! {
+            item, fn my_fn() {}, #info, { type = syn::DeriveInput }
+        })
+        .unwrap_err()
         .to_string()
-        .contains("__export_tokens_tt_my_struct"));
+        .contains("but it is a function (`syn::ItemFn`)"));
+        assert!(import_tokens_inner_internal(quote! {
+            item, struct MyStruct {}, #info, { version = 3, type = syn::ItemStruct }
+        })
+        .is_err());
     }
 
     #[test]
-    fn export_tokens_internal_bad_ident() {
-        assert!(export_tokens_internal(
-            quote!(Something<T>),
+    fn import_tokens_version() {
+        let tokens =
+            import_tokens_internal(quote!(let tokens = my_crate::MyStruct, version = 1..3))
+                .unwrap()
+                .to_string();
+        assert!(tokens.ends_with("import_tokens_inner , { version = 1 .. 3 } }"));
+        let info = ExportInfo {
+            version: Some(3),
+            ..Default::default()
+        };
+        assert!(import_tokens_inner_internal(quote! {
+            tokens, struct MyStruct {}, #info, { version = 3.. }
+        })
+        .is_ok());
+        let err = import_tokens_inner_internal(quote! {
+            tokens, struct MyStruct {}, #info, { version = 1..3 }
+        })
+        .unwrap_err()
+        .to_string();
+        assert!(err.contains("version 1..3 of the exported item"));
+        assert!(err.contains("but it has version 3"));
+        let info = ExportInfo::default();
+        assert!(import_tokens_inner_internal(quote! {
+            tokens, struct MyStruct {}, #info, { version = 1 }
+        })
+        .unwrap_err()
+        .to_string()
+        .contains("does not specify a version"));
+    }
+
+    #[test]
+    fn export_tokens_custom_prefix() {
+        let tokens = export_tokens_internal(
+            quote!(prefix = "__fw_export_"),
             quote!(
-                struct MyStruct {}
+                struct PrefixedStruct {}
             ),
             true,
         )
-        .is_err());
+        .unwrap()
+        .to_string();
+        assert!(tokens.contains("macro_rules ! __fw_export_"));
+        assert!(tokens.contains("as __fw_export_prefixed_struct"));
+        assert!(!tokens.contains("__export_tokens_tt_"));
         assert!(export_tokens_internal(
-            quote!(some::path),
+            quote!(prefix = "1fw"),
             quote!(
-                struct MyStruct {}
+                struct PrefixedStruct {}
             ),
             true,
         )
-        .is_err());
+        .unwrap_err()
+        .to_string()
+        .contains("must not start with a digit"));
+        let tokens = import_tokens_internal(
+            quote!(let tokens = my_crate::PrefixedStruct, prefix = "__fw_export_", version = 2),
+        )
+        .unwrap()
+        .to_string();
+        assert!(tokens.starts_with("my_crate :: __fw_export_prefixed_struct !"));
+        assert!(tokens.ends_with("import_tokens_inner , { version = 2 } }"));
+        let tokens = forward_tokens_internal(quote!(
+            my_crate::PrefixedStruct,
+            my_macro,
+            proc_macro,
+            prefix = "__fw_export_"
+        ))
+        .unwrap()
+        .to_string();
+        assert!(tokens.starts_with("my_crate :: __fw_export_prefixed_struct !"));
+        assert!(tokens.contains("forward_tokens_inner , proc_macro }"));
+        let tokens =
+            export_tokens_alias_internal(quote!(fw_export_tokens, prefix = "__fw_export_"), true)
+                .unwrap()
+                .to_string();
+        assert!(tokens.contains("args . with_prefix (\"__fw_export_\")"));
+        assert!(!tokens.contains("let attr"));
+        assert!(export_tokens_alias_internal(
+            quote!(fw_export_tokens, prefix = "__a_", prefix = "__b_"),
+            true
+        )
+        .unwrap_err()
+        .to_string()
+        .contains("can only be specified once"));
+        assert!(export_tokens_alias_internal(
+            quote!(fw_export_tokens, prefix = "__a_", options(strip_docs)),
+            true
+        )
+        .is_ok());
+        assert!(parse2::<ExportTokensArgs>(quote!(prefix = "__a_", prefix = "__b_")).is_err());
+        let args = parse2::<ExportTokensArgs>(quote!(MyName)).unwrap();
+        assert_eq!(args.with_prefix("__a_").unwrap().prefix(), "__a_");
+        let args = parse2::<ExportTokensArgs>(quote!(prefix = "__b_")).unwrap();
+        assert_eq!(
+            args.with_prefix("__a_").err().unwrap().to_string(),
+            "`prefix = \"__b_\"` can't be specified, since this attribute always uses the prefix \
+            \"__a_\""
+        );
     }
 
     #[test]
-    fn test_export_tokens_no_emit() {
-        assert!(export_tokens_internal(
-            quote!(some_name),
+    fn export_template_placeholders() {
+        let tokens = export_template_internal(
+            quote!(T, Field),
+            quote! {
+                impl Storage for T {
+                    fn key(&self) -> u64 {
+                        self.Field
+                    }
+                }
+            },
+        )
+        .unwrap()
+        .to_string();
+        assert!(tokens.contains("as __export_tokens_tt_storage"));
+        assert!(tokens.contains("template (T , Field)"));
+        assert!(!tokens.contains("# [allow (unused)]"));
+        let tokens = export_template_internal(
+            quote!(T, name = StorageTemplate, prefix = "__fw_"),
             quote!(
-                struct Something {}
+                impl Storage for T {}
             ),
-            false,
         )
         .unwrap()
+        .to_string();
+        assert!(tokens.contains("as __fw_storage_template"));
+        assert!(export_template_internal(
+            quote!(T, T),
+            quote!(
+                struct T;
+            )
+        )
+        .unwrap_err()
         .to_string()
-        .contains("some_name"));
+        .contains("duplicate placeholder `T`"));
+        assert!(export_template_internal(
+            quote!(),
+            quote!(
+                struct T;
+            )
+        )
+        .unwrap_err()
+        .to_string()
+        .contains("at least one placeholder"));
+        assert!(export_template_internal(quote!(T), quote!(impl T {}))
+            .unwrap_err()
+            .to_string()
+            .contains("a name must be specified"));
+        assert!(parse2::<ExportTokensArgs>(quote!(template(T))).is_err());
+        let args = parse2::<ExportTokensArgs>(quote!(MyName, __template(T, Field))).unwrap();
+        assert_eq!(args.placeholders, ["T", "Field"]);
     }
 
     #[test]
-    fn import_tokens_internal_simple_path() {
-        assert!(
-            import_tokens_internal(quote!(let tokens = my_crate::SomethingCool))
-                .unwrap()
-                .to_string()
-                .contains("__export_tokens_tt_something_cool")
+    fn instantiate_template_substitution() {
+        let tokens = instantiate_template_internal(quote!(
+            my_crate::Storage,
+            T = Vec<u8, A>,
+            N = 3 + 4,
+            prefix = "__fw_"
+        ))
+        .unwrap()
+        .to_string();
+        assert_eq!(
+            tokens,
+            "my_crate :: __fw_storage ! { __mm_template , :: macro_magic :: __private :: \
+            instantiate_template_inner , { T = Vec < u8 , A > , N = 3 + 4 } }"
         );
-    }
-
-    #[test]
-    fn import_tokens_internal_flatten_long_paths() {
-        assert!(import_tokens_internal(
-            quote!(let tokens = my_crate::some_mod::complex::SomethingElse)
-        )
+        let info = ExportInfo {
+            placeholders: vec![String::from("T"), String::from("Field")],
+            ..Default::default()
+        };
+        let item = quote! {
+            impl Storage for T {
+                fn key(&self) -> u64 {
+                    self.Field
+                }
+            }
+        };
+        let tokens = instantiate_template_inner_internal(quote! {
+            __mm_template, #item, #info, { Field = id, T = Option<MyType> }
+        })
         .unwrap()
+        .to_string();
+        assert_eq!(
+            tokens,
+            "impl Storage for Option < MyType > { fn key (& self) -> u64 { self . id } }"
+        );
+        let scaled_info = ExportInfo {
+            placeholders: vec![String::from("N")],
+            ..Default::default()
+        };
+        let tokens = instantiate_template_inner_internal(quote! {
+            __mm_template, const SCALED: u32 = N * 2;, #scaled_info, { N = 3 + 4 }
+        })
+        .unwrap()
+        .to_string();
+        assert_eq!(tokens, "const SCALED : u32 = (3 + 4) * 2 ;");
+        let err = instantiate_template_inner_internal(quote! {
+            __mm_template, #item, #info, { T = MyType }
+        })
+        .unwrap_err()
+        .to_string();
+        assert_eq!(
+            err,
+            "missing value for placeholder(s): Field, i.e. `Field = ..`"
+        );
+        let err = instantiate_template_inner_internal(quote! {
+            __mm_template, #item, #info, { T = MyType, Field = id, Other = u8 }
+        })
+        .unwrap_err()
+        .to_string();
+        assert_eq!(
+            err,
+            "unknown placeholder `Other`, expected one of: T, Field"
+        );
+        let info = ExportInfo::default();
+        assert!(instantiate_template_inner_internal(quote! {
+            __mm_template, #item, #info, { T = MyType }
+        })
+        .unwrap_err()
         .to_string()
-        .contains("__export_tokens_tt_something_else"));
+        .contains("is not a template"));
     }
 
     #[test]
-    fn import_tokens_internal_invalid_token_ident() {
-        assert!(import_tokens_internal(quote!(let 3 * 2 = my_crate::something)).is_err());
-    }
+    fn export_fragment_kinds() {
+        let tokens =
+            export_fragment_internal(quote!(default_timeout: expr = Duration::from_secs(30)))
+                .unwrap()
+                .to_string();
+        assert!(tokens.contains("default_timeout ! { Duration :: from_secs (30) }"));
+        assert!(tokens.contains(", fragment = expr]"));
+        assert!(tokens.contains("as __export_tokens_tt_default_timeout"));
+        let tokens = export_fragment_internal(quote!(prefix = "__fw_", id_type: ty = u64))
+            .unwrap()
+            .to_string();
+        assert!(tokens.contains("as __fw_id_type"));
+        assert!(export_fragment_internal(quote!(prefix: expr = 1)).is_ok());
+        assert!(export_fragment_internal(quote!(prefix = "1", id_type: ty = u64)).is_err());
+        assert!(export_fragment_internal(quote!(id_type: ty = u64)).is_ok());
+        assert!(export_fragment_internal(quote!(some_pat: pat = Some(_) | None)).is_ok());
+        assert!(export_fragment_internal(quote!(setup: stmts = let a = 1; let b = a;)).is_ok());
+        assert!(export_fragment_internal(quote!(bad_type: ty = 1 + 2)).is_err());
+        assert!(export_fragment_internal(quote!(bad_kind: item = struct A;))
+            .unwrap_err()
+            .to_string()
+            .contains("expected one of `expr`, `ty`, `pat` or `stmts`"));
 
-    #[test]
-    fn import_tokens_internal_invalid_path() {
-        assert!(import_tokens_internal(quote!(let my_tokens = 2 - 2)).is_err());
+        let info = ExportInfo {
+            fragment_kind: Some(FragmentKind::Stmts),
+            ..Default::default()
+        };
+        let item: Item = parse_quote!(setup! { let a = 1; let b = a + 1; });
+        let Fragment::Stmts(stmts) = info.fragment(item.clone()).unwrap() else {
+            panic!("expected statements");
+        };
+        assert_eq!(stmts.len(), 2);
+        assert!(ExportInfo::default().fragment(item).is_err());
     }
 
     #[test]
-    fn import_tokens_inner_internal_basic() {
-        assert!(import_tokens_inner_internal(quote! {
-            my_ident,
-            fn my_function() -> u32 {
-                33
-            }
+    fn import_fragment_kind_check() {
+        let tokens = import_fragment_internal(quote!(let timeout: expr = my_crate::timeout))
+            .unwrap()
+            .to_string();
+        assert_eq!(
+            tokens,
+            "my_crate :: __export_tokens_tt_timeout ! { timeout , :: macro_magic :: \
+            __private :: import_fragment_inner , { expr } }"
+        );
+        assert!(import_fragment_internal(quote!(let timeout: item = my_crate::timeout)).is_err());
+        let info = ExportInfo {
+            fragment_kind: Some(FragmentKind::Expr),
+            ..Default::default()
+        };
+        let tokens = import_fragment_inner_internal(quote! {
+            timeout, timeout! { Duration::from_secs(30) }, #info, { expr }
         })
         .unwrap()
+        .to_string();
+        assert!(tokens.starts_with("let timeout : :: macro_magic :: __private :: syn :: Expr ="));
+        assert!(tokens.contains("\"Duration :: from_secs (30)\""));
+        let err = import_fragment_inner_internal(quote! {
+            timeout, timeout! { Duration::from_secs(30) }, #info, { ty }
+        })
+        .unwrap_err()
+        .to_string();
+        assert_eq!(
+            err,
+            "expected a `ty` fragment, but the specified fragment was exported as `expr`"
+        );
+        let info = ExportInfo::default();
+        assert!(import_fragment_inner_internal(quote! {
+            tokens, struct MyStruct {}, #info, { expr }
+        })
+        .unwrap_err()
         .to_string()
-        .contains("my_ident"));
+        .contains("is not a fragment"));
     }
 
     #[test]
-    fn import_tokens_inner_internal_impl() {
-        assert!(import_tokens_inner_internal(quote! {
-            another_ident,
-            impl Something for MyThing {
-                fn something() -> CoolStuff {
-                    CoolStuff {}
+    fn export_tokens_alias_transform() {
+        let tokens = export_tokens_alias_internal(
+            quote!(
+                my_export,
+                transform = my_crate::normalize,
+                options(strip_docs, tags(api))
+            ),
+            true,
+        )
+        .unwrap()
+        .to_string();
+        assert!(tokens.contains("pub fn my_export"));
+        assert!(tokens.contains("quote :: quote ! (strip_docs , tags (api) ,)"));
+        assert!(tokens.contains("(args , tokens , true , my_crate :: normalize)"));
+        let tokens = export_tokens_alias_internal(quote!(my_export), false)
+            .unwrap()
+            .to_string();
+        assert!(tokens.contains("(args , tokens , false , Ok)"));
+        assert!(!tokens.contains("let attr"));
+        assert!(
+            export_tokens_alias_internal(quote!(my_export, options(private, local)), true)
+                .unwrap_err()
+                .to_string()
+                .contains("cannot be combined")
+        );
+        assert!(export_tokens_alias_internal(quote!(my_export, something_else), true).is_err());
+
+        let tokens = export_tokens_internal_with_transform(
+            quote!(),
+            quote! {
+                /// Some docs
+                struct TransformedStruct {}
+            },
+            true,
+            |mut item| {
+                if let Item::Struct(item_struct) = &mut item {
+                    item_struct.ident = format_ident!("Renamed");
                 }
-            }
-        })
+                Ok(item)
+            },
+        )
         .unwrap()
-        .to_string()
-        .contains("something ()"));
-    }
+        .to_string();
+        assert!(tokens.contains("struct Renamed { }"));
+        assert!(tokens.contains("struct TransformedStruct { }"));
+        let err = export_tokens_internal_with_transform(
+            quote!(),
+            quote!(
+                struct TransformedStruct {}
+            ),
+            true,
+            |item| Err(Error::new(item.span(), "not allowed")),
+        )
+        .unwrap_err();
+        assert_eq!(err.to_string(), "not allowed");
 
-    #[test]
-    fn import_tokens_inner_internal_missing_comma() {
-        assert!(import_tokens_inner_internal(quote! {
-            {
-                another_ident
-                impl Something for MyThing {
-                    fn something() -> CoolStuff {
-                        CoolStuff {}
+        let add_attrs = |mut item: Item| {
+            match &mut item {
+                Item::Impl(item_impl) => {
+                    for impl_item in item_impl.items.iter_mut() {
+                        if let syn::ImplItem::Fn(item_fn) = impl_item {
+                            item_fn.attrs.push(parse_quote!(#[inline]));
+                        }
                     }
                 }
+                Item::Struct(item_struct) => {
+                    item_struct.attrs.push(parse_quote!(#[non_exhaustive]))
+                }
+                _ => (),
             }
-        })
-        .is_err());
+            Ok(item)
+        };
+        let tokens = export_tokens_internal_with_transform(
+            quote!(),
+            quote! {
+                impl MyType {
+                    #[export_tokens]
+                    fn my_method() {}
+                }
+            },
+            true,
+            add_attrs,
+        )
+        .unwrap()
+        .to_string();
+        assert_eq!(tokens.matches("# [inline] fn my_method () { }").count(), 2);
+        assert!(tokens.ends_with("impl MyType { fn my_method () { } }"));
+        assert!(!tokens.contains("# [export_tokens]"));
+        let tokens = export_tokens_internal_with_transform(
+            quote!(recursive),
+            quote! {
+                mod my_mod {
+                    struct Child {}
+                }
+            },
+            true,
+            add_attrs,
+        )
+        .unwrap()
+        .to_string();
+        assert_eq!(
+            tokens
+                .matches("# [non_exhaustive] struct Child { }")
+                .count(),
+            1
+        );
     }
 
     #[test]
-    fn import_tokens_inner_internal_non_item() {
-        assert!(import_tokens_inner_internal(quote! {
-            {
-                another_ident,
-                2 + 2
-            }
+    fn test_tokens_hash() {
+        let hash = |tokens: &str| tokens_hash(&tokens.parse().unwrap());
+        let original = hash("struct MyStruct { field: u32 }");
+        assert_eq!(original.len(), 16);
+        assert_eq!(
+            original,
+            hash("struct   MyStruct {\n    field : u32 // comment\n}")
+        );
+        assert_ne!(original, hash("struct MyStruct { field: u64 }"));
+        assert_ne!(original, hash("struct MyStruct (field, u32)"));
+        assert_eq!(
+            hash("fn an_external_function(my_num: u32) -> u32 { my_num + 33 }"),
+            "5d0789bb097eb168"
+        );
+    }
+
+    #[test]
+    fn assert_tokens_hash_inner_internal_mismatch() {
+        let item = quote!(
+            struct MyStruct {}
+        );
+        let info = ExportInfo {
+            hash: tokens_hash(&item),
+            ..Default::default()
+        };
+        let expected = &info.hash;
+        assert!(assert_tokens_hash_inner_internal(quote! {
+            my_crate::MyStruct, #item, #info, { #expected }
         })
-        .is_err());
+        .unwrap()
+        .is_empty());
+        let err = assert_tokens_hash_inner_internal(quote! {
+            my_crate::MyStruct, #item, #info, { "0000000000000000" }
+        })
+        .unwrap_err()
+        .to_string();
+        assert!(err.contains("the tokens of `my_crate::MyStruct`"));
+        assert!(err.contains(expected));
+    }
+
+    #[test]
+    fn test_crate_relative_file() {
+        let root = std::path::Path::new(env!("CARGO_MANIFEST_DIR"));
+        let crate_dir = root.file_name().unwrap();
+        let relative = |file: &std::path::Path| crate_relative_file(file).unwrap();
+        assert_eq!(
+            relative(&root.join("src/foo.rs")),
+            relative("src/foo.rs".as_ref())
+        );
+        assert_eq!(
+            relative(&std::path::Path::new(crate_dir).join("src/foo.rs")),
+            std::path::Path::new("src/foo.rs")
+        );
+        assert_eq!(
+            relative("src/foo.rs".as_ref()),
+            std::path::Path::new("src/foo.rs")
+        );
+        assert!(crate_relative_file("/elsewhere/lib.rs".as_ref()).is_none());
+    }
+
+    #[test]
+    fn test_file_module_path() {
+        let root = std::path::Path::new(env!("CARGO_MANIFEST_DIR"));
+        let module_path = |file: &str| file_module_path(&root.join(file), "my_crate").join("::");
+        assert_eq!(module_path("src/lib.rs"), "");
+        assert_eq!(module_path("src/main.rs"), "");
+        assert_eq!(module_path("src/foo.rs"), "foo");
+        assert_eq!(module_path("src/foo/bar.rs"), "foo::bar");
+        assert_eq!(module_path("src/foo/mod.rs"), "foo");
+        assert_eq!(module_path("src/bin/my_crate.rs"), "");
+        assert_eq!(module_path("src/bin/my_crate/main.rs"), "");
+        assert_eq!(module_path("tests/my_crate.rs"), "");
+        assert_eq!(module_path("tests/common/mod.rs"), "common");
+        assert_eq!(module_path("examples/my_crate/helpers.rs"), "helpers");
+        assert_eq!(module_path("/elsewhere/lib.rs"), "");
+        assert_eq!(
+            file_module_path("src/foo/bar.rs".as_ref(), "my_crate").join("::"),
+            "foo::bar"
+        );
     }
 
     #[test]
@@ -10,13 +10,26 @@ use proc_macro::TokenStream;
 /// the regular name of the item, such as `#[export_tokens(MyCoolName)]` or
 /// `#[export_tokens(some_name)]`. Syntactically this name is parsed as a `syn::Ident` and is
 /// then normalized by converting to snake_case. Note that because of this, `MyCoolName` would
-/// collide with `my_cool_name`, resulting in a compiler error if these items are being
-/// exported from the same module.
-///
-/// Note that some types of items, namely `syn::ItemForeignMod`, `syn::ItemUse`,
-/// `syn::ItemImpl`, and `syn::Item::Verbatim`, do not have an inherent concept of a naming
-/// ident, and so for these items specifying an override name is required or you will get a
-/// compiler error. This also applies to `macro_rules!` definitions that do not specify a name.
+/// collide with `my_cool_name` if these items are being exported from the same module,
+/// resulting in a compiler error that
+/// `__export_tokens_tt_my_cool_name_is_exported_more_than_once_in_this_module` is defined
+/// multiple times, pointing at both names. An override name can be used to resolve the collision. Collisions between
+/// exports generated by the same attribute, such as the associated items of an `impl`, are
+/// reported with an error naming both items instead. Exports that only meet via glob imports,
+/// i.e. `use a::*;` and `use b::*;` where both `a` and `b` export a `MyCoolName`, are not
+/// detected up front, and instead result in an "ambiguous" error wherever the name is imported
+/// through the glob.
+///
+/// Note that some types of items, namely `syn::ItemForeignMod`, `syn::ItemUse` and
+/// `syn::ItemImpl`, do not have an inherent concept of a naming ident, and so a name is derived
+/// for them automatically: `impl Display for Foo` is exported as `display_for_foo`,
+/// `impl From<u8> for Foo` as `from_u8_for_foo`, `impl<T> Foo<T>` as `impl_foo`,
+/// `use std::fmt::Display;` as `use_std_fmt_display` and an `extern "C"` block as `extern_c_`
+/// followed by the name of its first item. See [`export_ident_for_item`] for the details.
+/// Impls that end up with the same name, such as `impl<T> Foo<T>` and `impl Foo<u8>`, need an
+/// override name to be exported from the same module. For items where no name can be derived,
+/// such as `syn::Item::Verbatim` and macro invocations, specifying an override name is
+/// required or you will get a compiler error.
 ///
 /// Note also that while you can presently _attach_ `#[export_tokens]` to anything attributes
 /// can be attached to, some of these items do not exist at the module path level, and
@@ -43,9 +56,9 @@ use proc_macro::TokenStream;
 /// }
 /// ```
 ///
-/// Applied to an `impl` requiring an override name:
+/// Applied to an `impl`, which is exported as `my_trait_for_my_item`:
 /// ```ignore
-/// #[export_tokens(impl_my_trait_for_my_item)]
+/// #[export_tokens]
 /// impl MyTrait for MyItem {
 ///     fn something() {
 ///         do_stuff();
@@ -53,6 +66,17 @@ use proc_macro::TokenStream;
 /// }
 /// ```
 ///
+/// Applied to an `impl` requiring an override name, since `impl_my_item` is already taken by
+/// an `impl<T> MyItem<T>` in the same module:
+/// ```ignore
+/// #[export_tokens(impl_my_item_u8)]
+/// impl MyItem<u8> {
+///     fn something() {
+///         do_stuff();
+///     }
+/// }
+/// ```
+///
 /// Applied to a struct, but specifying an override name:
 /// ```ignore
 /// #[export_tokens(SomeOtherName)]
@@ -61,8 +85,192 @@ use proc_macro::TokenStream;
 /// }
 /// ```
 ///
-/// Previously it was possible to access `#[export_tokens]` items defined in
-/// private/inaccessible contexts, however this was removed in 0.4.x.
+/// ## Multiple Names
+///
+/// Several override names can be specified at once, such as
+/// `#[export_tokens(Foo, legacy_foo, FooV1)]`, in which case the item can be imported under
+/// any of these names. This is handy for keeping old import paths working while items are
+/// renamed. All of the names refer to the same hidden export macro, so the tokens of the item
+/// are not duplicated. Names that normalize to the same snake_case ident are only exported
+/// once.
+///
+/// ## Recursive Module Exports
+///
+/// When `recursive` is specified on an inline module, i.e. `#[export_tokens(recursive)]`, every
+/// named child item of the module is exported individually as well, in addition to the module
+/// itself. Each child can then be imported by its path, i.e. `my_crate::my_mod::ChildStruct`,
+/// without having to annotate every item by hand. Nested inline modules are handled
+/// recursively. Items without an inherent name (such as `impl` blocks and `use` statements)
+/// are skipped, as are child items that are already marked with `#[export_tokens]`, which
+/// can be used to give a child an override name. The `private`, `local` and `version`
+/// options apply to the child exports too.
+/// ```ignore
+/// #[export_tokens(recursive)]
+/// pub mod schemas {
+///     pub struct User {
+///         pub id: u64,
+///     }
+///
+///     pub mod nested {
+///         pub struct Group;
+///     }
+/// }
+/// ```
+///
+/// ## Associated Items
+///
+/// Methods, associated consts and associated types inside an `impl` or `trait` can also be
+/// exported individually by marking them with `#[export_tokens]`, provided the enclosing
+/// `impl` or `trait` is itself marked with `#[export_tokens]`:
+/// ```ignore
+/// #[export_tokens(MyTypeImpl)]
+/// impl MyType {
+///     #[export_tokens]
+///     pub fn my_method(&self) -> u32 {
+///         3
+///     }
+/// }
+///
+/// #[export_tokens]
+/// pub trait MyTrait {
+///     #[export_tokens]
+///     const CONST: u32;
+/// }
+/// ```
+///
+/// These can then be imported using qualified path syntax, i.e. `<my_crate::MyType>::my_method`
+/// or `<my_crate::MyTrait>::CONST`. The associated items of trait impls are imported by also
+/// naming the trait, i.e. `<my_crate::MyType as Display>::fmt`, which keeps them apart from
+/// the associated items of other impls on the same type. Generic arguments have to be spelled
+/// the same way as in the `impl`, i.e. `<my_crate::MyType as From<u8>>::from`. Plain paths
+/// such as `my_crate::MyType::my_method` always refer to items within modules.
+///
+/// ## Private Exports
+///
+/// By default the hidden macro generated by `#[export_tokens]` is a `#[macro_export]` macro,
+/// which means `#[export_tokens]` can only be used in places where `#[macro_export]` is
+/// accessible, i.e. not inside function bodies or inaccessible private modules. Specifying the
+/// `private` option opts out of `#[macro_export]`:
+/// ```ignore
+/// fn my_function() {
+///     #[export_tokens(private)]
+///     struct LocalStruct {}
+///
+///     my_macro!(LocalStruct);
+/// }
+/// ```
+///
+/// A private export can be combined with an override name, such as
+/// `#[export_tokens(SomeName, private)]`. It is accessible by path from the same places the
+/// item itself is accessible from, except that it can never be accessed from outside the
+/// current crate, and, like any `macro_rules!` macro, a private export inside a function body
+/// is only accessible after its definition.
+///
+/// ## Crate-Local Exports
+///
+/// If the tokens of an item only need to be accessed from within the same crate, the `local`
+/// option can be used instead, such as `#[export_tokens(local)]` or
+/// `#[export_tokens(SomeName, local)]`. Like `private`, this opts out of `#[macro_export]`,
+/// however the hidden macro is always re-exported via `pub(crate) use`, making it accessible
+/// by path from anywhere in the current crate. This keeps the crate root free of hidden
+/// macros, and avoids collisions between hidden macros pulled in by glob imports from other
+/// crates. Local exports can be used with [`import_tokens!`](`macro@import_tokens`),
+/// [`forward_tokens!`](`macro@forward_tokens`) and
+/// [`#[import_tokens_attr]`](`macro@import_tokens_attr`) exactly like regular exports.
+///
+/// ## Versioned Exports
+///
+/// A schema version can be attached to an exported item via the `version` option, such as
+/// `#[export_tokens(version = 2)]` or `#[export_tokens(SomeName, version = 2)]`. The version is
+/// forwarded along with the tokens of the item, and importers can require a particular version
+/// (or range of versions) via `version = ..`, resulting in a clear compile error at the import
+/// site when the requirement isn't met. The idea is to bump the version whenever the shape of
+/// the item changes in a way that consumers need to know about.
+///
+/// ```ignore
+/// #[export_tokens(version = 2)]
+/// pub struct Schema {
+///     pub id: u64,
+///     pub name: String,
+/// }
+/// ```
+///
+/// ## cfg-Aware Exports
+///
+/// Exported tokens are forwarded as-is, so any `#[cfg(..)]` attributes on fields, variants and
+/// nested items are forwarded unevaluated. Specifying the `cfg` option, such as
+/// `#[export_tokens(cfg)]`, additionally records which of these predicates were active in the
+/// exporting crate. Importers can then pass the `__export_info` variable to
+/// [`strip_inactive_cfg`] to see the item exactly as the exporting crate compiled it.
+///
+/// ```ignore
+/// #[export_tokens(cfg)]
+/// pub struct Config {
+///     pub name: String,
+///     #[cfg(feature = "serde")]
+///     pub format: Format,
+/// }
+/// ```
+///
+/// ## Projections
+///
+/// By default the full item is exported, including any private fields and implementation
+/// details. The following options control which parts of the item are exported, without
+/// affecting the item itself:
+///
+/// * `strip_docs` removes all doc comments.
+/// * `signature_only` replaces the bodies of functions and methods with empty blocks and
+///   removes the default bodies of trait methods. This is recorded in the forwarded
+///   [`ExportInfo`], see [`ExportInfo::signature_only`].
+/// * `public_fields_only` removes all non-`pub` fields of structs and unions. It can't be
+///   used with tuple structs, since removing fields would change the indices of the remaining
+///   ones.
+/// * `strip_attrs(..)` removes the specified attributes, i.e. `strip_attrs(derive, serde)`.
+///
+/// These can be combined freely, such as `#[export_tokens(strip_docs, signature_only)]`. See
+/// [`ExportProjection`] for more information.
+///
+/// ```ignore
+/// #[export_tokens(signature_only, public_fields_only)]
+/// pub struct Account {
+///     pub id: u64,
+///     balance: u64,
+/// }
+/// ```
+///
+/// ## Tags and Metadata
+///
+/// Tags and key-value metadata can be attached to an exported item via the `tags(..)` and
+/// `meta(..)` options, such as `#[export_tokens(tags(db, api), meta(table = "users"))]`. These
+/// are forwarded along with the tokens of the item, and importers can read them from the
+/// `__export_info` variable via [`ExportInfo::has_tag`] and [`ExportInfo::meta`], instead of
+/// encoding them as helper attributes on the item and parsing them back out by hand.
+///
+/// ```ignore
+/// #[export_tokens(tags(db), meta(table = "users", shards = 4))]
+/// pub struct User {
+///     pub id: u64,
+/// }
+/// ```
+///
+/// ## Custom Prefixes
+///
+/// The hidden callback macros generated by this attribute are named using the
+/// [`MACRO_MAGIC_EXPORT_PREFIX`], which is `__export_tokens_tt_` unless the
+/// `MACRO_MAGIC_EXPORT_PREFIX` environment variable is set when `macro_magic` is compiled.
+/// Frameworks built on top of `macro_magic` can give their exports their own namespace via the
+/// `prefix` option, such as `#[export_tokens(prefix = "__my_framework_")]`, so that their
+/// exports can't collide with those of another framework in the same dependency graph. Items
+/// exported with a custom prefix can only be imported by importers that specify the same
+/// prefix, which is usually done via [`export_tokens_alias!`](`macro@export_tokens_alias`) and
+/// [`#[import_tokens_attr(prefix = "..")]`](`macro@import_tokens_attr`).
+///
+/// ```ignore
+/// #[export_tokens(prefix = "__my_framework_")]
+/// pub struct Component {
+///     pub id: u64,
+/// }
+/// ```
 #[proc_macro_attribute]
 pub fn export_tokens(attr: TokenStream, tokens: TokenStream) -> TokenStream {
     match export_tokens_internal(attr, tokens, true) {
@@ -71,6 +279,131 @@ pub fn export_tokens(attr: TokenStream, tokens: TokenStream) -> TokenStream {
     }
 }
 
+/// Creates a named export group which forwards the tokens of every listed item at once, such
+/// as `export_group!(api_types = [crate::User, crate::Account, crate::billing::Invoice])`.
+///
+/// The group can be imported by path from the module this macro is invoked in, just like an
+/// item marked with [`#[export_tokens]`](`macro@export_tokens`), i.e.
+/// `#[my_attribute(my_crate::api_types)]`. Importers receive an inline module named after the
+/// group that contains the items of the group in the order they were listed. Attribute
+/// macros created via [`#[import_tokens_attr]`](`macro@import_tokens_attr`) are given the
+/// items directly as `__group_items: Vec<syn::Item>`, while other importers can use
+/// [`ExportInfo::group_items`] with the `__export_info` variable to get them.
+///
+/// Each listed item must itself be marked with [`#[export_tokens]`](`macro@export_tokens`).
+/// Since the group can be imported from anywhere, the paths of the items must be absolute,
+/// i.e. start with `crate` for items of the current crate or be global paths such as
+/// `::other_crate::Item` for items of other crates. If the items
+/// were exported with a custom prefix, the same prefix must be specified after the list of
+/// items, i.e. `export_group!(api_types = [User, Account], prefix = "__my_framework_")`.
+///
+/// ## Example
+///
+/// ```ignore
+/// export_group!(api_types = [crate::User, crate::Account, crate::billing::Invoice]);
+///
+/// #[import_tokens_attr]
+/// #[proc_macro_attribute]
+/// pub fn my_attribute(_attr: TokenStream, tokens: TokenStream) -> TokenStream {
+///     for item in &__group_items {
+///         // ..
+///     }
+///     // ..
+/// }
+/// ```
+#[proc_macro]
+pub fn export_group(tokens: TokenStream) -> TokenStream {
+    match export_group_internal(tokens) {
+        Ok(tokens) => tokens.into(),
+        Err(err) => err.to_compile_error().into(),
+    }
+}
+
+/// Exports the attached item as a template whose placeholders are substituted at import time
+/// via [`instantiate_template!`](`macro@instantiate_template`). This makes it possible to
+/// share generic code across crates that would otherwise require a `macro_rules!` macro.
+///
+/// The placeholders are specified as idents, such as `#[export_template(T, Field)]`, and
+/// every occurrence of them within the item is substituted. Since the item usually refers to
+/// placeholders that don't exist, it is _not_ emitted, however it must still parse as an
+/// item.
+///
+/// Templates are exported under the name of the item or, for `impl` blocks, under the name of
+/// the implemented trait. A different name can be specified via `name = ..`, such as
+/// `#[export_template(T, name = StorageTemplate)]`, and a custom prefix via `prefix = ".."`
+/// (see [`#[export_tokens]`](`macro@export_tokens`)).
+///
+/// ## Example
+///
+/// ```ignore
+/// #[export_template(T, Field)]
+/// impl Storage for T {
+///     fn key(&self) -> u64 {
+///         self.Field
+///     }
+/// }
+/// ```
+#[proc_macro_attribute]
+pub fn export_template(attr: TokenStream, tokens: TokenStream) -> TokenStream {
+    match export_template_internal(attr, tokens) {
+        Ok(tokens) => tokens.into(),
+        Err(err) => err.to_compile_error().into(),
+    }
+}
+
+/// Instantiates a template exported via [`#[export_template]`](`macro@export_template`),
+/// expanding to the tokens of the template with each of its placeholders substituted by the
+/// specified value.
+///
+/// The first argument is the path of the template, followed by a value for each placeholder.
+/// Values can be any type or expression and are substituted verbatim, except that expressions
+/// which aren't also valid types, such as `3 + 4`, are wrapped in parentheses so that they keep
+/// their meaning when substituted into `N * 2`. A compile error is issued if a placeholder is
+/// missing or unknown. Templates exported with a custom prefix can be instantiated by
+/// specifying the same prefix, i.e. `prefix = "__my_framework_"`.
+///
+/// ## Example
+///
+/// ```ignore
+/// instantiate_template!(other_crate::Storage, T = MyType, Field = id);
+/// ```
+#[proc_macro]
+pub fn instantiate_template(tokens: TokenStream) -> TokenStream {
+    match instantiate_template_internal(tokens) {
+        Ok(tokens) => tokens.into(),
+        Err(err) => err.to_compile_error().into(),
+    }
+}
+
+/// Exports a named fragment of code that isn't an item, such as an expression, a type, a
+/// pattern or a sequence of statements, such that it can be imported by path just like an
+/// item marked with [`#[export_tokens]`](`macro@export_tokens`).
+///
+/// The name is followed by the kind of the fragment, which is one of `expr`, `ty`, `pat` or
+/// `stmts`, and the fragment itself, which must parse as the specified kind:
+///
+/// ```ignore
+/// export_fragment!(default_timeout: expr = Duration::from_secs(30));
+/// export_fragment!(id_type: ty = u64);
+/// export_fragment!(setup: stmts = let a = 1; let b = a + 1;);
+/// ```
+///
+/// A custom prefix for the hidden export macro (see [`#[export_tokens]`](`macro@export_tokens`))
+/// can be specified before the name, i.e.
+/// `export_fragment!(prefix = "__my_framework_", id_type: ty = u64)`, in which case importers
+/// need to specify the same prefix.
+///
+/// Fragments can be imported via [`import_fragment!`](`macro@import_fragment`), or by the
+/// other importers, in which case the fragment can be extracted from the forwarded item via
+/// [`ExportInfo::fragment`] using the `__export_info` variable.
+#[proc_macro]
+pub fn export_fragment(tokens: TokenStream) -> TokenStream {
+    match export_fragment_internal(tokens) {
+        Ok(tokens) => tokens.into(),
+        Err(err) => err.to_compile_error().into(),
+    }
+}
+
 /// Like [`#[export_tokens]`](`macro@export_tokens`) but does not emit the tokens of the
 /// attached item locally.
 ///
@@ -90,6 +423,31 @@ pub fn export_tokens_no_emit(attr: TokenStream, tokens: TokenStream) -> TokenStr
 /// Simply pass an ident to this proc macro, and an alias for
 /// [`#[export_tokens]`](`macro@export_tokens`) will be created with the specified name.
 ///
+/// The following options can optionally be specified after the ident, in any order:
+///
+/// * `transform = ..` specifies the path of a `fn(syn::Item) -> syn::Result<syn::Item>` that
+///   is run on the exported copy of every item exported via the alias, after any projections
+///   have been applied. This includes the child items of `recursive` exports, while associated
+///   items are exported from the transformed copy of their `impl` or `trait`. This lets
+///   frameworks enforce their own conventions, such as injecting attributes or normalizing
+///   paths. Errors returned by the transform are reported at the attribute site.
+/// * `options(..)` presets [`#[export_tokens]`](`macro@export_tokens`) options, such as
+///   `options(strip_docs, tags(api))`, which are applied in addition to those specified at the
+///   attribute site.
+/// * `prefix = ".."` specifies a custom prefix for the generated exports. See
+///   [`#[export_tokens]`](`macro@export_tokens`) for more information. Specifying another
+///   prefix at the attribute site of such an alias is an error.
+///
+/// ## Example
+///
+/// ```ignore
+/// fn normalize(item: syn::Item) -> syn::Result<syn::Item> {
+///     // ..
+/// }
+///
+/// export_tokens_alias!(my_export, transform = normalize, options(strip_docs));
+/// ```
+///
 /// Can only be used within a proc macro crate.
 #[proc_macro]
 pub fn export_tokens_alias(tokens: TokenStream) -> TokenStream {
@@ -99,8 +457,42 @@ pub fn export_tokens_alias(tokens: TokenStream) -> TokenStream {
     }
 }
 
+/// Exports the tokens of every named top-level item in the specified Rust source file, as if
+/// each of them had been marked with [`#[export_tokens]`](`macro@export_tokens`).
+///
+/// The path is relative to the `CARGO_MANIFEST_DIR` of the current crate. The exported items
+/// can be imported by path from the module this macro is invoked in, just like items that
+/// are marked with [`#[export_tokens]`](`macro@export_tokens`) in that module. This is
+/// useful for generated or vendored code that can't be annotated directly.
+///
+/// Note that the items in the file are _not_ emitted. Items without an inherent name, such as
+/// `impl` blocks and `use` statements, are skipped. The file is tracked such that the exports
+/// are regenerated whenever it changes.
+///
+/// ## Example
+///
+/// ```ignore
+/// mod user_schemas {
+///     macro_magic::export_tokens_file!("schemas/user.rs");
+/// }
+/// ```
+///
+/// If `schemas/user.rs` contains a `struct User`, its tokens can then be imported via the
+/// path `user_schemas::User`, i.e. `#[my_attribute(crate::user_schemas::User)]`.
+///
+/// A custom prefix for the generated exports can optionally be specified after the path, such
+/// as `export_tokens_file!("schemas/user.rs", prefix = "__my_framework_")`.
+#[proc_macro]
+pub fn export_tokens_file(tokens: TokenStream) -> TokenStream {
+    match export_tokens_file_internal(tokens) {
+        Ok(tokens) => tokens.into(),
+        Err(err) => err.to_compile_error().into(),
+    }
+}
+
 /// Like [`#[export_tokens]`](`macro@export_tokens`) but intead creates an alias for
-/// [`#[export_tokens_no_emit]`](`macro@export_tokens_no_emit`)
+/// [`#[export_tokens_no_emit]`](`macro@export_tokens_no_emit`). Accepts the same options as
+/// [`export_tokens_alias!`](`macro@export_tokens_alias`).
 ///
 /// Can only be used within a proc macro crate.
 #[proc_macro]
@@ -138,6 +530,31 @@ pub fn export_tokens_alias_no_emit(tokens: TokenStream) -> TokenStream {
 /// let result = forward_tokens!(LionStruct, receiver);
 /// assert_eq!(result, "struct LionStruct {}");
 /// ```
+///
+/// Items exported with a custom `prefix = ".."` can be forwarded by specifying the same prefix
+/// as a trailing argument, i.e. `forward_tokens!(LionStruct, receiver, prefix =
+/// "__my_framework_")`.
+///
+/// ## Forwarding Several Items
+///
+/// A bracketed list of paths can be specified instead of a single path, in which case the
+/// items are collected one after another and the target is then called once with all of
+/// them, in the order in which their paths were specified. This is useful for `macro_rules!`
+/// receivers that combine several items, such as generating one enum from several structs:
+///
+/// ```ignore
+/// macro_rules! variants {
+///     (__private_macro_magic_tokens_forwarded $(struct $name:ident { $($body:tt)* })*) => {
+///         enum Animal {
+///             $($name($name)),*
+///         }
+///     };
+/// }
+///
+/// forward_tokens!([LionStruct, zoo::TigerStruct], variants);
+/// ```
+///
+/// Extra data and `proc_macro` are not supported when forwarding several items.
 #[proc_macro]
 pub fn forward_tokens(tokens: TokenStream) -> TokenStream {
     match forward_tokens_internal(tokens) {
@@ -146,6 +563,38 @@ pub fn forward_tokens(tokens: TokenStream) -> TokenStream {
     }
 }
 
+/// Fails to compile if the tokens of the specified exported item (specified by path as the
+/// first arg) no longer match the expected [`tokens_hash`] (specified as a string literal as
+/// the second arg).
+///
+/// This is useful when code is generated based on the exact shape of an item in another
+/// crate, since any change to that item results in a loud compile error instead of generated
+/// code silently going stale. The hash is insensitive to whitespace and (non-doc) comments.
+///
+/// Note that the referenced item _must_ have the [`#[export_tokens]`][`macro@export_tokens`]
+/// attribute attached to it, or this will not work.
+///
+/// ## Example
+///
+/// ```ignore
+/// assert_tokens_hash!(other_crate::SomeStruct, "5d0b0a9c7c2f4e13");
+/// ```
+///
+/// The compile error emitted on a mismatch includes the current hash of the item, so an easy
+/// way to obtain the hash initially is to specify an empty string and copy the hash from the
+/// resulting error.
+///
+/// Items exported with a custom `prefix = ".."` can be checked by specifying the same prefix
+/// as a trailing argument, i.e. `assert_tokens_hash!(other_crate::SomeStruct, "..", prefix =
+/// "__my_framework_")`.
+#[proc_macro]
+pub fn assert_tokens_hash(tokens: TokenStream) -> TokenStream {
+    match assert_tokens_hash_internal(tokens) {
+        Ok(tokens) => tokens.into(),
+        Err(err) => err.to_compile_error().into(),
+    }
+}
+
 /// Allows you to import the tokens of an external item marked with
 /// [`#[export_tokens]`][`macro@export_tokens`] whose path is already known at compile-time
 /// without having to do any additional parsing.
@@ -176,6 +625,30 @@ pub fn forward_tokens(tokens: TokenStream) -> TokenStream {
 ///
 /// That said, this can be quite useful for scenarios where for whatever reason you have an
 /// item with a set-in-stone path whose tokens you need to access at compile time.
+///
+/// A version requirement can optionally be specified for items exported with
+/// `#[export_tokens(version = N)]`, in which case a compile error is issued if the version
+/// of the item does not satisfy it:
+///
+/// ```ignore
+/// import_tokens!(let tokens = external_crate::SomeItem, version = 2..);
+/// ```
+///
+/// Items exported with a custom `prefix = ".."` can be imported by specifying the same prefix,
+/// i.e. `import_tokens!(let tokens = external_crate::SomeItem, prefix = "__my_framework_")`.
+///
+/// A type can be specified for the variable, in which case the imported tokens are parsed as
+/// that type instead of being a `TokenStream2`:
+///
+/// ```ignore
+/// import_tokens!(let item: syn::ItemStruct = external_crate::SomeStruct);
+/// ```
+///
+/// For the `syn` item types (`syn::Item`, `syn::DeriveInput` and `syn::ItemStruct`,
+/// `syn::ItemEnum`, `syn::ItemFn` etc.) the kind of the imported item is checked when the
+/// import is expanded, resulting in a compile error naming the expected and actual kinds
+/// rather than a panic when your proc macro runs. Any other type implementing
+/// `syn::parse::Parse` can be specified as well, but is parsed without such a check.
 #[proc_macro]
 pub fn import_tokens(tokens: TokenStream) -> TokenStream {
     match import_tokens_internal(tokens) {
@@ -184,6 +657,28 @@ pub fn import_tokens(tokens: TokenStream) -> TokenStream {
     }
 }
 
+/// Like [`import_tokens!`](`macro@import_tokens`), but imports a fragment exported via
+/// [`export_fragment!`](`macro@export_fragment`) and parses it as the `syn` type that
+/// corresponds to the specified kind of fragment:
+///
+/// ```ignore
+/// import_fragment!(let timeout: expr = other_crate::default_timeout); // syn::Expr
+/// import_fragment!(let id_type: ty = other_crate::id_type); // syn::Type
+/// import_fragment!(let pattern: pat = other_crate::some_pattern); // syn::Pat
+/// import_fragment!(let setup: stmts = other_crate::setup); // Vec<syn::Stmt>
+/// ```
+///
+/// A compile error is issued if the fragment was exported as a different kind. Fragments
+/// exported with a custom prefix can be imported by specifying the same prefix, i.e.
+/// `import_fragment!(let timeout: expr = other_crate::default_timeout, prefix = "..")`.
+#[proc_macro]
+pub fn import_fragment(tokens: TokenStream) -> TokenStream {
+    match import_fragment_internal(tokens) {
+        Ok(tokens) => tokens.into(),
+        Err(err) => err.to_compile_error().into(),
+    }
+}
+
 /// An attribute macro that can be attached to a proc macro function definition that will cause
 /// it to receive the tokens of the external item referred to by its argument as input to your
 /// proc macro.
@@ -212,6 +707,19 @@ pub fn import_tokens(tokens: TokenStream) -> TokenStream {
 ///
 /// Note that this attribute can only be used within a proc macro crate.
 ///
+/// A handy `__source_info: SourceInfo` variable is also injected into your proc macro function
+/// definition which describes where the imported item was defined, including its crate name
+/// and version, module path, file, line and column. See
+/// [`SourceInfo`] for more information. A `__tokens_hash: String` variable containing the
+/// [`tokens_hash`] of the imported item is injected as well, along with an
+/// `__export_info: ExportInfo` variable containing all of the metadata forwarded with the
+/// item.
+///
+/// Like with [`#[import_tokens_attr]`](`macro@import_tokens_attr`), a `__source_path:
+/// TokenStream` variable is injected too, containing the path that was passed to your macro
+/// (i.e. `some_crate::some_item` above). This should parse to a `syn::Path`, and is handy for
+/// error messages and generated docs that refer to the imported item.
+///
 /// ## Overriding [`MACRO_MAGIC_ROOT`]:
 ///
 /// You can also provide a module path as an optional argument to this attribute macro and that
@@ -239,6 +747,27 @@ pub fn import_tokens(tokens: TokenStream) -> TokenStream {
 ///     // ..
 /// }
 /// ```
+///
+/// ## Version Requirements
+///
+/// If the items your macro consumes are exported with `#[export_tokens(version = N)]`, you
+/// can require a particular version (or range of versions) via `version = ..`, optionally
+/// after the [`MACRO_MAGIC_ROOT`] override. Imported items that don't satisfy the requirement
+/// result in a clear compile error rather than a confusing parse error in your macro:
+///
+/// ```ignore
+/// #[import_tokens_proc(version = 2..4)]
+/// pub fn my_macro(tokens: TokenStream) -> TokenStream {
+///     // ..
+/// }
+/// ```
+///
+/// ## Custom Prefixes
+///
+/// If the items your macro consumes are exported with a custom `prefix = ".."` (see
+/// [`#[export_tokens]`](`macro@export_tokens`)), the same prefix must be specified here as
+/// well, such as `#[import_tokens_proc(prefix = "__my_framework_")]`, optionally after the
+/// [`MACRO_MAGIC_ROOT`] override and the version requirement.
 #[proc_macro_attribute]
 pub fn import_tokens_proc(attr: TokenStream, tokens: TokenStream) -> TokenStream {
     match import_tokens_proc_internal(attr, tokens) {
@@ -314,12 +843,34 @@ pub fn import_tokens_proc(attr: TokenStream, tokens: TokenStream) -> TokenStream
 /// }
 /// ```
 ///
+/// ## Importing Multiple Items
+///
+/// The resulting attribute macro also accepts several comma-separated foreign paths, i.e.
+/// `#[my_attribute(a::Foo, b::Bar, c::Baz)]`. The items are imported one after another
+/// (forwarding is chained internally), and once all of them have been imported your proc
+/// macro is called a single time. In this case `attr` contains the tokens of all of the
+/// imported items one after another, and the magic variable `__imported_items:
+/// Vec<(syn::Item, ExportInfo)>` gives access to each item along with its [`ExportInfo`], in
+/// the order in which the paths were specified. The `__export_info`, `__source_info` and
+/// `__tokens_hash` variables only describe the first item, so use the [`ExportInfo`] in
+/// `__imported_items` to get the [`SourceInfo`] or the hash of any of the others:
+///
+/// ```ignore
+/// #[import_tokens_attr]
+/// #[proc_macro_attribute]
+/// pub fn merge_structs(_attr: TokenStream, tokens: TokenStream) -> TokenStream {
+///     for (item, export_info) in &__imported_items {
+///         // ..
+///     }
+///     // ..
+/// }
+/// ```
 ///
 /// ## Optional Feature: `#[with_custom_parsing(..)]`
 ///
 /// By default, [`#[import_tokens_attr]`](`macro@import_tokens_attr`)-based attribute macros
-/// expect the foreign item path to be passed directly as the only argument to the resulting
-/// macro. Sometimes, however, it is desirable to support multiple arguments, or otherwise
+/// expect one or more foreign item paths to be passed directly as the only arguments to the
+/// resulting macro. Sometimes, however, it is desirable to support multiple arguments, or otherwise
 /// implement some kind of custom parsing that determines how the foreign path is obtained. You
 /// can do this by attaching the optional attribute
 /// [`#[with_custom_parsing(..)]`](`macro@with_custom_parsing`) to the same proc macro
@@ -328,7 +879,8 @@ pub fn import_tokens_proc(attr: TokenStream, tokens: TokenStream) -> TokenStream
 /// This optional attribute takes one argument, which should be the path to a struct that
 /// implements `syn::parse::Parse`, `quote::ToTokens`, and [`ForeignPath`]. To access the
 /// tokens for your custom parsed input, you can use the magic variable `__custom_tokens:
-/// TokenStream` anywhere in your attribute proc macro.
+/// TokenStream` anywhere in your attribute proc macro. To import several foreign items at
+/// once, implement [`ForeignPaths`] instead of [`ForeignPath`].
 ///
 /// Here is a full example:
 ///
@@ -381,10 +933,36 @@ pub fn import_tokens_proc(attr: TokenStream, tokens: TokenStream) -> TokenStream
 /// * A handy `__source_path: TokenStream` variable is also injected into your proc macro
 ///   function definition which provides access to the original `syn::Path` that was provided
 ///   as the path for the foreign item before its tokens were imported. You can access this
-///   directly simply by referring to `__source_path`. This should parse to a `syn::Path`.
+///   directly simply by referring to `__source_path`. This should parse to a `syn::Path`,
+///   or to comma-separated paths when multiple items are imported.
+/// * Similarly, a `__source_info: SourceInfo` variable is injected which describes where the
+///   foreign item was defined, including its crate name and version, module path, file, line
+///   and column. This is useful for error messages and generated docs that link back to the
+///   original definition.
+/// * A `__tokens_hash: String` variable containing the [`tokens_hash`] of the foreign item is
+///   also injected, which can be used to detect when the foreign item has changed. See also
+///   [`macro@assert_tokens_hash`].
+/// * The full [`ExportInfo`] of the foreign item is available as `__export_info`, which
+///   can be passed to [`strip_inactive_cfg`] for items exported with
+///   `#[export_tokens(cfg)]`. When multiple items are imported, `__export_info`,
+///   `__source_info` and `__tokens_hash` only refer to the first one, while the
+///   [`ExportInfo`] of each item is available via `__imported_items` (see
+///   [Importing Multiple Items](#importing-multiple-items)).
+/// * The items of any export groups created via [`export_group!`](`macro@export_group`) that
+///   were imported are available as `__group_items: Vec<syn::Item>`, in the order they were
+///   listed. Items that aren't groups are included as-is, so this contains every imported
+///   item regardless of whether groups were imported or not.
 /// * When using the custom parsing feature, you can also access the original tokens for the
 ///   input attribute within your proc macro body using the magic variable `__custom_tokens`.
 ///   For more information and an example see [`macro@with_custom_parsing`].
+/// * Like [`#[import_tokens_proc]`](`macro@import_tokens_proc`), a version requirement can be
+///   specified via `version = ..`, i.e. `#[import_tokens_attr(version = 2)]` or
+///   `#[import_tokens_attr(my_crate::macro_magic, version = 1..=2)]`, in which case a compile
+///   error pointing at the foreign path is issued if the version of any foreign item does not
+///   satisfy the requirement.
+/// * Items exported with a custom `prefix = ".."` can only be imported if the same prefix is
+///   specified here, i.e. `#[import_tokens_attr(prefix = "__my_framework_")]`. See
+///   [`#[export_tokens]`](`macro@export_tokens`) for more information.
 #[proc_macro_attribute]
 pub fn import_tokens_attr(attr: TokenStream, tokens: TokenStream) -> TokenStream {
     match import_tokens_attr_internal(attr, tokens) {
@@ -415,6 +993,64 @@ pub fn with_custom_parsing(attr: TokenStream, tokens: TokenStream) -> TokenStrea
     }
 }
 
+/// An attribute macro that can be attached to a derive macro function definition that will
+/// cause it to receive the tokens of the external item referred to by a helper attribute on
+/// the item the derive is attached to, in addition to the item itself.
+///
+/// The argument is the name of the helper attribute, which must also be declared via
+/// `attributes(..)` in the `#[proc_macro_derive(..)]` attribute:
+///
+/// ```ignore
+/// #[import_tokens_derive(from)]
+/// #[proc_macro_derive(MyDerive, attributes(from))]
+/// pub fn my_derive(tokens: TokenStream) -> TokenStream {
+///     let derive_input = parse_macro_input!(tokens as DeriveInput);
+///     let foreign_item = parse_macro_input!(__foreign_tokens as Item);
+///     // ..
+/// }
+/// ```
+///
+/// Which you could use like this:
+///
+/// ```ignore
+/// #[derive(MyDerive)]
+/// #[from(other_crate::SomeType)]
+/// struct MyStruct;
+/// ```
+///
+/// In this case `tokens` contains the tokens of `MyStruct` as usual (including the
+/// `#[from(..)]` attribute), and the magic variable `__foreign_tokens: TokenStream` contains
+/// the tokens of `other_crate::SomeType`, as long as it has been marked with
+/// [`#[export_tokens]`][`macro@export_tokens`]. Like with
+/// [`#[import_tokens_attr]`](`macro@import_tokens_attr`), `__source_path`, `__source_info`,
+/// `__tokens_hash` and `__export_info` variables are injected as well.
+///
+/// The same optional arguments as [`#[import_tokens_proc]`](`macro@import_tokens_proc`) can
+/// be specified after the name of the helper attribute, i.e. a [`MACRO_MAGIC_ROOT`]
+/// override, `version = ..` and `prefix = ".."`:
+///
+/// ```ignore
+/// #[import_tokens_derive(from, my_crate::__private::macro_magic, version = 2)]
+/// ```
+///
+/// ## Notes
+///
+/// * Can only be used within a proc macro crate.
+/// * Under the hood the derive is invoked a second time on a hidden struct inside of a
+///   `const _: () = { .. };` block once the tokens of the foreign item have been obtained. Your
+///   derive macro should thus generate code (i.e. `impl` blocks) based on the `tokens` it
+///   receives rather than relying on being expanded next to the original item.
+/// * Like with [`#[import_tokens_attr]`](`macro@import_tokens_attr`), the name of the derive
+///   macro must be in scope where it is used, i.e. `#[derive(MyDerive)]` rather than
+///   `#[derive(my_crate::MyDerive)]`.
+#[proc_macro_attribute]
+pub fn import_tokens_derive(attr: TokenStream, tokens: TokenStream) -> TokenStream {
+    match import_tokens_derive_internal(attr, tokens) {
+        Ok(tokens) => tokens.into(),
+        Err(err) => err.to_compile_error().into(),
+    }
+}
+
 /// Deprecated: No-op
 #[deprecated(
     note = "`use_attr` is no longer needed for importing or re-exporting, implementation is no-op, it can be removed safely"
@@ -433,6 +1069,16 @@ pub fn use_proc(_attr: TokenStream, tokens: TokenStream) -> TokenStream {
     tokens
 }
 
+/// A helper macro used by [`macro@import_fragment`]. Hidden from docs.
+#[doc(hidden)]
+#[proc_macro]
+pub fn import_fragment_inner(tokens: TokenStream) -> TokenStream {
+    match import_fragment_inner_internal(tokens) {
+        Ok(tokens) => tokens.into(),
+        Err(err) => err.to_compile_error().into(),
+    }
+}
+
 /// A helper macro used by [`macro@import_tokens`]. Hidden from docs.
 #[doc(hidden)]
 #[proc_macro]
@@ -452,3 +1098,43 @@ pub fn forward_tokens_inner(tokens: TokenStream) -> TokenStream {
         Err(err) => err.to_compile_error().into(),
     }
 }
+
+/// A helper macro used by [`macro@forward_tokens`]. Hidden from docs.
+#[doc(hidden)]
+#[proc_macro]
+pub fn forward_tokens_batch(tokens: TokenStream) -> TokenStream {
+    match forward_tokens_batch_internal(tokens) {
+        Ok(tokens) => tokens.into(),
+        Err(err) => err.to_compile_error().into(),
+    }
+}
+
+/// A helper macro used by [`macro@assert_tokens_hash`]. Hidden from docs.
+#[doc(hidden)]
+#[proc_macro]
+pub fn assert_tokens_hash_inner(tokens: TokenStream) -> TokenStream {
+    match assert_tokens_hash_inner_internal(tokens) {
+        Ok(tokens) => tokens.into(),
+        Err(err) => err.to_compile_error().into(),
+    }
+}
+
+/// A helper macro used by [`macro@instantiate_template`]. Hidden from docs.
+#[doc(hidden)]
+#[proc_macro]
+pub fn instantiate_template_inner(tokens: TokenStream) -> TokenStream {
+    match instantiate_template_inner_internal(tokens) {
+        Ok(tokens) => tokens.into(),
+        Err(err) => err.to_compile_error().into(),
+    }
+}
+
+/// A helper macro used by [`macro@export_group`]. Hidden from docs.
+#[doc(hidden)]
+#[proc_macro]
+pub fn export_group_inner(tokens: TokenStream) -> TokenStream {
+    match export_group_inner_internal(tokens) {
+        Ok(tokens) => tokens.into(),
+        Err(err) => err.to_compile_error().into(),
+    }
+}
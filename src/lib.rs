@@ -30,7 +30,8 @@
 //!
 //! The `proc_support` feature _must_ be enabled in proc macro crates that make use of any
 //! import tokens functionality, including [`#[import_tokens_attr]`](`import_tokens_attr`),
-//! [`#[import_tokens_proc]`](`import_tokens_proc`) and [`import_tokens!`]. Otherwise these
+//! [`#[import_tokens_proc]`](`import_tokens_proc`),
+//! [`#[import_tokens_derive]`](`import_tokens_derive`) and [`import_tokens!`]. Otherwise these
 //! macros will not function correctly and will issue compiler errors complaining about items
 //! not existing under [`mm_core`]. The [`#[export_tokens]`](`export_tokens`) macro does not
 //! require this feature to function correctly, so you can safely use it without enabling this
@@ -44,18 +45,17 @@
 //! One thing that `macro_magic` _doesn't_ provide is the ability to build up state information
 //! across multiple macro invocations, however this problem can be tackled effectively using
 //! the [outer macro pattern](https://www.youtube.com/watch?v=aEWbZxNCH0A) or in some cases
-//! using static atomics and mutexes in your proc macro crate (which we actually do in this
-//! crate to keep track of unique identifiers).
+//! using static atomics and mutexes in your proc macro crate.
 //!
 //! ## Breaking Changes
 //!
 //! - **0.4x** removed `#[use_attr]` and `#[use_proc]` (they are no longer needed with the new
 //!   self-calling macro style that has been adopted in 0.4x) and also removed the ability to
 //!   access `#[export_tokens]` invocations in inaccessible locations like inside of functions
-//!   and across module permission boundaries like in an inaccessible private module. This
-//!   feature may be re-added in the future if there is interest, however removing it allowed
-//!   us to consolidate naming of our `macro_rules!` declarations and remove the need for
-//!  `#[use_attr]` / `#[use_proc]`.
+//!   and across module permission boundaries like in an inaccessible private module. Removing
+//!   this allowed us to consolidate naming of our `macro_rules!` declarations and remove the
+//!   need for `#[use_attr]` / `#[use_proc]`. Exporting from function bodies and private
+//!   modules has since been re-added as an opt-in via `#[export_tokens(private)]`.
 //! - **0.2x** removed and/or re-wrote a number of features that relied on a non-future-proof
 //!   behavior of writing/reading files in the `OUT_DIR`. Versions >= 0.2.0 are completely safe
 //!   and no longer contain this behavior, however features that provided the ability to
@@ -76,12 +76,15 @@ pub mod mm_core {
 }
 
 pub use macro_magic_macros::{
-    export_tokens, export_tokens_alias, export_tokens_no_emit, forward_tokens, use_attr, use_proc,
+    assert_tokens_hash, export_fragment, export_group, export_template, export_tokens,
+    export_tokens_alias, export_tokens_file, export_tokens_no_emit, forward_tokens,
+    instantiate_template, use_attr, use_proc,
 };
 
 #[cfg(feature = "proc_support")]
 pub use macro_magic_macros::{
-    import_tokens, import_tokens_attr, import_tokens_proc, with_custom_parsing,
+    import_fragment, import_tokens, import_tokens_attr, import_tokens_derive, import_tokens_proc,
+    with_custom_parsing,
 };
 
 /// Contains re-exports required at compile-time by the macro_magic macros and support
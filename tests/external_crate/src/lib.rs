@@ -2,17 +2,25 @@
 
 use macro_magic::*;
 
+/// The `file!()` of this crate, used to check the [`SourceInfo`] forwarded with its exports.
+///
+/// [`SourceInfo`]: macro_magic::mm_core::SourceInfo
+pub const FILE: &str = file!();
+
 pub mod some_submodule {
     use macro_magic::*;
 
     struct FooBarStruct {}
 
+    #[allow(dead_code)]
     trait FooBarTrait {
         fn foo(n: u32) -> u32;
         fn bar(n: i32) -> i32;
         fn fizz(v: bool) -> bool;
     }
 
+    /// The `line!()` the exported impl below starts on.
+    pub const AN_EXTERNAL_TRAIT_IMPL_LINE: u32 = line!() + 2;
     #[export_tokens(AnExternalTraitImpl)]
     impl FooBarTrait for FooBarStruct {
         fn foo(n: u32) -> u32 {
@@ -29,6 +37,61 @@ pub mod some_submodule {
     }
 }
 
+pub struct ExternalType;
+
+#[export_tokens(ExternalTypeImpl)]
+impl ExternalType {
+    #[export_tokens]
+    pub fn associated_fn(n: u32) -> u32 {
+        n * 2
+    }
+
+    pub fn not_exported() {}
+}
+
+#[export_tokens]
+impl core::fmt::Display for ExternalType {
+    #[export_tokens]
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        f.write_str("display")
+    }
+}
+
+#[export_tokens]
+impl core::fmt::Debug for ExternalType {
+    #[export_tokens]
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        f.write_str("debug")
+    }
+}
+
+#[export_tokens]
+impl From<u8> for ExternalType {
+    #[export_tokens]
+    fn from(_: u8) -> Self {
+        ExternalType
+    }
+}
+
+#[export_tokens]
+impl From<u16> for ExternalType {
+    #[export_tokens]
+    fn from(_: u16) -> Self {
+        ExternalType
+    }
+}
+
+#[export_tokens]
+pub trait ExternalTrait {
+    #[export_tokens]
+    const CONST: u32;
+
+    #[export_tokens(Describe)]
+    fn describe(&self) -> u32;
+}
+
+/// The `line!()` the exported function below starts on.
+pub const AN_EXTERNAL_FUNCTION_LINE: u32 = line!() + 2;
 #[export_tokens]
 fn an_external_function(my_num: u32) -> u32 {
     my_num + 33
@@ -52,3 +115,105 @@ fn external_fn_with_local_macro_calls() -> u32 {
     another_macro!();
     1337
 }
+
+#[export_tokens(version = 2)]
+pub struct VersionedStruct {
+    pub field: u32,
+}
+
+#[export_tokens(RenamedStruct, LegacyStruct, legacy_struct_v1)]
+pub struct RenamedStruct {
+    pub renamed_field: u8,
+}
+
+#[export_tokens(recursive)]
+pub mod schemas {
+    pub struct ChildStruct {
+        pub id: u64,
+    }
+
+    impl ChildStruct {
+        pub fn id(&self) -> u64 {
+            self.id
+        }
+    }
+
+    pub enum ChildEnum {
+        First,
+        Second,
+    }
+
+    pub mod nested {
+        pub struct NestedStruct;
+    }
+}
+
+#[export_tokens(cfg)]
+pub struct CfgStruct {
+    pub always: u32,
+    #[cfg(test)]
+    pub only_in_tests: u32,
+    #[cfg(not(test))]
+    pub not_in_tests: u32,
+}
+
+/// An account
+#[export_tokens(strip_docs, public_fields_only, strip_attrs(derive))]
+#[derive(Clone)]
+pub struct ProjectedAccount {
+    /// The id of the account
+    pub id: u64,
+    balance: u64,
+}
+
+impl ProjectedAccount {
+    pub fn balance(&self) -> u64 {
+        self.balance
+    }
+}
+
+#[export_tokens(signature_only)]
+pub fn projected_function(n: u32) -> u32 {
+    n * 3
+}
+
+#[export_tokens(tags(db, api), meta(table = "users", shards = 4))]
+pub struct TaggedUser {
+    pub id: u64,
+}
+
+export_group!(
+    api_types = [
+        crate::TaggedUser,
+        crate::VersionedStruct,
+        crate::schemas::ChildStruct
+    ]
+);
+
+#[export_tokens(prefix = "__fw_export_")]
+pub struct PrefixedStruct {
+    pub field: bool,
+}
+
+pub trait Storage {
+    fn key(&self) -> u64;
+}
+
+#[export_template(T, Field)]
+impl Storage for T {
+    fn key(&self) -> u64 {
+        self.Field as u64
+    }
+}
+
+export_fragment!(default_timeout: expr = core::time::Duration::from_secs(30));
+export_fragment!(id_type: ty = u64);
+export_fragment!(setup_stmts: stmts = let a = 1; let b = a + 1;);
+export_fragment!(prefix = "__fw_export_", prefixed_id_type: ty = u32);
+
+#[export_tokens]
+impl core::fmt::Display for TaggedUser {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "user {}", self.id)
+    }
+}
@@ -1,5 +1,7 @@
 use macro_magic::*;
 
+/// The `line!()` the exported function below starts on.
+pub const EXTERNAL_FN_WITH_PRINTLN_LINE: u32 = line!() + 2;
 #[export_tokens]
 fn external_fn_with_println() {
     println!("testing");
@@ -0,0 +1,24 @@
+// This file is intentionally not compiled as part of any crate, it is only read by
+// `export_tokens_file!` in `tests.rs`.
+
+use std::collections::HashMap;
+
+pub struct User {
+    pub id: u64,
+    pub name: String,
+    pub roles: Vec<Role>,
+}
+
+impl User {
+    pub fn is_admin(&self) -> bool {
+        self.roles.contains(&Role::Admin)
+    }
+}
+
+#[derive(PartialEq)]
+pub enum Role {
+    Admin,
+    Member,
+}
+
+pub type UserIndex = HashMap<u64, User>;
@@ -1,5 +1,8 @@
 use derive_syn_parse::Parse;
-use macro_magic::{mm_core::ForeignPath, *};
+use macro_magic::{
+    mm_core::{ExportInfo, ForeignPath, ForeignPaths},
+    *,
+};
 use proc_macro::TokenStream;
 use quote::{quote, ToTokens};
 use syn::{
@@ -105,7 +108,7 @@ pub fn test_tokens_attr1(attr: TokenStream, tokens: TokenStream) -> TokenStream
     assert_eq!(imported_item_str, "struct AnotherStruct { field1 : u32, }");
     assert_eq!(
         attached_item_str,
-        "pub mod hunter { pub fn stuff() { println! (\"things\") ; } }"
+        "pub mod hunter { pub fn stuff() { println! (\"things\"); } }"
     );
     quote! {
         #attached_item
@@ -226,6 +229,29 @@ pub fn test_tokens_attr_direct_import(attr: TokenStream, tokens: TokenStream) ->
     .into()
 }
 
+#[import_tokens_derive(from)]
+#[proc_macro_derive(ForeignFields, attributes(from))]
+pub fn foreign_fields(tokens: TokenStream) -> TokenStream {
+    let derive_input = parse_macro_input!(tokens as syn::DeriveInput);
+    let foreign_struct = parse_macro_input!(__foreign_tokens as ItemStruct);
+    let ident = derive_input.ident;
+    let field_names = foreign_struct
+        .fields
+        .iter()
+        .filter_map(|field| field.ident.as_ref())
+        .map(|ident| ident.to_string());
+    let source_path = __source_path.to_string();
+    let version = __export_info.version.unwrap_or_default();
+    quote! {
+        impl #ident {
+            pub const FOREIGN_FIELDS: &'static [&'static str] = &[#(#field_names),*];
+            pub const FOREIGN_SOURCE: &'static str = #source_path;
+            pub const FOREIGN_VERSION: u64 = #version;
+        }
+    }
+    .into()
+}
+
 #[import_tokens_proc]
 #[proc_macro]
 pub fn example_tokens_proc(tokens: TokenStream) -> TokenStream {
@@ -244,6 +270,19 @@ pub fn item_level_proc(tokens: TokenStream) -> TokenStream {
     .into()
 }
 
+#[import_tokens_proc]
+#[proc_macro]
+pub fn emit_foreign_path_proc(tokens: TokenStream) -> TokenStream {
+    let path = __source_path.to_string();
+    let item = parse_macro_input!(tokens as Item);
+    let doc = format!(
+        "Generated from `{}` defined in `{}`",
+        path, __source_info.module_path
+    );
+    let item_str = item.to_token_stream().to_string();
+    quote!((#path, #doc, #item_str)).into()
+}
+
 #[import_tokens_attr]
 #[proc_macro_attribute]
 pub fn emit_foreign_path(attr: TokenStream, tokens: TokenStream) -> TokenStream {
@@ -266,14 +305,18 @@ pub fn combine_structs(attr: TokenStream, tokens: TokenStream) -> TokenStream {
     let Fields::Named(local_fields) = local_struct.fields else {
         return Error::new(
             local_struct.fields.span(),
-            "unnamed fields are not supported"
-        ).to_compile_error().into()
+            "unnamed fields are not supported",
+        )
+        .to_compile_error()
+        .into();
     };
     let Fields::Named(foreign_fields) = foreign_struct.fields else {
         return Error::new(
             foreign_struct.fields.span(),
-            "unnamed fields are not supported"
-        ).to_compile_error().into()
+            "unnamed fields are not supported",
+        )
+        .to_compile_error()
+        .into();
     };
     let local_fields = local_fields.named.iter();
     let foreign_fields = foreign_fields.named.iter();
@@ -295,6 +338,105 @@ pub fn combine_structs(attr: TokenStream, tokens: TokenStream) -> TokenStream {
     .into()
 }
 
+fn merged_fields(items: &[(Item, ExportInfo)]) -> Result<Vec<syn::Field>, Error> {
+    let mut fields = Vec::new();
+    for (item, _) in items {
+        let Item::Struct(item_struct) = item else {
+            return Err(Error::new(item.span(), "expected a struct"));
+        };
+        let Fields::Named(named) = &item_struct.fields else {
+            return Err(Error::new(
+                item_struct.fields.span(),
+                "unnamed fields are not supported",
+            ));
+        };
+        fields.extend(named.named.iter().cloned());
+    }
+    Ok(fields)
+}
+
+#[import_tokens_attr]
+#[proc_macro_attribute]
+pub fn merge_structs(_attr: TokenStream, tokens: TokenStream) -> TokenStream {
+    let local_struct = parse_macro_input!(tokens as ItemStruct);
+    let foreign_fields = match merged_fields(&__imported_items) {
+        Ok(fields) => fields,
+        Err(err) => return err.to_compile_error().into(),
+    };
+    let Fields::Named(local_fields) = local_struct.fields else {
+        return Error::new(
+            local_struct.fields.span(),
+            "unnamed fields are not supported",
+        )
+        .to_compile_error()
+        .into();
+    };
+    let local_fields = local_fields.named.iter();
+    let attrs = local_struct.attrs;
+    let ident = local_struct.ident;
+    let vis = local_struct.vis;
+    let source_path = __source_path.to_string();
+    quote! {
+        #(#attrs)
+        *
+        #vis struct #ident {
+            #(#local_fields,)
+            *
+            #(#foreign_fields),
+            *
+        }
+
+        impl #ident {
+            pub const MERGED_FROM: &'static str = #source_path;
+        }
+    }
+    .into()
+}
+
+#[derive(Parse)]
+struct CustomParsingMulti {
+    name: syn::LitStr,
+    _arrow: syn::Token![=>],
+    #[call(syn::punctuated::Punctuated::parse_separated_nonempty)]
+    foreign_paths: syn::punctuated::Punctuated<Path, syn::Token![,]>,
+}
+
+impl ToTokens for CustomParsingMulti {
+    fn to_tokens(&self, tokens: &mut proc_macro2::TokenStream) {
+        self.name.to_tokens(tokens);
+        self._arrow.to_tokens(tokens);
+        self.foreign_paths.to_tokens(tokens);
+    }
+}
+
+impl ForeignPaths for CustomParsingMulti {
+    fn foreign_paths(&self) -> Vec<syn::Path> {
+        self.foreign_paths.iter().cloned().collect()
+    }
+}
+
+#[with_custom_parsing(CustomParsingMulti)]
+#[import_tokens_attr]
+#[proc_macro_attribute]
+pub fn named_field_list(_attr: TokenStream, tokens: TokenStream) -> TokenStream {
+    let custom = parse_macro_input!(__custom_tokens as CustomParsingMulti);
+    let attached_item = parse_macro_input!(tokens as Item);
+    let foreign_fields = match merged_fields(&__imported_items) {
+        Ok(fields) => fields,
+        Err(err) => return err.to_compile_error().into(),
+    };
+    let name = Ident::new(&custom.name.value(), custom.name.span());
+    let field_names = foreign_fields
+        .iter()
+        .filter_map(|field| field.ident.as_ref())
+        .map(|ident| ident.to_string());
+    quote! {
+        const #name: &[&str] = &[#(#field_names),*];
+        #attached_item
+    }
+    .into()
+}
+
 #[import_tokens_proc]
 #[proc_macro]
 pub fn require(tokens: TokenStream) -> TokenStream {
@@ -303,8 +445,10 @@ pub fn require(tokens: TokenStream) -> TokenStream {
         return Error::new(
             external_mod.span(),
             "cannot import tokens from a file-based module since custom file-level \
-            attributes are not yet supported by Rust"
-        ).to_compile_error().into()
+            attributes are not yet supported by Rust",
+        )
+        .to_compile_error()
+        .into();
     };
     quote! {
         #(#stmts)
@@ -314,3 +458,149 @@ pub fn require(tokens: TokenStream) -> TokenStream {
 }
 
 export_tokens_alias!(custom_export_tokens);
+
+export_tokens_alias!(fw_export_tokens, prefix = "__fw_export_");
+
+fn normalize_export(mut item: Item) -> syn::Result<Item> {
+    let Item::Struct(item_struct) = &mut item else {
+        return Err(Error::new(item.span(), "only structs can be normalized"));
+    };
+    item_struct
+        .attrs
+        .push(syn::parse_quote!(#[allow(dead_code)]));
+    Ok(item)
+}
+
+export_tokens_alias!(
+    normalized_export_tokens,
+    transform = normalize_export,
+    options(strip_docs)
+);
+
+#[import_tokens_proc]
+#[proc_macro]
+pub fn source_info_proc(_tokens: TokenStream) -> TokenStream {
+    let crate_name = __source_info.crate_name;
+    let crate_version = __source_info.crate_version;
+    let module_path = __source_info.module_path;
+    let file = __source_info.file;
+    let line = __source_info.line as u32;
+    let column = __source_info.column as u32;
+    quote!((#crate_name, #crate_version, #module_path, #file, #line, #column)).into()
+}
+
+#[import_tokens_attr]
+#[proc_macro_attribute]
+pub fn emit_source_info(_attr: TokenStream, tokens: TokenStream) -> TokenStream {
+    let module_path = __source_info.module_path;
+    let file = __source_info.file;
+    let line = __source_info.line as u32;
+    let tokens_hash = __tokens_hash;
+    let imported_files = __imported_items.iter().map(|(_, info)| &info.source.file);
+    let item = parse_macro_input!(tokens as Item);
+    quote! {
+        const source_module_path: &'static str = #module_path;
+        const source_file: &'static str = #file;
+        const source_line: u32 = #line;
+        const source_tokens_hash: &'static str = #tokens_hash;
+        const imported_source_files: &[&'static str] = &[#(#imported_files),*];
+        #item
+    }
+    .into()
+}
+
+#[import_tokens_proc(version = 2..)]
+#[proc_macro]
+pub fn versioned_proc(tokens: TokenStream) -> TokenStream {
+    let item_as_string = tokens.to_string();
+    quote!(#item_as_string).into()
+}
+
+#[import_tokens_proc(prefix = "__fw_export_")]
+#[proc_macro]
+pub fn prefixed_proc(tokens: TokenStream) -> TokenStream {
+    let item_as_string = tokens.to_string();
+    quote!(#item_as_string).into()
+}
+
+#[import_tokens_proc]
+#[proc_macro]
+pub fn emit_fragment(tokens: TokenStream) -> TokenStream {
+    let item = parse_macro_input!(tokens as Item);
+    match __export_info.fragment(item) {
+        Ok(fragment) => fragment.to_token_stream().into(),
+        Err(err) => err.to_compile_error().into(),
+    }
+}
+
+#[import_tokens_attr(version = 1..=2)]
+#[proc_macro_attribute]
+pub fn versioned_attr(attr: TokenStream, tokens: TokenStream) -> TokenStream {
+    let foreign_item_str = attr.to_string();
+    let item = parse_macro_input!(tokens as Item);
+    quote! {
+        const versioned_item_str: &'static str = #foreign_item_str;
+        #item
+    }
+    .into()
+}
+
+#[import_tokens_attr]
+#[proc_macro_attribute]
+pub fn strip_cfg_attr(attr: TokenStream, tokens: TokenStream) -> TokenStream {
+    let mut foreign_item = parse_macro_input!(attr as Item);
+    mm_core::strip_inactive_cfg(&mut foreign_item, &__export_info);
+    let foreign_item_str = foreign_item.to_token_stream().to_string();
+    let item = parse_macro_input!(tokens as Item);
+    quote! {
+        const stripped_item_str: &'static str = #foreign_item_str;
+        #item
+    }
+    .into()
+}
+
+#[import_tokens_proc]
+#[proc_macro]
+pub fn export_tags_proc(_tokens: TokenStream) -> TokenStream {
+    let tags = __export_info.tags.join(",");
+    let table = __export_info.meta("table").unwrap_or_default();
+    let shards = __export_info.meta("shards").unwrap_or_default();
+    quote!((#tags, #table, #shards)).into()
+}
+
+#[import_tokens_proc]
+#[proc_macro]
+pub fn group_item_names(tokens: TokenStream) -> TokenStream {
+    let group = parse_macro_input!(tokens as Item);
+    let names = __export_info
+        .group_items(group)
+        .iter()
+        .map(|item| match item {
+            Item::Struct(item_struct) => item_struct.ident.to_string(),
+            Item::Enum(item_enum) => item_enum.ident.to_string(),
+            item => item.to_token_stream().to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join(",");
+    quote!(#names).into()
+}
+
+#[import_tokens_attr]
+#[proc_macro_attribute]
+pub fn group_names_attr(_attr: TokenStream, tokens: TokenStream) -> TokenStream {
+    let names = __group_items
+        .iter()
+        .map(|item| match item {
+            Item::Struct(item_struct) => item_struct.ident.to_string(),
+            Item::Enum(item_enum) => item_enum.ident.to_string(),
+            item => item.to_token_stream().to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join(",");
+    let item = parse_macro_input!(tokens as Item);
+    quote! {
+        const group_names: &'static str = #names;
+        #item
+    }
+    .into()
+}
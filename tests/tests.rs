@@ -11,13 +11,23 @@ use test_macros::some_macro;
 
 use test_macros::combine_structs;
 use test_macros::emit_foreign_path;
+use test_macros::emit_foreign_path_proc;
 use test_macros::example_tokens_proc;
 use test_macros::import_tokens_attr_with_custom_parsing_a;
 use test_macros::import_tokens_attr_with_custom_parsing_b;
 use test_macros::item_level_proc;
+use test_macros::merge_structs;
+use test_macros::named_field_list;
 use test_macros::require;
 use test_macros::test_tokens_attr1;
 use test_macros::test_tokens_attr2;
+use test_macros::ForeignFields;
+use test_macros::{
+    emit_fragment, emit_source_info, export_tags_proc, group_item_names, group_names_attr,
+    source_info_proc, strip_cfg_attr,
+};
+use test_macros::{fw_export_tokens, normalized_export_tokens, prefixed_proc};
+use test_macros::{versioned_attr, versioned_proc};
 
 /// Some doc comment
 pub use test_macros::test_tokens_attr_direct_import;
@@ -121,6 +131,26 @@ fn test_import_tokens_proc_expr_position() {
     let _something_else = example_tokens_proc!(external_crate::some_submodule::AnExternalTraitImpl);
 }
 
+#[test]
+fn test_import_associated_items() {
+    let tokens = example_tokens_proc!(<external_crate::ExternalType>::associated_fn);
+    assert_eq!(tokens, "pub fn associated_fn(n : u32) -> u32 { n * 2 }");
+    let tokens = example_tokens_proc!(<external_crate::ExternalTrait>::CONST);
+    assert_eq!(tokens, "const CONST : u32;");
+    let tokens = example_tokens_proc!(<external_crate::ExternalTrait>::Describe);
+    assert_eq!(tokens, "fn describe(& self) -> u32;");
+    let tokens = example_tokens_proc!(<external_crate::ExternalType as core::fmt::Display>::fmt);
+    assert!(tokens.ends_with("{ f.write_str(\"display\") }"));
+    let tokens = example_tokens_proc!(<external_crate::ExternalType as core::fmt::Debug>::fmt);
+    assert!(tokens.ends_with("{ f.write_str(\"debug\") }"));
+    let tokens = example_tokens_proc!(<external_crate::ExternalType as From<u8>>::from);
+    assert_eq!(tokens, "fn from(_ : u8) -> Self { ExternalType }");
+    let tokens = example_tokens_proc!(<external_crate::ExternalType as From<u16>>::from);
+    assert_eq!(tokens, "fn from(_ : u16) -> Self { ExternalType }");
+    let tokens = example_tokens_proc!(external_crate::from_u16_for_external_type);
+    assert!(tokens.starts_with("impl From < u16 > for ExternalType"));
+}
+
 #[test]
 fn attr_direct_import() {
     assert_eq!(an_external_function(4), 37);
@@ -128,7 +158,6 @@ fn attr_direct_import() {
 
 #[test]
 fn test_forward_tokens() {
-    #[macro_export]
     macro_rules! receiver {
         (__private_macro_magic_tokens_forwarded $tokens:item) => {
             stringify!($tokens)
@@ -147,6 +176,47 @@ fn import_tokens_same_mod_no_ident() {
     assert!(tokens.to_string().contains("field1"));
 }
 
+#[cfg(feature = "proc_support")]
+#[test]
+fn import_tokens_private_fn_body() {
+    #[export_tokens(private)]
+    struct FnLocalStruct {
+        field: bool,
+    }
+
+    import_tokens!(let tokens = FnLocalStruct);
+    assert_eq!(
+        tokens.to_string(),
+        "struct FnLocalStruct { field : bool , }"
+    );
+}
+
+#[cfg(feature = "proc_support")]
+#[test]
+fn import_tokens_local() {
+    import_tokens!(let tokens = local_exports::LocalExportedStruct);
+    assert!(tokens.to_string().contains("local_field : u16"));
+}
+
+#[cfg(feature = "proc_support")]
+#[test]
+fn import_tokens_versioned() {
+    import_tokens!(let tokens = external_crate::VersionedStruct, version = 2);
+    assert!(tokens.to_string().contains("pub field : u32"));
+}
+
+#[cfg(feature = "proc_support")]
+#[test]
+fn import_tokens_typed() {
+    use macro_magic::__private::syn;
+
+    import_tokens!(let item: syn::ItemStruct = external_crate::VersionedStruct, version = 2);
+    assert_eq!(item.ident, "VersionedStruct");
+    assert_eq!(item.fields.len(), 1);
+    import_tokens!(let item: syn::DeriveInput = external_crate::TaggedUser);
+    assert_eq!(item.ident, "TaggedUser");
+}
+
 #[cfg(feature = "proc_support")]
 #[test]
 fn import_tokens_same_mod_ident() {
@@ -174,6 +244,74 @@ fn import_tokens_different_mod_ident() {
     );
 }
 
+mod private_exports {
+    use macro_magic::*;
+
+    #[export_tokens(private)]
+    struct HiddenStruct {
+        field: u8,
+    }
+
+    mod child {
+        use test_macros::example_tokens_proc;
+
+        #[test]
+        fn test_export_tokens_private_module() {
+            let tokens = example_tokens_proc!(super::HiddenStruct);
+            assert_eq!(tokens, "struct HiddenStruct { field : u8, }");
+        }
+    }
+}
+
+mod local_exports {
+    use macro_magic::*;
+
+    pub const LOCAL_EXPORTED_STRUCT_LINE: u32 = line!() + 2;
+    #[export_tokens(local)]
+    struct LocalExportedStruct {
+        local_field: u16,
+    }
+
+    #[export_tokens(local, LocalSnake)]
+    struct LocalRenamed {}
+}
+
+#[combine_structs(local_exports::LocalExportedStruct)]
+struct CombinedWithLocal {
+    field: u8,
+}
+
+#[test]
+fn test_export_tokens_local() {
+    let tokens = example_tokens_proc!(local_exports::LocalSnake);
+    assert_eq!(tokens, "struct LocalRenamed {}");
+    let _combined = CombinedWithLocal {
+        field: 1,
+        local_field: 2,
+    };
+
+    macro_rules! local_receiver {
+        (__private_macro_magic_tokens_forwarded $tokens:item) => {
+            stringify!($tokens)
+        };
+    }
+
+    let result = forward_tokens!(local_exports::LocalExportedStruct, local_receiver);
+    assert_eq!(result, "struct LocalExportedStruct { local_field : u16, }");
+}
+
+#[test]
+fn test_export_tokens_private_fn_body() {
+    #[export_tokens(private, FnLocal)]
+    fn fn_local_item() -> u32 {
+        5
+    }
+
+    let tokens = example_tokens_proc!(FnLocal);
+    assert_eq!(tokens, "fn fn_local_item() -> u32 { 5 }");
+    assert_eq!(fn_local_item(), 5);
+}
+
 #[export_tokens]
 fn a_random_fn() {
     println!("hey");
@@ -184,7 +322,7 @@ fn println_inside_fn_current_file() {
     let tokens = example_tokens_proc!(a_random_fn);
     assert_eq!(
         tokens.to_string(),
-        "fn a_random_fn() { println! (\"hey\") ; }"
+        "fn a_random_fn() { println! (\"hey\"); }"
     );
 }
 
@@ -193,7 +331,7 @@ fn println_inside_fn_external_file() {
     let tokens = example_tokens_proc!(external_file::external_fn_with_println);
     assert_eq!(
         tokens.to_string(),
-        "fn external_fn_with_println() { println! (\"testing\") ; }"
+        "fn external_fn_with_println() { println! (\"testing\"); }"
     );
 }
 
@@ -202,7 +340,7 @@ fn macro_calls_inside_fn_external_crate() {
     let tokens = example_tokens_proc!(external_crate::external_fn_with_local_macro_calls);
     assert_eq!(
         tokens.to_string(),
-        "fn external_fn_with_local_macro_calls() -> u32 { another_macro! () ; 1337 }"
+        "fn external_fn_with_local_macro_calls() -> u32 { another_macro! (); 1337 }"
     );
 }
 
@@ -230,6 +368,116 @@ fn test_combine_structs_example() {
     };
 }
 
+#[export_tokens]
+struct ExtraStruct {
+    qux: char,
+}
+
+#[merge_structs(ExternalStruct, ExtraStruct, local_exports::LocalExportedStruct)]
+struct MergedStruct {
+    biz: bool,
+}
+
+#[named_field_list("MERGED_FIELD_NAMES" => ExtraStruct, ExternalStruct)]
+struct NamedFieldList;
+
+#[test]
+fn test_import_tokens_attr_multiple_paths() {
+    let merged = MergedStruct {
+        biz: true,
+        foo: 42,
+        bar: 19,
+        fizz: -22,
+        qux: 'q',
+        local_field: 7,
+    };
+    assert!(merged.biz);
+    assert_eq!(merged.qux, 'q');
+    assert_eq!(
+        MergedStruct::MERGED_FROM,
+        "ExternalStruct, ExtraStruct, local_exports :: LocalExportedStruct"
+    );
+    assert_eq!(MERGED_FIELD_NAMES, ["qux", "foo", "bar", "fizz"]);
+    let _ = NamedFieldList;
+}
+
+macro_rules! struct_kinds {
+    (
+        __private_macro_magic_tokens_forwarded
+        $($(#[$attr:meta])* $vis:vis struct $name:ident $body:tt)*
+    ) => {
+        #[allow(clippy::enum_variant_names)]
+        #[derive(Debug, PartialEq)]
+        enum StructKind {
+            $($name),*
+        }
+
+        const STRUCT_KINDS: &[StructKind] = &[$(StructKind::$name),*];
+    };
+}
+
+forward_tokens!(
+    [
+        ExternalStruct,
+        external_crate::VersionedStruct,
+        local_exports::LocalExportedStruct
+    ],
+    struct_kinds
+);
+
+#[test]
+fn test_forward_tokens_batch() {
+    assert_eq!(
+        STRUCT_KINDS,
+        [
+            StructKind::ExternalStruct,
+            StructKind::VersionedStruct,
+            StructKind::LocalExportedStruct
+        ]
+    );
+
+    macro_rules! receiver {
+        (__private_macro_magic_tokens_forwarded $($tokens:item)*) => {
+            [$(stringify!($tokens)),*]
+        };
+    }
+
+    let result = forward_tokens!([ExtraStruct], receiver);
+    assert_eq!(result, ["struct ExtraStruct { qux : char, }"]);
+    let result = forward_tokens!(
+        [
+            external_crate::PrefixedStruct,
+            external_crate::PrefixedStruct
+        ],
+        receiver,
+        prefix = "__fw_export_"
+    );
+    assert_eq!(result.len(), 2);
+    assert_eq!(result[0], result[1]);
+}
+
+#[derive(ForeignFields)]
+#[from(external_crate::VersionedStruct)]
+struct DerivedFromVersioned {
+    _local: u8,
+}
+
+#[derive(ForeignFields)]
+#[from(ExternalStruct)]
+enum DerivedFromLocal {}
+
+#[test]
+fn test_import_tokens_derive() {
+    assert_eq!(DerivedFromVersioned::FOREIGN_FIELDS, ["field"]);
+    assert_eq!(
+        DerivedFromVersioned::FOREIGN_SOURCE,
+        "external_crate :: VersionedStruct"
+    );
+    assert_eq!(DerivedFromVersioned::FOREIGN_VERSION, 2);
+    let _ = DerivedFromVersioned { _local: 1 };
+    assert_eq!(DerivedFromLocal::FOREIGN_FIELDS, ["foo", "bar", "fizz"]);
+}
+
 #[test]
 fn test_require_example() {
     require!(external_crate::an_external_module);
@@ -263,6 +511,305 @@ fn test_foreign_path_emission() {
     );
 }
 
+#[test]
+fn test_foreign_path_emission_proc() {
+    let (path, doc, item) = emit_foreign_path_proc!(external_crate::an_external_function);
+    assert_eq!(path, "external_crate :: an_external_function");
+    assert_eq!(
+        doc,
+        "Generated from `external_crate :: an_external_function` defined in `external_crate`"
+    );
+    assert_eq!(
+        item,
+        "fn an_external_function(my_num : u32) -> u32 { my_num + 33 }"
+    );
+    let (path, _, _) = emit_foreign_path_proc!(local_exports::LocalExportedStruct);
+    assert_eq!(path, "local_exports :: LocalExportedStruct");
+}
+
+#[emit_source_info(external_crate::some_submodule::AnExternalTraitImpl)]
+struct StructWithSourceInfo {}
+
+#[test]
+fn test_source_info_attr() {
+    // inline modules aren't reflected in the best-effort module path
+    assert_eq!(source_module_path, "external_crate");
+    assert_eq!(source_file, external_crate::FILE);
+    assert_eq!(
+        source_line,
+        external_crate::some_submodule::AN_EXTERNAL_TRAIT_IMPL_LINE
+    );
+    assert_eq!(source_tokens_hash.len(), 16);
+    assert_eq!(imported_source_files, [external_crate::FILE]);
+}
+
+mod multi_source_info {
+    use test_macros::emit_source_info;
+
+    #[emit_source_info(external_crate::some_submodule::AnExternalTraitImpl, crate::LionStruct)]
+    struct StructWithMultiSourceInfo {}
+
+    #[test]
+    fn test_source_info_attr_multiple_items() {
+        // `__source_info` describes the first item, `__imported_items` all of them
+        assert_eq!(source_file, external_crate::FILE);
+        assert_eq!(
+            imported_source_files,
+            [external_crate::FILE, "tests/tests.rs"]
+        );
+    }
+}
+
+#[test]
+fn test_source_info_proc() {
+    let (crate_name, crate_version, module_path, file, line, column) =
+        source_info_proc!(external_crate::an_external_function);
+    assert_eq!(crate_name, "external_crate");
+    assert_eq!(crate_version, "0.1.0");
+    assert_eq!(module_path, "external_crate");
+    assert_eq!(file, external_crate::FILE);
+    assert_eq!(
+        (line, column),
+        (external_crate::AN_EXTERNAL_FUNCTION_LINE, 0)
+    );
+
+    let (crate_name, crate_version, module_path, file, line, _) =
+        source_info_proc!(external_file::external_fn_with_println);
+    assert_eq!(crate_name, "tests");
+    assert_eq!(crate_version, env!("CARGO_PKG_VERSION"));
+    assert_eq!(module_path, "tests::external_file");
+    assert_eq!(file, "tests/external_file.rs");
+    assert_eq!(line, external_file::EXTERNAL_FN_WITH_PRINTLN_LINE);
+
+    let (_, _, module_path, file, line, _) = source_info_proc!(local_exports::LocalExportedStruct);
+    assert_eq!(module_path, "tests");
+    assert_eq!(file, file!());
+    assert_eq!(line, local_exports::LOCAL_EXPORTED_STRUCT_LINE);
+}
+
+assert_tokens_hash!(external_crate::an_external_function, "5d0789bb097eb168");
+
+#[versioned_attr(external_crate::VersionedStruct)]
+struct StructWithVersionedImport {}
+
+#[test]
+fn test_versioned_imports() {
+    let tokens = versioned_proc!(external_crate::VersionedStruct);
+    assert_eq!(tokens, "pub struct VersionedStruct { pub field : u32, }");
+    assert_eq!(
+        versioned_item_str,
+        "pub struct VersionedStruct { pub field : u32, }"
+    );
+}
+
+#[fw_export_tokens]
+struct LocalPrefixedStruct {
+    field: u8,
+}
+
+#[test]
+fn test_export_tokens_custom_prefix() {
+    let tokens = prefixed_proc!(external_crate::PrefixedStruct);
+    assert_eq!(tokens, "pub struct PrefixedStruct { pub field : bool, }");
+    let tokens = prefixed_proc!(LocalPrefixedStruct);
+    assert_eq!(tokens, "struct LocalPrefixedStruct { field : u8, }");
+}
+
+#[cfg(feature = "proc_support")]
+#[test]
+fn import_tokens_custom_prefix() {
+    import_tokens!(let tokens = external_crate::PrefixedStruct, prefix = "__fw_export_");
+    assert!(tokens.to_string().contains("pub field : bool"));
+}
+
+/// Docs that are stripped by the alias
+#[normalized_export_tokens]
+struct NormalizedStruct {
+    field: u8,
+}
+
+#[test]
+fn test_export_tokens_alias_transform() {
+    let tokens = example_tokens_proc!(NormalizedStruct);
+    assert_eq!(
+        tokens,
+        "#[allow(dead_code)] struct NormalizedStruct { field : u8, }"
+    );
+}
+
+#[export_template(Name, Ty, name = double_template)]
+fn Name(value: Ty) -> Ty {
+    value * 2
+}
+
+instantiate_template!(crate::double_template, Name = double_u32, Ty = u32);
+instantiate_template!(crate::double_template, Ty = i64, Name = double_i64);
+
+#[export_template(Name, Amount, name = scaled_template)]
+const Name: u32 = Amount * 2;
+
+instantiate_template!(crate::scaled_template, Name = SCALED, Amount = 3 + 4);
+
+use external_crate::Storage;
+
+struct TemplateUser {
+    id: u32,
+}
+
+instantiate_template!(external_crate::Storage, T = TemplateUser, Field = id);
+
+#[test]
+fn test_instantiate_template() {
+    assert_eq!(double_u32(21), 42);
+    assert_eq!(double_i64(-4), -8);
+    assert_eq!(SCALED, 14);
+    assert_eq!(TemplateUser { id: 7 }.key(), 7);
+}
+
+#[test]
+fn test_export_fragment() {
+    let timeout = emit_fragment!(external_crate::default_timeout);
+    assert_eq!(timeout.as_secs(), 30);
+    let id: emit_fragment!(external_crate::id_type) = 5;
+    assert_eq!(id, 5u64);
+}
+
+#[cfg(feature = "proc_support")]
+#[test]
+fn import_fragment_typed() {
+    use macro_magic::__private::quote::ToTokens;
+
+    import_fragment!(let timeout: expr = external_crate::default_timeout);
+    assert_eq!(
+        timeout.to_token_stream().to_string(),
+        "core :: time :: Duration :: from_secs (30)"
+    );
+    import_fragment!(let id_type: ty = external_crate::id_type);
+    assert_eq!(id_type.to_token_stream().to_string(), "u64");
+    import_fragment!(let setup: stmts = external_crate::setup_stmts);
+    assert_eq!(setup.len(), 2);
+    import_fragment!(
+        let prefixed: ty = external_crate::prefixed_id_type,
+        prefix = "__fw_export_"
+    );
+    assert_eq!(prefixed.to_token_stream().to_string(), "u32");
+}
+
+macro_rules! config_module {
+    ($name:ident) => {
+        pub mod $name {
+            use macro_magic::*;
+
+            #[export_tokens]
+            pub struct Config;
+        }
+    };
+}
+
+config_module!(config_a);
+config_module!(config_b);
+
+#[test]
+fn test_export_tokens_same_item_in_two_modules() {
+    assert_eq!(example_tokens_proc!(config_a::Config), "pub struct Config;");
+    assert_eq!(example_tokens_proc!(config_b::Config), "pub struct Config;");
+}
+
+pub struct Wrapper<T>(T);
+
+#[export_tokens]
+impl<T> Wrapper<T> {
+    pub fn new(value: T) -> Self {
+        Wrapper(value)
+    }
+}
+
+#[test]
+fn test_export_tokens_derived_names() {
+    let tokens = example_tokens_proc!(external_crate::display_for_tagged_user);
+    assert!(tokens.starts_with("impl core :: fmt :: Display for TaggedUser"));
+    let tokens = example_tokens_proc!(impl_wrapper);
+    assert!(tokens.starts_with("impl < T > Wrapper < T >"));
+}
+
+#[test]
+fn test_export_tokens_multiple_names() {
+    let tokens = example_tokens_proc!(external_crate::RenamedStruct);
+    assert_eq!(
+        tokens,
+        "pub struct RenamedStruct { pub renamed_field : u8, }"
+    );
+    assert_eq!(example_tokens_proc!(external_crate::LegacyStruct), tokens);
+    assert_eq!(
+        example_tokens_proc!(external_crate::legacy_struct_v1),
+        tokens
+    );
+}
+
+#[test]
+fn test_export_tokens_recursive() {
+    let tokens = example_tokens_proc!(external_crate::schemas::ChildStruct);
+    assert_eq!(tokens, "pub struct ChildStruct { pub id : u64, }");
+    let tokens = example_tokens_proc!(external_crate::schemas::ChildEnum);
+    assert_eq!(tokens, "pub enum ChildEnum { First, Second, }");
+    let tokens = example_tokens_proc!(external_crate::schemas::nested::NestedStruct);
+    assert_eq!(tokens, "pub struct NestedStruct;");
+    let tokens = example_tokens_proc!(external_crate::schemas::nested);
+    assert_eq!(tokens, "pub mod nested { pub struct NestedStruct; }");
+    let tokens = example_tokens_proc!(external_crate::schemas);
+    assert!(tokens.starts_with("pub mod schemas"));
+    assert!(tokens.contains("pub struct ChildStruct"));
+    assert!(!tokens.contains("__export_tokens_tt"));
+}
+
+mod user_schemas {
+    macro_magic::export_tokens_file!("tests/schemas/user.rs");
+}
+
+mod more_user_schemas {
+    macro_magic::export_tokens_file!("tests/schemas/user.rs");
+}
+
+#[test]
+fn test_export_tokens_file() {
+    let tokens = example_tokens_proc!(user_schemas::User).replace('\n', " ");
+    assert_eq!(
+        tokens,
+        "pub struct User { pub id : u64, pub name : String, pub roles : Vec < Role > , }"
+    );
+    let tokens = example_tokens_proc!(user_schemas::Role).replace('\n', " ");
+    assert_eq!(
+        tokens,
+        "#[derive(PartialEq)] pub enum Role { Admin, Member, }"
+    );
+    let tokens = example_tokens_proc!(user_schemas::UserIndex).replace('\n', " ");
+    assert_eq!(tokens, "pub type UserIndex = HashMap < u64, User > ;");
+    let (_, _, module_path, file, line, column) = source_info_proc!(user_schemas::User);
+    assert_eq!(module_path, "tests");
+    assert_eq!(file, "tests/schemas/user.rs");
+    assert_eq!((line, column), (6, 11));
+    let (_, _, _, _, line, column) = source_info_proc!(user_schemas::Role);
+    assert_eq!((line, column), (19, 9));
+}
+
+#[test]
+fn test_export_tokens_file_from_two_modules() {
+    assert_eq!(
+        example_tokens_proc!(user_schemas::User),
+        example_tokens_proc!(more_user_schemas::User)
+    );
+    assert_eq!(
+        source_info_proc!(user_schemas::Role),
+        source_info_proc!(more_user_schemas::Role)
+    );
+}
+
+#[test]
+fn test_assert_tokens_hash() {
+    assert_tokens_hash!(external_crate::an_external_function, "5d0789bb097eb168");
+    assert_tokens_hash!(SomeStruct, "726b53dc53a8f614");
+}
+
 #[export_tokens_no_emit]
 fn _non_compiling_fn() {
     compile_error!("this should not compile ");
@@ -284,3 +831,92 @@ fn test_export_tokens_no_emit_exportation() {
     );
     assert_eq!(_non_compiling_fn(), 3);
 }
+
+#[export_tokens(cfg, local)]
+struct LocalCfgStruct {
+    #[cfg(test)]
+    in_tests: u8,
+    #[cfg(not(test))]
+    not_in_tests: u8,
+}
+
+#[strip_cfg_attr(external_crate::CfgStruct)]
+struct StructWithStrippedImport {}
+
+mod local_cfg {
+    use test_macros::strip_cfg_attr;
+
+    #[strip_cfg_attr(crate::LocalCfgStruct)]
+    pub struct StructWithLocalStrippedImport {}
+
+    #[test]
+    fn test_export_tokens_cfg_local() {
+        assert_eq!(stripped_item_str, "struct LocalCfgStruct { in_tests : u8 }");
+    }
+}
+
+#[test]
+fn test_export_tokens_cfg() {
+    assert_eq!(
+        stripped_item_str,
+        "pub struct CfgStruct { pub always : u32, pub not_in_tests : u32 }"
+    );
+    let tokens = example_tokens_proc!(external_crate::CfgStruct);
+    assert!(tokens.contains("#[cfg(test)] pub only_in_tests : u32"));
+}
+
+#[test]
+fn test_export_tokens_projections() {
+    assert_eq!(
+        example_tokens_proc!(external_crate::ProjectedAccount),
+        "pub struct ProjectedAccount { pub id : u64, }"
+    );
+    assert_eq!(
+        example_tokens_proc!(external_crate::projected_function),
+        "pub fn projected_function(n : u32) -> u32 {}"
+    );
+    assert_eq!(external_crate::projected_function(2), 6);
+}
+
+#[test]
+fn test_export_tokens_tags_and_meta() {
+    assert_eq!(
+        export_tags_proc!(external_crate::TaggedUser),
+        ("db,api", "users", "4")
+    );
+}
+
+pub mod grouped {
+    use macro_magic::*;
+
+    #[export_tokens]
+    pub struct GroupedStruct {
+        pub a: u8,
+    }
+
+    export_group!(local_types = [crate::grouped::GroupedStruct, crate::LocalCfgStruct]);
+}
+
+#[test]
+fn test_export_group() {
+    assert_eq!(
+        group_item_names!(external_crate::api_types),
+        "TaggedUser,VersionedStruct,ChildStruct"
+    );
+    assert_eq!(
+        group_item_names!(grouped::local_types),
+        "GroupedStruct,LocalCfgStruct"
+    );
+    assert_eq!(group_item_names!(external_crate::TaggedUser), "TaggedUser");
+}
+
+#[group_names_attr(external_crate::api_types, grouped::local_types)]
+struct StructWithGroupNames {}
+
+#[test]
+fn test_export_group_attr() {
+    assert_eq!(
+        group_names,
+        "TaggedUser,VersionedStruct,ChildStruct,GroupedStruct,LocalCfgStruct"
+    );
+}
@@ -0,0 +1,36 @@
+//! Compile-fail tests. Each binary in `tests/ui/src/bin` is built separately and must fail to
+//! compile with every error message marked with `//~ ERROR` in its source.
+
+use std::{fs, path::Path, process::Command};
+
+#[test]
+fn ui() {
+    let ui_dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/ui");
+    let target_dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("ui");
+    let mut cases: Vec<_> = fs::read_dir(ui_dir.join("src/bin"))
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .collect();
+    cases.sort();
+    for case in cases {
+        let name = case.file_stem().unwrap().to_str().unwrap();
+        let source = fs::read_to_string(&case).unwrap();
+        let output = Command::new(env!("CARGO"))
+            .args(["build", "--quiet", "--bin", name])
+            .current_dir(&ui_dir)
+            .env("CARGO_TARGET_DIR", &target_dir)
+            .output()
+            .unwrap();
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(!output.status.success(), "`{name}` compiled successfully");
+        for (_, expected) in source
+            .lines()
+            .filter_map(|line| line.split_once("//~ ERROR "))
+        {
+            assert!(
+                stderr.contains(expected.trim()),
+                "`{name}` didn't fail with `{expected}`:\n{stderr}"
+            );
+        }
+    }
+}
@@ -0,0 +1,8 @@
+use test_macros::fw_export_tokens;
+
+#[fw_export_tokens(prefix = "__other_")]
+pub struct PrefixedStruct;
+//~ ERROR `prefix = "__other_"` can't be specified, since this attribute always uses the prefix "__fw_export_"
+//~ ERROR --> src/bin/alias_prefix_override.rs:3:29
+
+fn main() {}
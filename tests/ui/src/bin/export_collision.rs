@@ -0,0 +1,10 @@
+use macro_magic::*;
+
+#[export_tokens]
+pub struct MyCoolName;
+
+#[export_tokens]
+pub fn my_cool_name() {}
+//~ ERROR the name `__export_tokens_tt_my_cool_name_is_exported_more_than_once_in_this_module` is defined multiple times
+
+fn main() {}
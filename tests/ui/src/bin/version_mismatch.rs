@@ -0,0 +1,9 @@
+use test_macros::versioned_proc;
+
+fn main() {
+    // `versioned_proc` is declared via `#[import_tokens_proc(version = 2..)]`, while
+    // `an_external_function` doesn't specify a version
+    let _tokens = versioned_proc!(external_crate::an_external_function);
+    //~ ERROR version 2.. of the exported item defined at
+    //~ ERROR is required, but it does not specify a version, i.e. `#[export_tokens(version = 1)]`
+}